@@ -1,180 +1,545 @@
 use std::{
-    env,
-    path::PathBuf,
-    process::Command,
+    env, fs,
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
 };
 
 use anyhow::{anyhow, Context, Result};
+use futures_util::StreamExt;
 use url::Url;
 
 use crate::{
-    cache::Cache,
-    crawl::{self, CrawlOptions},
+    cache::{strip_html_ext, Cache},
+    cli::{self, SUBCOMMANDS},
+    completions::{self, Shell},
+    config::{insert_cli_flag, Config, Effective, Settings, SiteAlias},
+    crawl::{self, CrawlOptions, DiscoverMode, ImageMode},
+    external,
     http::HttpOptions,
-    urlspec::{SourceSpec, UrlPattern},
-    util::{is_url_like, split_comma_separated},
+    llms::MarkdownOptions,
+    render::{RenderOptions, RenderWhen},
+    sanitize::SanitizeOptions,
+    sections::{self, AnchorStyle},
+    tables::TableMode,
+    urlspec::{SourceSpec, UrlPattern, UrlRegex},
+    util::{filename_safe, is_url_like, parse_url_list, split_comma_separated, split_command_line},
 };
 
+/// Page size threshold for `--split-sections` without `--split-min-kib`.
+const DEFAULT_SPLIT_MIN_KIB: usize = 256;
+
+/// Per-file placeholders in host args with `--each`.
+const PLACEHOLDERS: &[&str] = &["{}", "{url}", "{title}", "{stem}", "{name}", "{dir}"];
+
+/// Exit status with `--each` when a host command failed, as with xargs.
+const EACH_FAILED_EXIT: i32 = 123;
+
+/// A resolved cache file or directory and the URL it was cached from.
+#[derive(Debug, Clone)]
+struct Target {
+    path: PathBuf,
+    url: String,
+    /// `{stem}`: see `url_stem`.
+    stem: String,
+}
+
+impl Target {
+    /// A cached page or subtree directory; `root` is the crawl root URL, or the page's own URL
+    /// for a single page.
+    fn new(path: PathBuf, url: String, root: &str) -> Self {
+        let stem = url_stem(&url, root);
+        Self { path, url, stem }
+    }
+
+    /// A section file of the page `page_stem` names.
+    fn section(path: PathBuf, url: String, page_stem: &str) -> Self {
+        let file_stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let stem = format!("{page_stem}-{file_stem}");
+        Self { path, url, stem }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct GgOptions {
     refresh: bool,
     cache_dir: Option<PathBuf>,
     parallelism: Option<usize>,
     max_depth: Option<usize>,
+    max_pages: Option<usize>,
     use_sitemap: bool,
+    allow_hosts: Vec<String>,
+    discover: DiscoverMode,
+    use_feeds: bool,
     timeout_secs: Option<u64>,
     connect_timeout_secs: Option<u64>,
     max_body_mib: Option<usize>,
     user_agent: Option<String>,
     cmd_override: Option<String>,
+    each: bool,
+    jobs: Option<usize>,
     print_paths: bool,
     force_crawl: bool,
     force_page: bool,
+    sanitize: bool,
+    sanitize_disable: Vec<String>,
+    sanitize_rules: Option<PathBuf>,
+    images: ImageMode,
+    tables: TableMode,
+    heading_anchors: AnchorStyle,
+    split_sections: Option<bool>,
+    split_min_kib: Option<usize>,
+    guess_code_lang: bool,
+    strip_prompts: bool,
+    converters: Option<PathBuf>,
+    render_cmd: Option<String>,
+    render_when: RenderWhen,
+    prefer_markdown: bool,
 }
 
-impl Default for GgOptions {
-    fn default() -> Self {
-        Self {
-            refresh: false,
-            cache_dir: None,
-            parallelism: None,
-            max_depth: None,
-            use_sitemap: true,
-            timeout_secs: None,
-            connect_timeout_secs: None,
-            max_body_mib: None,
-            user_agent: None,
-            cmd_override: None,
-            print_paths: false,
-            force_crawl: false,
-            force_page: false,
-        }
+impl GgOptions {
+    /// Options from settings resolved across the config layers and the command line.
+    fn from_settings(settings: &Effective) -> Result<Self> {
+        let path = |key| settings.get(key).map(PathBuf::from);
+        let text = |key| settings.get(key).map(str::to_string);
+        Ok(Self {
+            refresh: settings.switch("refresh"),
+            cache_dir: path("cache_dir"),
+            parallelism: settings.parse("parallelism")?,
+            max_depth: settings.parse("max_depth")?,
+            max_pages: settings.parse("max_pages")?,
+            use_sitemap: settings.switch("sitemap"),
+            allow_hosts: settings.list("allow_host"),
+            discover: settings.parse("discover")?.unwrap_or_default(),
+            use_feeds: settings.switch("feeds"),
+            timeout_secs: settings.parse("timeout")?,
+            connect_timeout_secs: settings.parse("connect_timeout")?,
+            max_body_mib: settings.parse("max_body_mib")?,
+            user_agent: text("user_agent"),
+            cmd_override: text("cmd"),
+            each: settings.switch("each"),
+            jobs: settings.parse::<usize>("jobs")?.map(|j| j.max(1)),
+            print_paths: settings.switch("print_paths"),
+            force_crawl: settings.switch("crawl"),
+            force_page: settings.switch("page"),
+            sanitize: settings.switch("sanitize"),
+            sanitize_disable: settings.list("sanitize_disable"),
+            sanitize_rules: path("sanitize_rules"),
+            images: settings.parse("images")?.unwrap_or_default(),
+            tables: settings.parse("tables")?.unwrap_or_default(),
+            heading_anchors: settings.parse("heading_anchors")?.unwrap_or_default(),
+            split_sections: settings
+                .is_set("split_sections")
+                .then(|| settings.switch("split_sections")),
+            split_min_kib: settings.parse("split_min_kib")?,
+            guess_code_lang: settings.switch("guess_code_lang"),
+            strip_prompts: settings.switch("strip_prompts"),
+            converters: path("converters"),
+            render_cmd: text("render_cmd"),
+            render_when: settings.parse("render")?.unwrap_or_default(),
+            prefer_markdown: settings.switch("prefer_markdown"),
+        })
     }
 }
 
 pub async fn run() -> Result<()> {
     let argv: Vec<String> = env::args().skip(1).collect();
-    let (opts, remaining) = parse_gg_flags(argv)?;
+    let ParsedArgs {
+        help,
+        config,
+        cli,
+        positional,
+        dashdash,
+    } = parse_gg_flags(argv)?;
+    let config = Config::load(config.as_deref())?;
+    let mut opts = GgOptions::from_settings(&config.effective(None, &cli))?;
+
+    // A subcommand is the first word, unless that word comes after `--`.
+    let subcommand = positional
+        .first()
+        .filter(|_| dashdash != Some(0))
+        .and_then(|w| SUBCOMMANDS.iter().find(|s| s.name == w))
+        .map(|s| s.name);
 
-    if remaining.is_empty() {
-        print_help();
-        return Err(anyhow!("missing URL"));
+    if help {
+        match subcommand.and_then(cli::subcommand_help) {
+            Some(help) if subcommand != Some("help") => eprint!("{help}"),
+            _ => print_help(),
+        }
+        return Ok(());
     }
 
-    let first_url_idx = remaining
-        .iter()
-        .position(|t| is_url_like(t))
-        .ok_or_else(|| anyhow!("missing URL"))?;
+    // `gg __complete PREFIX` is the hidden hook the completion scripts use for URLs.
+    if dashdash != Some(0) && positional.first().map(String::as_str) == Some("__complete") {
+        let prefix = positional.get(1).map(String::as_str).unwrap_or("");
+        for candidate in complete_sources(&config, &opts, prefix) {
+            println!("{candidate}");
+        }
+        return Ok(());
+    }
 
-    let host_part = &remaining[..first_url_idx];
-    let url_part = &remaining[first_url_idx..];
+    // A word that names a subcommand but whose arguments do not fit it is a host command when
+    // it is one (`gg ls -la URL`).
+    let parsed = match subcommand {
+        Some(name) => match parse_subcommand(name, &positional[1..], dashdash.map(|i| i - 1)) {
+            Ok(sub) => Some(sub),
+            Err(_)
+                if is_executable_in_path(name)
+                    && positional[1..].iter().any(|t| is_source_token(t, &config)) =>
+            {
+                None
+            }
+            Err(err) => return Err(err),
+        },
+        None => None,
+    };
 
-    let (host_cmd, host_args) = resolve_host_invocation(host_part, opts.cmd_override.clone())?;
+    match parsed {
+        Some(Subcommand::Help(name)) => {
+            match name.and_then(cli::subcommand_help) {
+                Some(help) => eprint!("{help}"),
+                None => print_help(),
+            }
+            Ok(())
+        }
+        Some(Subcommand::Completions(shell)) => {
+            print!("{}", completions::script(shell));
+            Ok(())
+        }
+        Some(Subcommand::Man) => {
+            print!("{}", cli::man_page());
+            Ok(())
+        }
+        Some(Subcommand::ConfigShow) => {
+            print!("{}", config.show(&cli));
+            Ok(())
+        }
+        Some(Subcommand::Cache(cmd)) => run_cache_command(&opts, cmd),
+        Some(Subcommand::Run { sources, cmd, args }) => {
+            let targets = resolve_targets(&config, &opts, &cli, sources).await?;
+            if opts.print_paths {
+                print_paths(&targets);
+                return Ok(());
+            }
+            if opts.each {
+                return exec_each(cmd, args, &targets, opts.jobs).await;
+            }
+            exec_host_command(cmd, args, &targets)
+        }
+        Some(Subcommand::Paths { name, sources }) => {
+            // fetch, crawl and paths cache their sources and print the paths.
+            if name == "fetch" {
+                opts.force_page = true;
+            } else if name == "crawl" {
+                (opts.force_crawl, opts.force_page) = (true, false);
+            }
+            let targets = resolve_targets(&config, &opts, &cli, sources).await?;
+            print_paths(&targets);
+            Ok(())
+        }
+        None => {
+            if positional.is_empty() {
+                print_help();
+                return Err(anyhow!("missing URL"));
+            }
 
-    let cache = Cache::new(opts.cache_dir.clone())?;
+            // `-` is a host argument (`gg diff - URL`) unless it follows a source or ends the
+            // command line.
+            let first_url_idx = positional
+                .iter()
+                .position(|t| is_source_token(t, &config))
+                .or_else(|| {
+                    (positional.last().map(String::as_str) == Some("-"))
+                        .then(|| positional.len() - 1)
+                })
+                .ok_or_else(|| anyhow!("missing URL"))?;
 
-    let mut http_opts = HttpOptions::default();
-    if let Some(ua) = opts.user_agent.clone() {
-        http_opts.user_agent = ua;
-    }
-    if let Some(secs) = opts.timeout_secs {
-        http_opts.timeout = std::time::Duration::from_secs(secs);
+            let host_part = &positional[..first_url_idx];
+            let url_part = &positional[first_url_idx..];
+
+            let (host_cmd, host_args) =
+                resolve_host_invocation(host_part, opts.cmd_override.clone())?;
+            let targets = resolve_targets(&config, &opts, &cli, url_part).await?;
+            if opts.print_paths {
+                print_paths(&targets);
+                return Ok(());
+            }
+            if opts.each {
+                return exec_each(&host_cmd, &host_args, &targets, opts.jobs).await;
+            }
+            exec_host_command(&host_cmd, &host_args, &targets)
+        }
     }
-    if let Some(secs) = opts.connect_timeout_secs {
-        http_opts.connect_timeout = std::time::Duration::from_secs(secs);
+}
+
+/// A subcommand with its arguments checked.
+enum Subcommand<'a> {
+    Help(Option<&'a str>),
+    Completions(Shell),
+    Man,
+    ConfigShow,
+    Cache(CacheCommand<'a>),
+    Run {
+        sources: &'a [String],
+        cmd: &'a String,
+        args: &'a [String],
+    },
+    /// `fetch`, `crawl` or `paths`: cache the sources and print their paths.
+    Paths {
+        name: &'static str,
+        sources: &'a [String],
+    },
+}
+
+enum CacheCommand<'a> {
+    Dir,
+    List,
+    ClearAll,
+    Clear(&'a [String]),
+}
+
+/// Check the words after subcommand `name`; `dashdash` is the index of the first word after
+/// `--` among them.
+fn parse_subcommand<'a>(
+    name: &'static str,
+    args: &'a [String],
+    dashdash: Option<usize>,
+) -> Result<Subcommand<'a>> {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    Ok(match (name, words.as_slice()) {
+        ("help", []) => Subcommand::Help(None),
+        ("help", [sub]) if cli::subcommand_help(sub).is_some() => Subcommand::Help(Some(sub)),
+        ("help", [sub]) => return Err(anyhow!("unknown subcommand: {sub}")),
+        ("completions", [shell]) => Subcommand::Completions(shell.parse()?),
+        ("completions", _) => {
+            return Err(anyhow!(
+                "usage: gg [GG_FLAGS] completions bash | zsh | fish"
+            ))
+        }
+        ("man", []) => Subcommand::Man,
+        ("config", ["show"]) => Subcommand::ConfigShow,
+        ("config", _) => return Err(anyhow!("usage: gg [GG_FLAGS] config show")),
+        ("cache", ["dir"]) => Subcommand::Cache(CacheCommand::Dir),
+        ("cache", ["list"]) => Subcommand::Cache(CacheCommand::List),
+        ("cache", ["clear", "--all"]) => Subcommand::Cache(CacheCommand::ClearAll),
+        ("cache", ["clear", urls @ ..]) if !urls.is_empty() => {
+            Subcommand::Cache(CacheCommand::Clear(&args[1..]))
+        }
+        ("cache", _) => {
+            return Err(anyhow!(
+                "usage: gg [GG_FLAGS] cache dir | list | clear (URL... | --all)"
+            ))
+        }
+        ("run", _) => {
+            let dd = dashdash
+                .filter(|&i| i < args.len())
+                .ok_or_else(|| anyhow!("usage: gg [GG_FLAGS] run SOURCE... -- CMD [ARGS...]"))?;
+            Subcommand::Run {
+                sources: subcommand_sources(name, &args[..dd])?,
+                cmd: &args[dd],
+                args: &args[dd + 1..],
+            }
+        }
+        ("fetch" | "crawl" | "paths", _) => Subcommand::Paths {
+            name,
+            sources: subcommand_sources(name, args)?,
+        },
+        _ => return Err(anyhow!("usage: gg [GG_FLAGS] {name}")),
+    })
+}
+
+/// Words a subcommand takes as sources: at least one, and no unknown flags.
+fn subcommand_sources<'a>(name: &str, words: &'a [String]) -> Result<&'a [String]> {
+    if let Some(flag) = words.iter().find(|w| w.starts_with('-') && *w != "-") {
+        return Err(anyhow!("unknown flag for gg {name}: {flag}"));
     }
-    if let Some(mib) = opts.max_body_mib {
-        http_opts.max_body_bytes = mib * 1024 * 1024;
+    if words.is_empty() {
+        return Err(anyhow!("missing URL (usage: gg [GG_FLAGS] {name} ...)"));
     }
+    Ok(words)
+}
 
-    let parallelism = opts
-        .parallelism
-        .unwrap_or_else(default_parallelism)
-        .clamp(1, 512);
-
-    let crawl_opts = CrawlOptions {
-        http: http_opts,
-        parallelism,
-        max_depth: opts.max_depth,
-        use_sitemap: opts.use_sitemap,
-    };
+/// Fetch or crawl the sources in `url_part` as needed and return their cached files.
+/// `cli` holds the settings given on the command line, which site aliases do not override.
+async fn resolve_targets(
+    config: &Config,
+    opts: &GgOptions,
+    cli: &Settings,
+    url_part: &[String],
+) -> Result<Vec<Target>> {
+    let cache = Cache::new(opts.cache_dir.clone())?;
+    let crawl_opts = crawl_options(opts)?;
 
     // Parse URL arguments into source specs.
-    let mut sources: Vec<SourceSpec> = Vec::new();
-    for tok in url_part {
-        for piece in split_comma_separated(tok) {
-            sources.push(parse_source(&piece, opts.force_crawl, opts.force_page)?);
+    let mut sources: Vec<(SourceSpec, Option<&SiteAlias>)> = Vec::new();
+    let mut exclusions: Vec<UrlPattern> = Vec::new();
+    for piece in expand_source_tokens(url_part, config)? {
+        for (source, alias) in config.resolve(&piece) {
+            match parse_source(&source, opts.force_crawl, opts.force_page)? {
+                SourceSpec::Exclude(pat) => exclusions.push(pat),
+                spec => sources.push((spec, alias)),
+            }
         }
     }
+    if sources.is_empty() {
+        return Err(anyhow!("missing URL (only exclusions given)"));
+    }
+    let excluded = |url: &str| exclusions.iter().any(|p| p.matches_url_string(url));
 
     // Resolve sources into local file/dir paths.
-    let mut local_targets: Vec<PathBuf> = Vec::new();
+    let mut local_targets: Vec<Target> = Vec::new();
 
     // Shared client for single-page fetches.
     let client_all = crate::http::build_client_all(&crawl_opts.http)?;
 
-    for spec in sources {
+    for (spec, alias) in sources {
+        // `[host."..."]` config sections, then the alias's own settings, apply to this source.
+        let host_opts;
+        let alias_opts;
+        let mut source_opts = &crawl_opts;
+        if let Some(host) = spec
+            .root()
+            .host_str()
+            .filter(|h| config.has_host_overrides(h))
+        {
+            let parsed = GgOptions::from_settings(&config.effective(Some(host), cli))?;
+            host_opts = crawl_options(&parsed)?;
+            source_opts = &host_opts;
+        }
+        if let Some(a) = alias {
+            alias_opts = a.apply(
+                source_opts,
+                cli.contains_key("max_depth"),
+                cli.contains_key("user_agent"),
+            );
+            source_opts = &alias_opts;
+        }
+        let source_client;
+        let (crawl_opts, client_all) = if std::ptr::eq(source_opts, &crawl_opts) {
+            (&crawl_opts, &client_all)
+        } else {
+            source_client = crate::http::build_client_all(&source_opts.http)?;
+            (source_opts, &source_client)
+        };
         match spec {
             SourceSpec::Page(url) => {
+                if excluded(url.as_str()) {
+                    continue;
+                }
                 let url_for_err = url.clone();
-                let path = crawl::ensure_page_cached(
-                    &cache,
-                    &client_all,
-                    &crawl_opts,
-                    url,
-                    opts.refresh,
-                )
-                .await
-                .with_context(|| format!("failed to fetch {url_for_err}"))?;
-                local_targets.push(path);
+                let path =
+                    crawl::ensure_page_cached(&cache, client_all, crawl_opts, url, opts.refresh)
+                        .await
+                        .with_context(|| format!("failed to fetch {url_for_err}"))?;
+                // A split page is its index plus the section files.
+                let url = url_for_err.to_string();
+                let page = Target::new(path, url.clone(), &url);
+                local_targets.extend(
+                    sections::section_files(&page.path)
+                        .into_iter()
+                        .map(|path| Target::section(path, url.clone(), &page.stem)),
+                );
+                local_targets.push(page);
             }
             SourceSpec::CrawlRoot(root) => {
                 let root_for_err = root.clone();
-                let manifest = crawl::ensure_subtree_cached(&cache, &crawl_opts, root, opts.refresh)
+                let manifest = crawl::ensure_subtree_cached(&cache, crawl_opts, root, opts.refresh)
                     .await
                     .with_context(|| format!("failed to crawl {root_for_err}"))?;
+                // With exclusions, extra hosts or --each, list the selected pages instead of the directory.
+                if opts.each || !exclusions.is_empty() || !crawl_opts.allow_hosts.is_empty() {
+                    push_manifest_pages(
+                        &cache,
+                        &manifest,
+                        |url| !excluded(url),
+                        &mut local_targets,
+                    );
+                    continue;
+                }
                 // For a crawl root, pass the directory itself to the host command.
                 let dir = cache.subtree_dir(&root_for_err)?;
                 if manifest.pages.is_empty() {
                     // Still pass dir; user can see emptiness.
                 }
-                local_targets.push(dir);
+                local_targets.push(Target::new(
+                    dir,
+                    manifest.root_url.clone(),
+                    &manifest.root_url,
+                ));
             }
             SourceSpec::Pattern(pat) => {
-                let manifest =
-                    crawl::ensure_subtree_cached(&cache, &crawl_opts, pat.root.clone(), opts.refresh)
-                    .await
-                    .with_context(|| format!("failed to crawl {root}", root = pat.root))?;
+                // A host wildcard widens the crawl to the matching hosts.
+                let wildcard_opts;
+                let pattern_opts = match &pat.host_pattern {
+                    Some(host) => {
+                        let mut o = crawl_opts.clone();
+                        o.allow_hosts.push(host.clone());
+                        wildcard_opts = o;
+                        &wildcard_opts
+                    }
+                    None => crawl_opts,
+                };
+                let manifest = crawl::ensure_subtree_cached(
+                    &cache,
+                    pattern_opts,
+                    pat.root.clone(),
+                    opts.refresh,
+                )
+                .await
+                .with_context(|| format!("failed to crawl {root}", root = pat.root))?;
 
-                // Fast path: a whole-subtree pattern like .../**/*.
-                if pat.is_subtree_pattern() {
-                    local_targets.push(cache.subtree_dir(&pat.root)?);
+                // Fast path: a whole-subtree pattern like .../**/*, all on the root's host.
+                if pat.is_subtree_pattern()
+                    && !opts.each
+                    && exclusions.is_empty()
+                    && pattern_opts.allow_hosts.is_empty()
+                {
+                    local_targets.push(Target::new(
+                        cache.subtree_dir(&pat.root)?,
+                        manifest.root_url.clone(),
+                        &manifest.root_url,
+                    ));
                     continue;
                 }
 
-                for page in &manifest.pages {
-                    if pat.matches_url_string(&page.url) {
-                        local_targets.push(cache.root().join(&page.cache_path));
-                    }
-                }
+                push_manifest_pages(
+                    &cache,
+                    &manifest,
+                    |url| pat.matches_url_string(url) && !excluded(url),
+                    &mut local_targets,
+                );
+            }
+            SourceSpec::Regex(re) => {
+                let manifest =
+                    crawl::ensure_subtree_cached(&cache, crawl_opts, re.root.clone(), opts.refresh)
+                        .await
+                        .with_context(|| format!("failed to crawl {root}", root = re.root))?;
+                push_manifest_pages(
+                    &cache,
+                    &manifest,
+                    |url| re.matches_url_string(url) && !excluded(url),
+                    &mut local_targets,
+                );
             }
+            SourceSpec::Exclude(_) => unreachable!("exclusions are collected above"),
         }
     }
 
     // Deduplicate targets (stable order).
-    local_targets = dedupe_paths(local_targets);
+    Ok(dedupe_targets(local_targets))
+}
 
-    if opts.print_paths {
-        for p in &local_targets {
-            println!("{}", p.display());
-        }
-        return Ok(());
+fn print_paths(targets: &[Target]) {
+    for t in targets {
+        println!("{}", t.path.display());
     }
+}
 
-    // Execute the host command.
-    let mut cmd = Command::new(&host_cmd);
-    cmd.args(&host_args);
-    cmd.args(local_targets.iter().map(|p| p.as_os_str()));
+/// Run the host command with the targets appended and exit with its status.
+fn exec_host_command(host_cmd: &str, host_args: &[String], targets: &[Target]) -> Result<()> {
+    let mut cmd = Command::new(host_cmd);
+    cmd.args(host_args);
+    cmd.args(targets.iter().map(|t| t.path.as_os_str()));
 
     let status = cmd
         .status()
@@ -189,7 +554,333 @@ pub async fn run() -> Result<()> {
     }
 }
 
+/// `--each`: run the host command once per target, at most `jobs` at a time, with the
+/// placeholders in `host_args` filled in. Exits with 123 if any run failed.
+async fn exec_each(
+    host_cmd: &str,
+    host_args: &[String],
+    targets: &[Target],
+    jobs: Option<usize>,
+) -> Result<()> {
+    let jobs = jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+    // Like xargs, append the path unless the args say where it goes.
+    let append = !host_args
+        .iter()
+        .any(|a| PLACEHOLDERS.iter().any(|p| a.contains(p)));
+    let mut runs = futures_util::stream::iter(targets.iter().map(|target| {
+        let mut cmd = tokio::process::Command::new(host_cmd);
+        cmd.args(host_args.iter().map(|a| expand_placeholders(a, target)));
+        if append {
+            cmd.arg(&target.path);
+        }
+        // Concurrent runs cannot share gg's stdin.
+        cmd.stdin(Stdio::null());
+        async move {
+            cmd.status()
+                .await
+                .with_context(|| format!("failed to execute host command: {host_cmd}"))
+        }
+    }))
+    .buffer_unordered(jobs);
+
+    let mut failed = false;
+    while let Some(status) = runs.next().await {
+        failed |= !status?.success();
+    }
+    if failed {
+        std::process::exit(EACH_FAILED_EXIT);
+    }
+    Ok(())
+}
+
+/// `arg` with the `--each` placeholders replaced for `target`; other braces are kept.
+fn expand_placeholders(arg: &str, target: &Target) -> String {
+    let mut out = String::with_capacity(arg.len());
+    let mut rest = arg;
+    while let Some(i) = rest.find('{') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        match PLACEHOLDERS.iter().find(|p| rest.starts_with(**p)) {
+            Some(p) => {
+                out.push_str(&placeholder_value(p, target));
+                rest = &rest[p.len()..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn placeholder_value(placeholder: &str, target: &Target) -> String {
+    let path = &target.path;
+    match placeholder {
+        "{url}" => target.url.clone(),
+        // The first heading of the cached Markdown, else the stem; either way usable as a file
+        // name.
+        "{title}" => fs::read_to_string(path)
+            .ok()
+            .and_then(|md| sections::markdown_title(&md))
+            .map(|title| filename_safe(&title))
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| target.stem.clone()),
+        "{stem}" => target.stem.clone(),
+        "{name}" => path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+        "{dir}" => path.parent().unwrap_or(Path::new("")).display().to_string(),
+        _ => path.display().to_string(),
+    }
+}
+
+/// The page's URL path below the crawl root `root` with `-` between segments and without an
+/// HTML extension (`guide-setup` for `ROOT/guide/setup.html`), so pages in different
+/// directories get different stems. The root itself is named by its last segment; pages on
+/// other hosts by their whole path.
+fn url_stem(url: &str, root: &str) -> String {
+    let (Ok(url), Ok(root)) = (Url::parse(url), Url::parse(root)) else {
+        return "index".to_string();
+    };
+    let segments = |u: &Url| -> Vec<String> {
+        u.path_segments()
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let page = segments(&url);
+    let root_segments = segments(&root);
+    let below = match page.strip_prefix(root_segments.as_slice()) {
+        _ if url.host() != root.host() => &page[..],
+        Some([]) | None => page.last().map(std::slice::from_ref).unwrap_or_default(),
+        Some(rest) => rest,
+    };
+    let stem = match below.split_last() {
+        Some((last, dirs)) => {
+            let mut parts = dirs.to_vec();
+            parts.push(strip_html_ext(last));
+            filename_safe(&parts.join("-"))
+        }
+        None => String::new(),
+    };
+    if stem.is_empty() {
+        "index".to_string()
+    } else {
+        stem
+    }
+}
+
+/// `gg cache dir|list|clear`: inspect or prune the cache.
+fn run_cache_command(opts: &GgOptions, cmd: CacheCommand) -> Result<()> {
+    let cache = Cache::new(opts.cache_dir.clone())?;
+    match cmd {
+        CacheCommand::Dir => println!("{}", cache.root().display()),
+        CacheCommand::List => {
+            for m in crawl::cached_manifests(&cache) {
+                println!("{}\t{} pages", m.root_url, m.pages.len());
+            }
+        }
+        CacheCommand::ClearAll => {
+            let sites = cache.root().join("sites");
+            if sites.exists() {
+                fs::remove_dir_all(&sites)
+                    .with_context(|| format!("failed to remove {}", sites.display()))?;
+            }
+        }
+        CacheCommand::Clear(urls) => {
+            for u in urls {
+                let url = Url::parse(u).with_context(|| format!("invalid URL: {u}"))?;
+                // A crawl root or site root is a directory, anything else a page file.
+                let path = if url.path().ends_with('/') {
+                    cache.subtree_dir(&url)?
+                } else {
+                    cache.page_path(&url)?
+                };
+                if path.is_dir() {
+                    fs::remove_dir_all(&path)
+                } else if path.is_file() {
+                    fs::remove_file(&path)
+                } else {
+                    eprintln!("gg: not cached: {url}");
+                    continue;
+                }
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+                eprintln!("gg: removed {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Completion candidates for a source word: `@alias` names and cached URLs.
+fn complete_sources(config: &Config, opts: &GgOptions, prefix: &str) -> Vec<String> {
+    if prefix.starts_with('@') {
+        return config
+            .aliases
+            .keys()
+            .map(|name| format!("@{name}"))
+            .filter(|a| a.starts_with(prefix))
+            .collect();
+    }
+    match Cache::new(opts.cache_dir.clone()) {
+        Ok(cache) => completions::cached_urls(&cache, prefix),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Crawl options for the flags in `opts`.
+fn crawl_options(opts: &GgOptions) -> Result<CrawlOptions> {
+    let mut http_opts = HttpOptions::default();
+    if let Some(ua) = opts.user_agent.clone() {
+        http_opts.user_agent = ua;
+    }
+    if let Some(secs) = opts.timeout_secs {
+        http_opts.timeout = std::time::Duration::from_secs(secs);
+    }
+    if let Some(secs) = opts.connect_timeout_secs {
+        http_opts.connect_timeout = std::time::Duration::from_secs(secs);
+    }
+    if let Some(mib) = opts.max_body_mib {
+        http_opts.max_body_bytes = mib * 1024 * 1024;
+    }
+
+    let parallelism = opts
+        .parallelism
+        .unwrap_or_else(default_parallelism)
+        .clamp(1, 512);
+
+    let mut sanitize_opts = SanitizeOptions {
+        enabled: opts.sanitize,
+        ..Default::default()
+    };
+    if let Some(path) = &opts.sanitize_rules {
+        sanitize_opts.load_rules_file(path)?;
+    }
+    for rule in &opts.sanitize_disable {
+        sanitize_opts.disable(rule)?;
+    }
+
+    Ok(CrawlOptions {
+        http: http_opts,
+        parallelism,
+        max_depth: opts.max_depth,
+        max_pages: opts.max_pages,
+        use_sitemap: opts.use_sitemap,
+        allow_hosts: opts.allow_hosts.clone(),
+        discover: opts.discover,
+        use_feeds: opts.use_feeds,
+        sanitize: sanitize_opts,
+        images: opts.images,
+        tables: opts.tables,
+        heading_anchors: opts.heading_anchors,
+        split_min_bytes: match (opts.split_sections, opts.split_min_kib) {
+            (Some(false), _) => None,
+            (Some(true), kib) => Some(kib.unwrap_or(DEFAULT_SPLIT_MIN_KIB) * 1024),
+            // `--split-min-kib` alone implies `--split-sections`.
+            (None, kib) => kib.map(|kib| kib * 1024),
+        },
+        guess_code_language: opts.guess_code_lang,
+        strip_prompts: opts.strip_prompts,
+        converters: match &opts.converters {
+            Some(path) => external::load_converters(path)?,
+            None => Vec::new(),
+        },
+        render: RenderOptions {
+            command: opts.render_cmd.as_deref().map(split_command_line),
+            when: opts.render_when,
+            ..Default::default()
+        },
+        markdown: if opts.prefer_markdown {
+            MarkdownOptions::enabled()
+        } else {
+            MarkdownOptions::default()
+        },
+        max_age_secs: None,
+    })
+}
+
+/// Push the cached files (page plus any sections) of the manifest pages whose URL is selected.
+fn push_manifest_pages(
+    cache: &Cache,
+    manifest: &crawl::CrawlManifest,
+    select: impl Fn(&str) -> bool,
+    out: &mut Vec<Target>,
+) {
+    for page in &manifest.pages {
+        if select(&page.url) {
+            let target = Target::new(
+                cache.root().join(&page.cache_path),
+                page.url.clone(),
+                &manifest.root_url,
+            );
+            let page_stem = target.stem.clone();
+            out.push(target);
+            for section in &page.sections {
+                out.push(Target::section(
+                    cache.root().join(&section.cache_path),
+                    section.url.clone(),
+                    &page_stem,
+                ));
+            }
+        }
+    }
+}
+
+/// Whether a token starts the sources: a URL, a site alias, `-` (list on stdin) or `@file`
+/// (list in a file).
+fn is_source_token(t: &str, config: &Config) -> bool {
+    is_url_like(t)
+        || config.is_alias_token(t)
+        || is_url_like(t.trim_start_matches('!'))
+        || UrlRegex::is_regex_source(t)
+        || t.strip_prefix('@').is_some_and(|p| Path::new(p).is_file())
+}
+
+/// Expand source tokens into URLs and globs: comma-separated lists, `@file` and `-` lists.
+fn expand_source_tokens(tokens: &[String], config: &Config) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    let mut read_stdin = false;
+    for tok in tokens {
+        if tok.starts_with('@') && config.is_alias_token(tok) {
+            // `@alias` wins over a file of the same name; `@./name` reads the file.
+            out.push(tok.clone());
+        } else if tok == "-" {
+            // Stdin can only be read once; a repeated `-` adds nothing.
+            if std::mem::replace(&mut read_stdin, true) {
+                continue;
+            }
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .context("failed to read URL list from stdin")?;
+            out.extend(parse_url_list(&text));
+        } else if let Some(path) = tok.strip_prefix('@') {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("failed to read URL list: {path}"))?;
+            out.extend(parse_url_list(&text));
+        } else if UrlRegex::is_regex_source(tok) {
+            // Commas are regex syntax (`{2,3}`), not list separators.
+            out.push(tok.clone());
+        } else {
+            out.extend(split_comma_separated(tok));
+        }
+    }
+    Ok(out)
+}
+
 fn parse_source(s: &str, force_crawl: bool, force_page: bool) -> Result<SourceSpec> {
+    if UrlRegex::is_regex_source(s) {
+        return Ok(SourceSpec::Regex(UrlRegex::new(s)?));
+    }
+    if let Some(excluded) = s.strip_prefix('!') {
+        return Ok(SourceSpec::Exclude(UrlPattern::exclusion(excluded)?));
+    }
     if !force_page && UrlPattern::has_glob(s) {
         return Ok(SourceSpec::Pattern(UrlPattern::new(s)?));
     }
@@ -213,10 +904,10 @@ fn default_parallelism() -> usize {
     (cores * 8).max(16)
 }
 
-fn dedupe_paths(mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+fn dedupe_targets(mut targets: Vec<Target>) -> Vec<Target> {
     let mut seen = std::collections::HashSet::new();
-    paths.retain(|p| seen.insert(p.clone()));
-    paths
+    targets.retain(|t| seen.insert(t.path.clone()));
+    targets
 }
 
 fn resolve_host_invocation(host_part: &[String], cmd_override: Option<String>) -> Result<(String, Vec<String>)> {
@@ -268,155 +959,77 @@ fn is_executable_in_path(cmd: &str) -> bool {
     false
 }
 
-fn parse_gg_flags(argv: Vec<String>) -> Result<(GgOptions, Vec<String>)> {
-    let mut opts = GgOptions::default();
-    let mut remaining: Vec<String> = Vec::new();
+/// gg's flags and the remaining words: subcommand, sources and host command.
+#[derive(Debug)]
+struct ParsedArgs {
+    help: bool,
+    /// `--config FILE`.
+    config: Option<PathBuf>,
+    /// The settings the flags give, which take precedence over the config.
+    cli: Settings,
+    /// Words that are not gg flags, in order; those after `--` are kept as is.
+    positional: Vec<String>,
+    /// Index in `positional` of the first word after `--`.
+    dashdash: Option<usize>,
+}
+
+fn parse_gg_flags(argv: Vec<String>) -> Result<ParsedArgs> {
+    let mut help = false;
+    let mut config = None;
+    let mut cli = Settings::new();
+    let mut positional: Vec<String> = Vec::new();
+    let mut dashdash = None;
 
     let mut i = 0;
     while i < argv.len() {
         let t = &argv[i];
+        i += 1;
 
         if t == "--" {
-            remaining.extend(argv[i + 1..].iter().cloned());
+            dashdash = Some(positional.len());
+            positional.extend(argv[i..].iter().cloned());
             break;
         }
 
-        match t.as_str() {
-            "-h" | "--help" => {
-                print_help();
-                std::process::exit(0);
+        let (name, inline) = match t.split_once('=') {
+            Some((name, v)) if name.starts_with("--") => (name, Some(v)),
+            _ => (t.as_str(), None),
+        };
+        // Other words, including unknown flags, belong to the host command.
+        let Some((spec, on)) = cli::find_flag(name) else {
+            positional.push(t.clone());
+            continue;
+        };
+        let value = match (spec.value, inline) {
+            (Some(_), Some(v)) => v,
+            (Some(_), None) => {
+                i += 1;
+                argv.get(i - 1)
+                    .ok_or_else(|| anyhow!("{} requires a value", spec.long))?
             }
-            "-V" | "--version" => {
+            (None, Some(_)) => return Err(anyhow!("{name} takes no value")),
+            (None, None) => "",
+        };
+        match spec.long {
+            "--help" => help = true,
+            "--version" => {
                 println!("gg {}", env!("CARGO_PKG_VERSION"));
                 std::process::exit(0);
             }
-            "--refresh" => {
-                opts.refresh = true;
-                i += 1;
-            }
-            "--cache-dir" => {
-                let v = argv
-                    .get(i + 1)
-                    .ok_or_else(|| anyhow!("--cache-dir requires a value"))?;
-                opts.cache_dir = Some(PathBuf::from(v));
-                i += 2;
-            }
-            "--parallelism" => {
-                let v = argv
-                    .get(i + 1)
-                    .ok_or_else(|| anyhow!("--parallelism requires a value"))?;
-                opts.parallelism = Some(v.parse::<usize>().context("invalid --parallelism")?);
-                i += 2;
-            }
-            "--max-depth" => {
-                let v = argv
-                    .get(i + 1)
-                    .ok_or_else(|| anyhow!("--max-depth requires a value"))?;
-                let n = v.parse::<usize>().context("invalid --max-depth")?;
-                opts.max_depth = Some(n);
-                i += 2;
-            }
-            "--no-sitemap" => {
-                opts.use_sitemap = false;
-                i += 1;
-            }
-            "--sitemap" => {
-                opts.use_sitemap = true;
-                i += 1;
-            }
-            "--timeout" => {
-                let v = argv
-                    .get(i + 1)
-                    .ok_or_else(|| anyhow!("--timeout requires a value"))?;
-                opts.timeout_secs = Some(v.parse::<u64>().context("invalid --timeout")?);
-                i += 2;
-            }
-            "--connect-timeout" => {
-                let v = argv
-                    .get(i + 1)
-                    .ok_or_else(|| anyhow!("--connect-timeout requires a value"))?;
-                opts.connect_timeout_secs =
-                    Some(v.parse::<u64>().context("invalid --connect-timeout")?);
-                i += 2;
-            }
-            "--max-body-mib" => {
-                let v = argv
-                    .get(i + 1)
-                    .ok_or_else(|| anyhow!("--max-body-mib requires a value"))?;
-                opts.max_body_mib = Some(v.parse::<usize>().context("invalid --max-body-mib")?);
-                i += 2;
-            }
-            "--user-agent" => {
-                let v = argv
-                    .get(i + 1)
-                    .ok_or_else(|| anyhow!("--user-agent requires a value"))?;
-                opts.user_agent = Some(v.to_string());
-                i += 2;
-            }
-            "--cmd" => {
-                let v = argv
-                    .get(i + 1)
-                    .ok_or_else(|| anyhow!("--cmd requires a value"))?;
-                opts.cmd_override = Some(v.to_string());
-                i += 2;
-            }
-            "--print-paths" => {
-                opts.print_paths = true;
-                i += 1;
-            }
-            "--crawl" => {
-                opts.force_crawl = true;
-                i += 1;
-            }
-            "--page" => {
-                opts.force_page = true;
-                i += 1;
-            }
-            _ => {
-                remaining.push(t.clone());
-                i += 1;
-            }
+            "--config" => config = Some(PathBuf::from(value)),
+            _ => insert_cli_flag(&mut cli, spec, value, on),
         }
     }
 
-    Ok((opts, remaining))
+    Ok(ParsedArgs {
+        help,
+        config,
+        cli,
+        positional,
+        dashdash,
+    })
 }
 
 fn print_help() {
-    let help = r#"gg - filesystem-like interface to the web (Rust)
-
-USAGE:
-  gg [GG_FLAGS] [HOST_CMD [HOST_ARGS...]] URL_OR_GLOB [URL_OR_GLOB ...]
-
-DATA SOURCES:
-  - A single URL (no globs) fetches just that page and caches it as Markdown.
-  - A URL ending with '/' is treated as a crawl root (subtree crawl).
-  - A URL containing glob characters (* ? [) is treated as a pattern; gg crawls
-    the pattern's root and then selects only matching pages.
-  - A single argument may be a comma-separated list of URLs.
-
-DEFAULT HOST COMMAND:
-  If HOST_CMD is omitted, gg defaults to 'rg'.
-
-GG FLAGS:
-  --refresh               Re-fetch / re-crawl even if cache exists
-  --cache-dir <DIR>       Override cache directory (also: GG_CACHE_DIR)
-  --parallelism <N>       Concurrent fetches while crawling
-  --max-depth <N>         Limit crawl depth (0-based); omitted = unlimited
-  --no-sitemap            Disable sitemap seeding
-  --timeout <SECS>        Request timeout
-  --connect-timeout <SECS>Connect timeout
-  --max-body-mib <N>      Maximum bytes per HTML page (MiB)
-  --user-agent <UA>       Override User-Agent
-  --cmd <CMD>             Force host command (disambiguation)
-  --print-paths           Print resolved local paths instead of running command
-  --crawl                 Force subtree crawl for non-glob URLs
-  --page                  Force single-page mode even if URL ends with '/'
-
-EXAMPLES:
-  gg -i "pattern" https://example.com/docs/**/*
-  gg tree https://example.com/docs/**/*
-  gg cat https://example.com/docs/getting-started
-"#;
-    eprintln!("{help}");
+    eprint!("{}", cli::help());
 }
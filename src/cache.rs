@@ -104,6 +104,49 @@ impl Cache {
         Ok(dir.join(filename))
     }
 
+    /// Local path for an image referenced by `page`, mirrored under the page's site as
+    /// `.gg/assets/<image host>/<image path>`.
+    pub fn asset_path(&self, page: &Url, asset: &Url) -> Result<PathBuf> {
+        let host = asset
+            .host_str()
+            .ok_or_else(|| anyhow!("URL has no host: {asset}"))?;
+        let mut path = self
+            .site_dir(page)?
+            .join(".gg")
+            .join("assets")
+            .join(host_port_dirname(asset, host));
+
+        let segments: Vec<&str> = asset.path().split('/').filter(|s| !s.is_empty()).collect();
+        let (last, dirs) = match segments.split_last() {
+            Some((last, dirs)) if !asset.path().ends_with('/') => (*last, dirs),
+            _ => ("index", &segments[..]),
+        };
+        for seg in dirs {
+            path = path.join(sanitize_component(seg));
+        }
+
+        let mut filename = sanitize_component(last);
+        if let Some(q) = asset.query() {
+            let digest = blake3::hash(q.as_bytes());
+            // Keep the extension last so viewers still recognize the file type.
+            let (stem, ext) = match filename.rfind('.') {
+                Some(i) if i > 0 => (filename[..i].to_string(), filename[i..].to_string()),
+                _ => (filename.clone(), String::new()),
+            };
+            filename = format!("{stem}__q{}{ext}", &digest.to_hex()[..8]);
+        }
+        Ok(path.join(filename))
+    }
+
+    /// File recording how the page for `url` was converted, kept as
+    /// `<site>/.gg/conversions/<page path>` so a page converted with other options is refetched.
+    pub fn conversion_path(&self, url: &Url) -> Result<PathBuf> {
+        let site_dir = self.site_dir(url)?;
+        let page = self.page_path(url)?;
+        let rel = page.strip_prefix(&site_dir).unwrap_or(&page);
+        Ok(site_dir.join(".gg").join("conversions").join(rel))
+    }
+
     pub fn manifest_path_for_subtree(&self, root: &Url) -> Result<PathBuf> {
         let dir = self.subtree_dir(root)?;
         Ok(dir.join(".gg").join("manifest.json"))
@@ -164,7 +207,8 @@ fn host_port_dirname(url: &Url, host: &str) -> String {
     host_l
 }
 
-fn strip_html_ext(s: &str) -> String {
+/// `s` without a trailing `.html`, `.htm` or `.xhtml`.
+pub fn strip_html_ext(s: &str) -> String {
     for ext in [".html", ".htm", ".xhtml"] {
         if s.to_ascii_lowercase().ends_with(ext) {
             return s[..s.len() - ext.len()].to_string();
@@ -0,0 +1,644 @@
+//! Command-line definitions: gg's flags and subcommands, and the help generated from them.
+//!
+//! `FLAGS` is the one table of gg's options: the config file keys, their defaults and
+//! completions all come from it (see `config`).
+
+use std::fmt::Write as _;
+
+use crate::{
+    crawl::{DiscoverMode, ImageMode},
+    render::RenderWhen,
+    sections::AnchorStyle,
+    tables::TableMode,
+};
+
+/// What a flag takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagKind {
+    /// No value; a setting switch also has a `--no-` form.
+    Switch,
+    /// `--flag VALUE`.
+    Value,
+    /// `--flag PATH`; relative paths in a config file are relative to that file.
+    Path,
+    /// `--flag A,B`; config files may use an array, repeated flags add up.
+    List,
+}
+
+/// A gg flag, and the setting it stands for.
+#[derive(Debug)]
+pub struct FlagSpec {
+    pub long: &'static str,
+    pub short: Option<&'static str>,
+    /// Value placeholder, e.g. `N` in `--parallelism <N>`.
+    pub value: Option<&'static str>,
+    pub kind: FlagKind,
+    /// Accepted values, for flags that take one of a fixed set.
+    pub choices: &'static [&'static str],
+    /// Config file key (`max_depth` for `--max-depth`); empty for flags that are no setting.
+    pub key: &'static str,
+    /// Value when nothing sets it; empty when unset by default.
+    pub default: &'static str,
+    /// Whether `[host."..."]` config sections may override it.
+    pub per_host: bool,
+    /// Help text; continuation lines are indented under the first.
+    pub help: &'static str,
+}
+
+/// A crawl setting switch, off by default; it also has a `--no-` form.
+const fn flag(long: &'static str, key: &'static str, help: &'static str) -> FlagSpec {
+    FlagSpec {
+        long,
+        short: None,
+        value: None,
+        kind: FlagKind::Switch,
+        choices: &[],
+        key,
+        default: "false",
+        per_host: true,
+        help,
+    }
+}
+
+/// A crawl setting taking a value, unset by default.
+const fn opt(
+    long: &'static str,
+    value: &'static str,
+    key: &'static str,
+    help: &'static str,
+) -> FlagSpec {
+    FlagSpec {
+        long,
+        short: None,
+        value: Some(value),
+        kind: FlagKind::Value,
+        choices: &[],
+        key,
+        default: "",
+        per_host: true,
+        help,
+    }
+}
+
+const fn choice(
+    long: &'static str,
+    value: &'static str,
+    key: &'static str,
+    choices: &'static [&'static str],
+    help: &'static str,
+) -> FlagSpec {
+    FlagSpec {
+        choices,
+        // The first choice is the default.
+        default: choices[0],
+        ..opt(long, value, key, help)
+    }
+}
+
+impl FlagSpec {
+    const fn kind(self, kind: FlagKind) -> Self {
+        Self { kind, ..self }
+    }
+
+    const fn default(self, default: &'static str) -> Self {
+        Self { default, ..self }
+    }
+
+    /// A setting for the whole run, which `[host."..."]` sections may not override.
+    const fn global(self) -> Self {
+        Self {
+            per_host: false,
+            ..self
+        }
+    }
+}
+
+pub const FLAGS: &[FlagSpec] = &[
+    FlagSpec {
+        long: "--help",
+        short: Some("-h"),
+        value: None,
+        kind: FlagKind::Switch,
+        choices: &[],
+        key: "",
+        default: "",
+        per_host: false,
+        help: "Print help (gg help <SUBCOMMAND> for a subcommand)",
+    },
+    FlagSpec {
+        long: "--version",
+        short: Some("-V"),
+        value: None,
+        kind: FlagKind::Switch,
+        choices: &[],
+        key: "",
+        default: "",
+        per_host: false,
+        help: "Print version",
+    },
+    flag(
+        "--refresh",
+        "refresh",
+        "Re-fetch / re-crawl even if cache exists (pages a sitemap\nor feed reports unchanged since they were fetched are kept)",
+    )
+    .global(),
+    opt(
+        "--config",
+        "FILE",
+        "",
+        "User config file with settings and site aliases (default:\nconfig.toml in the user config directory, e.g.\n~/.config/gg/config.toml)",
+    )
+    .kind(FlagKind::Path)
+    .global(),
+    opt(
+        "--cache-dir",
+        "DIR",
+        "cache_dir",
+        "Override cache directory (also: GG_CACHE_DIR)",
+    )
+    .kind(FlagKind::Path)
+    .global(),
+    opt(
+        "--parallelism",
+        "N",
+        "parallelism",
+        "Concurrent fetches while crawling",
+    ),
+    opt(
+        "--max-depth",
+        "N",
+        "max_depth",
+        "Limit crawl depth (0-based); omitted = unlimited",
+    ),
+    opt(
+        "--max-pages",
+        "N",
+        "max_pages",
+        "Stop a crawl after N pages, highest sitemap priority first",
+    ),
+    flag("--sitemap", "sitemap", "Seed crawls from sitemaps (default)").default("true"),
+    flag(
+        "--feeds",
+        "feeds",
+        "Seed crawls from RSS/Atom feeds (default)",
+    )
+    .default("true"),
+    opt(
+        "--allow-host",
+        "H,..",
+        "allow_host",
+        "Also crawl these hosts (exact or *.example.com), under the same path",
+    )
+    .kind(FlagKind::List),
+    choice(
+        "--discover",
+        "MODE",
+        "discover",
+        DiscoverMode::CHOICES,
+        "How crawls find pages: both (default; sitemap/feed seeds plus\nlinks), sitemap (listed pages only), links (link following only)\nor none (only the given URLs)",
+    ),
+    opt("--timeout", "SECS", "timeout", "Request timeout"),
+    opt(
+        "--connect-timeout",
+        "SECS",
+        "connect_timeout",
+        "Connect timeout",
+    ),
+    opt(
+        "--max-body-mib",
+        "N",
+        "max_body_mib",
+        "Maximum bytes per HTML page (MiB)",
+    ),
+    opt("--user-agent", "UA", "user_agent", "Override User-Agent"),
+    opt(
+        "--cmd",
+        "CMD",
+        "cmd",
+        "Force host command (disambiguation)",
+    )
+    .global(),
+    flag(
+        "--sanitize",
+        "sanitize",
+        "Clean up converted Markdown with the cleanup rules (default;\n--no-sanitize caches it exactly as converted)",
+    )
+    .default("true"),
+    opt(
+        "--sanitize-disable",
+        "R,..",
+        "sanitize_disable",
+        "Disable built-in cleanup rules (frontmatter, svg, footer,\nhorizontal-rules, images, nav-links, link-blocks, copyright,\njunk-lines, copy-buttons, svg-image-marker)",
+    )
+    .kind(FlagKind::List),
+    opt(
+        "--sanitize-rules",
+        "FILE",
+        "sanitize_rules",
+        "TOML file with `disable = [..]` and extra [[rule]] regexes",
+    )
+    .kind(FlagKind::Path),
+    choice(
+        "--images",
+        "MODE",
+        "images",
+        ImageMode::CHOICES,
+        "Images in cached Markdown: drop (default), alt (keep alt/title\ntext as [Image: ...]) or download (fetch into .gg/assets/)",
+    ),
+    choice(
+        "--tables",
+        "MODE",
+        "tables",
+        TableMode::CHOICES,
+        "Table rendering: native (default), gfm (spans expanded, one-line\ncells) or rows (one `col: value; col: value` line per row)",
+    ),
+    choice(
+        "--heading-anchors",
+        "S",
+        "heading_anchors",
+        AnchorStyle::CHOICES,
+        "Keep source heading ids: none (default), attr\n(`## Usage {#usage}`) or html (`<a id=\"usage\"></a>`)",
+    ),
+    flag(
+        "--split-sections",
+        "split_sections",
+        "Split large pages at H1/H2 into page/01-section.md files",
+    ),
+    opt(
+        "--split-min-kib",
+        "N",
+        "split_min_kib",
+        "Only split pages of at least N KiB (default 256; implies\n--split-sections)",
+    ),
+    flag(
+        "--guess-code-lang",
+        "guess_code_lang",
+        "Tag code blocks that name no language with one guessed from\ntheir content",
+    ),
+    flag(
+        "--strip-prompts",
+        "strip_prompts",
+        "Remove `$ ` / `>>> ` prompt markers from terminal-session code",
+    ),
+    opt(
+        "--converters",
+        "FILE",
+        "converters",
+        "TOML file of [[converter]] commands (by host / content type) whose\nstdout replaces the built-in HTML conversion",
+    )
+    .kind(FlagKind::Path),
+    opt(
+        "--render-cmd",
+        "CMD",
+        "render_cmd",
+        "Headless-browser command printing rendered HTML for a page; gets\nthe URL as {url} (or last argument) and the fetched HTML on stdin",
+    ),
+    choice(
+        "--render",
+        "WHEN",
+        "render",
+        RenderWhen::CHOICES,
+        "When to use --render-cmd: auto (empty JavaScript shells, default)\nor always",
+    ),
+    flag(
+        "--prefer-markdown",
+        "prefer_markdown",
+        "Use Markdown published by the site when available: seed crawls\nfrom llms.txt and cache page.md / `Accept: text/markdown` responses\nas is instead of converting HTML",
+    ),
+    flag(
+        "--each",
+        "each",
+        "Run the host command once per cached file, up to --jobs at a time;\n{} (path), {url}, {title}, {stem}, {name} and {dir} in HOST_ARGS\nare replaced (the path is appended when HOST_ARGS use none)",
+    )
+    .global(),
+    opt(
+        "--jobs",
+        "N",
+        "jobs",
+        "Host commands running at once with --each (default: CPU count)",
+    )
+    .global(),
+    flag(
+        "--print-paths",
+        "print_paths",
+        "Print resolved local paths instead of running command",
+    )
+    .global(),
+    flag("--crawl", "crawl", "Force subtree crawl for non-glob URLs").global(),
+    flag(
+        "--page",
+        "page",
+        "Force single-page mode even if URL ends with '/'",
+    )
+    .global(),
+];
+
+/// A subcommand. Without one, gg runs the shorthand `gg [HOST_CMD [HOST_ARGS...]] SOURCE...`.
+#[derive(Debug)]
+pub struct SubcommandSpec {
+    pub name: &'static str,
+    /// Arguments after the name, for the usage line.
+    pub args: &'static str,
+    pub about: &'static str,
+}
+
+pub const SUBCOMMANDS: &[SubcommandSpec] = &[
+    SubcommandSpec {
+        name: "run",
+        args: "SOURCE... -- CMD [ARGS...]",
+        about: "Run CMD with ARGS and the cached files of SOURCE...; the words\nafter `--` go to CMD as is (no guessing which one is the command)",
+    },
+    SubcommandSpec {
+        name: "fetch",
+        args: "URL...",
+        about: "Fetch single pages into the cache and print their paths",
+    },
+    SubcommandSpec {
+        name: "crawl",
+        args: "ROOT...",
+        about: "Crawl subtrees into the cache and print their directories",
+    },
+    SubcommandSpec {
+        name: "paths",
+        args: "SOURCE...",
+        about: "Print the cached files SOURCE... resolves to, fetching as needed",
+    },
+    SubcommandSpec {
+        name: "cache",
+        args: "dir | list | clear (URL... | --all)",
+        about: "Show the cache directory, list cached crawl roots, or remove cached\nsites, subtrees and pages",
+    },
+    SubcommandSpec {
+        name: "config",
+        args: "show",
+        about: "Print the effective settings and where each one comes from",
+    },
+    SubcommandSpec {
+        name: "completions",
+        args: "bash | zsh | fish",
+        about: "Print a shell completion script; URLs complete from the cached crawl roots",
+    },
+    SubcommandSpec {
+        name: "man",
+        args: "",
+        about: "Print the gg(1) man page",
+    },
+    SubcommandSpec {
+        name: "help",
+        args: "[SUBCOMMAND]",
+        about: "Print help",
+    },
+];
+
+const DATA_SOURCES: &str = r#"DATA SOURCES:
+  - A single URL (no globs) fetches just that page and caches it as Markdown.
+  - A URL ending with '/' is treated as a crawl root (subtree crawl).
+  - A URL containing glob characters (* ? [) is treated as a pattern; gg crawls
+    the pattern's root and then selects only matching pages.
+  - Globs may hold alternatives, e.g. https://site/docs/{guide,reference}/**
+    (one crawl of the common root covers them all).
+  - A glob may end in query conditions, e.g. https://site/docs/**?lang=en or **?lang for
+    any value (other parameters ignored); write \? to match a literal '?' and compare the
+    query as text.
+  - The host may start with a wildcard, e.g. https://*.example.com/**; the crawl
+    starts at example.com and follows links into any matching subdomain.
+  - re:REGEX selects pages whose URL fully matches REGEX, crawling from its literal
+    prefix, e.g. 're:https://site/docs/v[2-9]/.*'; re(ROOT):REGEX sets the root.
+  - A pattern prefixed with ! excludes matching pages from all other sources,
+    e.g. '!https://site/docs/legacy/**'.
+  - A single argument may be a comma-separated list of URLs.
+  - @FILE reads URLs/globs from FILE and - reads them from stdin, one per line;
+    blank lines and # comments are ignored (e.g. `cat links.txt | gg rg -i token -`).
+    - counts as a source only after another source or as the last word, so
+    `gg diff - URL` passes it to the host command.
+  - @NAME and NAME:GLOB use a site alias from the config file, e.g. with
+    rustbook = "https://doc.rust-lang.org/book/" under [alias], `gg rg lifetimes @rustbook`
+    or `gg cat rustbook:ch10-*`. Aliases may set url, include, max_depth, user_agent, ttl.
+"#;
+
+const CONFIGURATION: &str = r#"CONFIGURATION:
+  Every GG flag can also be set, lowest precedence first, in /etc/gg/config.toml, the user
+  config file, a project-local .gg.toml (working directory or nearest parent) and GG_*
+  environment variables; command-line flags win. Keys are the long flag names with '_'
+  (parallelism = 32, sitemap = false, user_agent = "..."; env: GG_PARALLELISM=32), and
+  [host."docs.rs"] / [host."*.example.com"] sections in files override their crawl
+  settings per host (GG_* variables and flags still win).
+  A .gg.toml may not set cmd, render_cmd, converters or sanitize_rules.
+  `gg config show` prints the effective settings and where each one comes from.
+"#;
+
+const DEFAULT_HOST_COMMAND: &str = r#"DEFAULT HOST COMMAND:
+  Without a subcommand, the first word that is not a source is the host command if it
+  is an executable in PATH; otherwise (or if omitted) gg runs 'rg' with all the words.
+  Use `gg run SOURCE... -- CMD ARGS...` or --cmd when a word could be either. A first
+  word naming both a subcommand and an executable (e.g. man) runs the executable when
+  the words after it do not fit the subcommand.
+"#;
+
+const EXAMPLES: &str = r#"EXAMPLES:
+  gg -i "pattern" https://example.com/docs/**/*
+  gg tree https://example.com/docs/**/*
+  gg cat https://example.com/docs/getting-started
+  gg run https://example.com/docs/ -- rg -i test
+  gg --each pandoc {} -o {stem}.pdf https://example.com/docs/**
+  gg --max-depth=2 crawl https://example.com/docs/
+"#;
+
+/// Column where flag and subcommand descriptions start.
+const HELP_COLUMN: usize = 26;
+
+/// The full `gg --help` text.
+pub fn help() -> String {
+    let mut out = String::from("gg - filesystem-like interface to the web (Rust)\n\nUSAGE:\n");
+    out.push_str("  gg [GG_FLAGS] [HOST_CMD [HOST_ARGS...]] URL_OR_GLOB [URL_OR_GLOB ...]\n");
+    out.push_str("  gg [GG_FLAGS] SUBCOMMAND [ARGS...]\n\nSUBCOMMANDS:\n");
+    for sub in SUBCOMMANDS {
+        push_entry(&mut out, &sub.usage(), sub.about);
+    }
+    for section in [DATA_SOURCES, CONFIGURATION, DEFAULT_HOST_COMMAND] {
+        out.push('\n');
+        out.push_str(section);
+    }
+    out.push_str("\nGG FLAGS (also as --flag=VALUE; --no-FLAG turns a switch off):\n");
+    for f in FLAGS {
+        push_entry(&mut out, &f.usage(), f.help);
+    }
+    out.push('\n');
+    out.push_str(EXAMPLES);
+    out
+}
+
+/// Help for one subcommand, or `None` if there is no such subcommand.
+pub fn subcommand_help(name: &str) -> Option<String> {
+    let sub = SUBCOMMANDS.iter().find(|s| s.name == name)?;
+    let mut out = format!("USAGE:\n  gg [GG_FLAGS] {}\n\n", sub.usage());
+    for line in sub.about.lines() {
+        let _ = writeln!(out, "{line}");
+    }
+    out.push_str("\nGG_FLAGS are listed by `gg --help`.\n");
+    Some(out)
+}
+
+/// The gg(1) man page, in roff.
+pub fn man_page() -> String {
+    let mut out = format!(
+        ".TH GG 1 \"\" \"gg {}\" \"User Commands\"\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    out.push_str(".SH NAME\ngg \\- filesystem-like interface to the web\n.SH SYNOPSIS\n");
+    out.push_str("\\fBgg\\fR [\\fIGG_FLAGS\\fR] [\\fIHOST_CMD\\fR [\\fIHOST_ARGS\\fR...]] \\fIURL_OR_GLOB\\fR...\n.br\n");
+    out.push_str("\\fBgg\\fR [\\fIGG_FLAGS\\fR] \\fISUBCOMMAND\\fR [\\fIARGS\\fR...]\n");
+    out.push_str(".SH DESCRIPTION\nCaches web pages and crawled subtrees as Markdown files and runs a host command\n");
+    out.push_str("(\\fBrg\\fR by default) against them.\n.SH SUBCOMMANDS\n");
+    for sub in SUBCOMMANDS {
+        let _ = writeln!(
+            out,
+            ".TP\n\\fB{}\\fR{}\n{}",
+            sub.name,
+            roff_escape(format!(" {}", sub.args).trim_end()),
+            roff_text(sub.about)
+        );
+    }
+    for section in [DATA_SOURCES, CONFIGURATION, DEFAULT_HOST_COMMAND] {
+        push_man_section(&mut out, section);
+    }
+    out.push_str(".SH OPTIONS\n");
+    for f in FLAGS {
+        let mut names = match f.short {
+            Some(short) => format!(
+                "\\fB{}\\fR, \\fB{}\\fR",
+                roff_escape(short),
+                roff_escape(f.long)
+            ),
+            None if f.negatable() => format!("\\fB{}\\fR", roff_escape(&f.usage())),
+            None => format!("\\fB{}\\fR", roff_escape(f.long)),
+        };
+        if let Some(v) = f.value {
+            let _ = write!(names, " \\fI{}\\fR", roff_escape(v));
+        }
+        let _ = writeln!(out, ".TP\n{names}\n{}", roff_text(f.help));
+    }
+    let (_, examples) = EXAMPLES.split_once('\n').unwrap_or_default();
+    out.push_str(".SH EXAMPLES\n.nf\n");
+    for line in examples.lines() {
+        let _ = writeln!(out, "{}", roff_text(line.trim()));
+    }
+    out.push_str(".fi\n");
+    out
+}
+
+/// One `TITLE:` help section as a man page section; `  - ` items become bullets.
+fn push_man_section(out: &mut String, section: &str) {
+    let (title, body) = section.split_once('\n').unwrap_or((section, ""));
+    let _ = writeln!(out, ".SH {}", title.trim_end_matches(':'));
+    for line in body.lines() {
+        let line = line.trim();
+        if let Some(item) = line.strip_prefix("- ") {
+            let _ = writeln!(out, ".IP \\(bu 2\n{}", roff_text(item));
+        } else if !line.is_empty() {
+            let _ = writeln!(out, "{}", roff_text(line));
+        }
+    }
+}
+
+/// Help text as roff lines: escaped, continuation lines kept as filled text.
+fn roff_text(text: &str) -> String {
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            let line = roff_escape(line.trim());
+            // A leading `.` or `'` would start a roff request.
+            if line.starts_with(['.', '\'']) {
+                format!("\\&{line}")
+            } else {
+                line
+            }
+        })
+        .collect();
+    lines.join("\n")
+}
+
+fn roff_escape(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
+
+impl FlagSpec {
+    /// `-h, --help`, `--[no-]sitemap` or `--max-depth <N>`.
+    pub fn usage(&self) -> String {
+        let mut s = match self.short {
+            Some(short) => format!("{short}, {}", self.long),
+            None if self.negatable() => format!("--[no-]{}", &self.long[2..]),
+            None => self.long.to_string(),
+        };
+        if let Some(v) = self.value {
+            let _ = write!(s, " <{v}>");
+        }
+        s
+    }
+
+    /// The help text on one line.
+    pub fn summary(&self) -> String {
+        self.help.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Whether this is a setting switch, which also has a `--no-` form to turn it off.
+    pub fn negatable(&self) -> bool {
+        self.kind == FlagKind::Switch && !self.key.is_empty()
+    }
+
+    /// The `--no-` form of a negatable switch.
+    pub fn negated(&self) -> Option<String> {
+        self.negatable()
+            .then(|| format!("--no-{}", &self.long[2..]))
+    }
+}
+
+impl SubcommandSpec {
+    /// `crawl ROOT...`.
+    pub fn usage(&self) -> String {
+        format!("{} {}", self.name, self.args)
+            .trim_end()
+            .to_string()
+    }
+
+    /// The description on one line.
+    pub fn summary(&self) -> String {
+        self.about.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// The flags that stand for settings, in help order.
+pub fn settings() -> impl Iterator<Item = &'static FlagSpec> {
+    FLAGS.iter().filter(|f| !f.key.is_empty())
+}
+
+/// Look up a flag by its long, short or `--no-` name; the flag is on unless negated.
+pub fn find_flag(name: &str) -> Option<(&'static FlagSpec, bool)> {
+    FLAGS.iter().find_map(|f| {
+        if f.long == name || f.short == Some(name) {
+            Some((f, true))
+        } else if f.negated().as_deref() == Some(name) {
+            Some((f, false))
+        } else {
+            None
+        }
+    })
+}
+
+/// `  left   help` with continuation lines aligned; a long `left` gets its help on the lines below.
+fn push_entry(out: &mut String, left: &str, help: &str) {
+    let mut lines = help.lines();
+    if left.len() + 2 < HELP_COLUMN {
+        let _ = writeln!(
+            out,
+            "  {left:<w$}{}",
+            lines.next().unwrap_or(""),
+            w = HELP_COLUMN - 2
+        );
+    } else {
+        let _ = writeln!(out, "  {left}");
+    }
+    for line in lines {
+        let _ = writeln!(out, "{:w$}{line}", "", w = HELP_COLUMN);
+    }
+}
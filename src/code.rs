@@ -0,0 +1,488 @@
+//! Code-block language detection.
+//!
+//! Fence info strings are normalized to one canonical name per language (`js` → `javascript`)
+//! so cached code can be searched by language. Names come from the converter (`language-*` /
+//! `lang-*` on `<pre>`/`<code>`), from highlighter class conventions on the block and its
+//! wrappers (`hljs rust`, `highlight-source-go`, `brush: java`, `data-lang`), and optionally from
+//! a content heuristic.
+//!
+//! Code is also cleaned up: line-number gutters are dropped and, optionally, prompt markers
+//! are stripped so cached commands can be copied and grepped verbatim.
+
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use regex::Regex;
+use tl::{HTMLTag, Node, Parser};
+
+use crate::preprocess::{attr, child_tags, replace_elements, tag_name};
+
+/// Aliases seen in class names and fence info strings, mapped to canonical names.
+const ALIASES: &[(&str, &str)] = &[
+    ("bash", "bash"),
+    ("sh", "bash"),
+    ("shell", "bash"),
+    ("zsh", "bash"),
+    ("console", "bash"),
+    ("shell-session", "bash"),
+    ("shellsession", "bash"),
+    ("terminal", "bash"),
+    ("fish", "fish"),
+    ("powershell", "powershell"),
+    ("ps1", "powershell"),
+    ("pwsh", "powershell"),
+    ("bat", "batch"),
+    ("batch", "batch"),
+    ("cmd", "batch"),
+    ("c", "c"),
+    ("h", "c"),
+    ("cpp", "cpp"),
+    ("c++", "cpp"),
+    ("cc", "cpp"),
+    ("cxx", "cpp"),
+    ("hpp", "cpp"),
+    ("csharp", "csharp"),
+    ("cs", "csharp"),
+    ("c#", "csharp"),
+    ("clojure", "clojure"),
+    ("clj", "clojure"),
+    ("css", "css"),
+    ("scss", "scss"),
+    ("sass", "sass"),
+    ("less", "less"),
+    ("dart", "dart"),
+    ("diff", "diff"),
+    ("patch", "diff"),
+    ("dockerfile", "dockerfile"),
+    ("docker", "dockerfile"),
+    ("elixir", "elixir"),
+    ("ex", "elixir"),
+    ("exs", "elixir"),
+    ("erlang", "erlang"),
+    ("erl", "erlang"),
+    ("fsharp", "fsharp"),
+    ("fs", "fsharp"),
+    ("go", "go"),
+    ("golang", "go"),
+    ("graphql", "graphql"),
+    ("gql", "graphql"),
+    ("groovy", "groovy"),
+    ("gradle", "groovy"),
+    ("haskell", "haskell"),
+    ("hs", "haskell"),
+    ("html", "html"),
+    ("htm", "html"),
+    ("xhtml", "html"),
+    ("ini", "ini"),
+    ("cfg", "ini"),
+    ("java", "java"),
+    ("javascript", "javascript"),
+    ("js", "javascript"),
+    ("mjs", "javascript"),
+    ("cjs", "javascript"),
+    ("node", "javascript"),
+    ("jsx", "jsx"),
+    ("json", "json"),
+    ("jsonc", "json"),
+    ("json5", "json"),
+    ("julia", "julia"),
+    ("jl", "julia"),
+    ("kotlin", "kotlin"),
+    ("kt", "kotlin"),
+    ("kts", "kotlin"),
+    ("latex", "latex"),
+    ("tex", "latex"),
+    ("lua", "lua"),
+    ("makefile", "makefile"),
+    ("make", "makefile"),
+    ("mk", "makefile"),
+    ("markdown", "markdown"),
+    ("md", "markdown"),
+    ("nginx", "nginx"),
+    ("nix", "nix"),
+    ("objectivec", "objectivec"),
+    ("objc", "objectivec"),
+    ("ocaml", "ocaml"),
+    ("ml", "ocaml"),
+    ("perl", "perl"),
+    ("pl", "perl"),
+    ("php", "php"),
+    ("plaintext", "text"),
+    ("plain", "text"),
+    ("text", "text"),
+    ("txt", "text"),
+    ("none", "text"),
+    ("nohighlight", "text"),
+    ("protobuf", "protobuf"),
+    ("proto", "protobuf"),
+    ("python", "python"),
+    ("py", "python"),
+    ("python3", "python"),
+    ("py3", "python"),
+    ("pycon", "pycon"),
+    ("ipython", "python"),
+    ("r", "r"),
+    ("ruby", "ruby"),
+    ("rb", "ruby"),
+    ("rust", "rust"),
+    ("rs", "rust"),
+    ("scala", "scala"),
+    ("sql", "sql"),
+    ("mysql", "sql"),
+    ("postgresql", "sql"),
+    ("postgres", "sql"),
+    ("plpgsql", "sql"),
+    ("sqlite", "sql"),
+    ("swift", "swift"),
+    ("terraform", "hcl"),
+    ("tf", "hcl"),
+    ("hcl", "hcl"),
+    ("toml", "toml"),
+    ("tsx", "tsx"),
+    ("typescript", "typescript"),
+    ("ts", "typescript"),
+    ("mts", "typescript"),
+    ("vb", "vbnet"),
+    ("vbnet", "vbnet"),
+    ("vim", "vim"),
+    ("viml", "vim"),
+    ("wasm", "wasm"),
+    ("wat", "wasm"),
+    ("xml", "xml"),
+    ("svg", "xml"),
+    ("xsl", "xml"),
+    ("rss", "xml"),
+    ("yaml", "yaml"),
+    ("yml", "yaml"),
+    ("zig", "zig"),
+];
+
+/// Class tokens that mark an element as highlighted code, so a bare language token next to
+/// them (`hljs rust`, `sourceCode python`, `prettyprint lang-js`) can be trusted.
+const HIGHLIGHTER_MARKERS: &[&str] = &[
+    "hljs",
+    "sourcecode",
+    "highlight",
+    "prettyprint",
+    "syntax",
+    "chroma",
+    "codehilite",
+];
+
+/// Canonical name for a known language alias.
+pub fn canonical_language(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_ascii_lowercase();
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map(|(_, canon)| *canon)
+}
+
+/// Normalize a language name: known aliases map to their canonical name, anything else is
+/// kept lowercased.
+pub fn normalize_language(name: &str) -> String {
+    match canonical_language(name) {
+        Some(canon) => canon.to_string(),
+        None => name.trim().to_ascii_lowercase(),
+    }
+}
+
+/// Language named by an element's attributes, following common highlighter conventions.
+pub fn language_hint(attributes: &BTreeMap<String, String>) -> Option<String> {
+    for key in ["data-lang", "data-language", "lang"] {
+        if let Some(v) = attributes
+            .get(key)
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+        {
+            // `lang` is also the natural-language attribute (`lang="en"`).
+            if key != "lang" || canonical_language(v).is_some() {
+                return Some(normalize_language(v));
+            }
+        }
+    }
+    language_from_class(attributes.get("class")?)
+}
+
+/// Language named by a class attribute: `language-rust`, `lang-js`, `highlight-source-go`,
+/// `highlight-python`, `brush: java;`, or a known name next to a highlighter marker.
+pub fn language_from_class(class: &str) -> Option<String> {
+    // SyntaxHighlighter: `brush: java; gutter: false`.
+    if let Some(rest) = class.split("brush:").nth(1) {
+        let name = rest
+            .split([';', ' '])
+            .map(str::trim)
+            .find(|s| !s.is_empty())?;
+        return Some(normalize_language(name));
+    }
+
+    let tokens: Vec<String> = class
+        .split_whitespace()
+        .map(|t| t.to_ascii_lowercase())
+        .collect();
+    for t in &tokens {
+        let prefixed = ["language-", "lang-", "highlight-source-", "highlight-"]
+            .iter()
+            .find_map(|p| t.strip_prefix(p));
+        if let Some(name) = prefixed.filter(|n| !n.is_empty()) {
+            // `highlight-*` is only a language when the rest names one (`highlight-python`,
+            // not `highlight-default` or `highlight-notranslate`).
+            if t.starts_with("highlight-")
+                && !t.starts_with("highlight-source-")
+                && canonical_language(name).is_none()
+            {
+                continue;
+            }
+            return Some(normalize_language(name));
+        }
+    }
+
+    if tokens
+        .iter()
+        .any(|t| HIGHLIGHTER_MARKERS.contains(&t.as_str()))
+    {
+        return tokens
+            .iter()
+            .find_map(|t| canonical_language(t))
+            .map(str::to_string);
+    }
+    None
+}
+
+/// Best-effort language guess from the code itself, for blocks with no language anywhere.
+pub fn guess_language(code: &str) -> Option<&'static str> {
+    let trimmed = code.trim_start();
+    let first = trimmed.lines().next().unwrap_or("").trim();
+
+    if let Some(shebang) = first.strip_prefix("#!") {
+        let interp = shebang.split_whitespace().last().unwrap_or("");
+        let interp = interp.rsplit('/').next().unwrap_or(interp);
+        let interp = interp.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        return canonical_language(interp);
+    }
+    if first.starts_with("<?php") {
+        return Some("php");
+    }
+    if first.starts_with("<?xml") {
+        return Some("xml");
+    }
+    if first.to_ascii_lowercase().starts_with("<!doctype html") || first.starts_with("<html") {
+        return Some("html");
+    }
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(code).is_ok()
+    {
+        return Some("json");
+    }
+    if first.starts_with(">>> ") {
+        return Some("pycon");
+    }
+    if first.starts_with("$ ") {
+        return Some("bash");
+    }
+
+    let has = |needle: &str| code.contains(needle);
+    let any_line = |pred: &dyn Fn(&str) -> bool| code.lines().any(|l| pred(l.trim_start()));
+
+    if has("fn ") && (has("let ") || has("->") || has("::") || has("impl ")) || has("#[derive(") {
+        return Some("rust");
+    }
+    if any_line(&|l| l.starts_with("package ")) && has("func ") {
+        return Some("go");
+    }
+    if has("#include") {
+        return Some(if has("std::") || has("template<") || has("class ") {
+            "cpp"
+        } else {
+            "c"
+        });
+    }
+    if has("public class ") || has("public static void") || has("System.out.") {
+        return Some("java");
+    }
+    if any_line(&|l| l.starts_with("def ") || l.starts_with("class "))
+        && any_line(&|l| l.ends_with(':'))
+        || any_line(&|l| l.starts_with("from ") && l.contains(" import "))
+        || any_line(&|l| l.starts_with("import ") && !l.ends_with(';') && !l.contains(" from "))
+    {
+        return Some("python");
+    }
+    if has("interface ") && has(": ") && (has("const ") || has("export ")) {
+        return Some("typescript");
+    }
+    if has("const ") || has("function ") || has("=> ") || has("console.log") || has("require(") {
+        return Some("javascript");
+    }
+    if any_line(&|l| {
+        let upper = l.to_ascii_uppercase();
+        upper.starts_with("SELECT ")
+            || upper.starts_with("CREATE TABLE")
+            || upper.starts_with("INSERT INTO")
+    }) {
+        return Some("sql");
+    }
+    if any_line(&|l| l.starts_with('[') && l.ends_with(']') && !l.contains(' '))
+        && any_line(&|l| l.contains(" = "))
+    {
+        return Some("toml");
+    }
+    if any_line(&|l| {
+        let cmd = l.split_whitespace().next().unwrap_or("");
+        matches!(
+            cmd,
+            "sudo"
+                | "apt"
+                | "apt-get"
+                | "brew"
+                | "npm"
+                | "npx"
+                | "yarn"
+                | "pnpm"
+                | "pip"
+                | "cargo"
+                | "curl"
+                | "git"
+                | "docker"
+                | "kubectl"
+                | "cd"
+                | "export"
+                | "echo"
+        )
+    }) {
+        return Some("bash");
+    }
+    None
+}
+
+/// Class tokens of line-number gutters (Pygments, Rouge, Chroma, highlight.js plugins, Prism).
+const GUTTER_CLASSES: &[&str] = &[
+    "linenos",
+    "lineno",
+    "linenodiv",
+    "gutter",
+    "lnt",
+    "ln",
+    "line-number",
+    "line-numbers-rows",
+    "hljs-ln-numbers",
+    "hljs-ln-n",
+];
+
+/// Class tokens of prompt spans (`<span class="gp">$ </span>` in Pygments, Prism command-line).
+const PROMPT_CLASSES: &[&str] = &["gp", "prompt", "command-line-prompt"];
+
+/// Tables that only exist to put a line-number gutter next to the code.
+const GUTTER_TABLE_CLASSES: &[&str] = &["highlighttable", "lntable", "rouge-table", "hljs-ln"];
+
+fn has_class(attributes: &BTreeMap<String, String>, classes: &[&str]) -> bool {
+    attributes
+        .get("class")
+        .is_some_and(|c| c.split_whitespace().any(|t| classes.contains(&t)))
+}
+
+/// Whether an element inside a `<pre>` is a line-number gutter.
+pub fn is_gutter(attributes: &BTreeMap<String, String>) -> bool {
+    has_class(attributes, GUTTER_CLASSES)
+}
+
+/// Whether an element inside a `<pre>` is a rendered prompt marker.
+pub fn is_prompt(attributes: &BTreeMap<String, String>) -> bool {
+    has_class(attributes, PROMPT_CLASSES)
+}
+
+/// Replace gutter tables (`<td class="linenos">` next to `<td class="code">`) with just their
+/// code, so the converter sees a plain code block instead of a table.
+pub fn strip_gutter_tables(html: &str) -> String {
+    replace_elements(html, is_gutter_table, |table, parser| {
+        let mut lines: Vec<String> = Vec::new();
+        for node in table.children().all(parser) {
+            let Node::Tag(tr) = node else { continue };
+            if tag_name(tr) != "tr" {
+                continue;
+            }
+            let code: String = child_tags(tr, parser)
+                .into_iter()
+                .filter(|td| tag_name(td) == "td" && !is_gutter_cell(td, parser))
+                .map(|td| td.inner_html(parser))
+                .collect();
+            lines.push(code);
+        }
+        // Per-line tables (`hljs-ln`) need their rows joined back into lines.
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    })
+}
+
+fn is_gutter_table(tag: &HTMLTag, parser: &Parser) -> bool {
+    if tag_name(tag) != "table" {
+        return false;
+    }
+    let classes = attr(tag, "class").unwrap_or_default();
+    if classes
+        .split_whitespace()
+        .any(|c| GUTTER_TABLE_CLASSES.contains(&c))
+    {
+        return true;
+    }
+    tag.children().all(parser).iter().any(|n| {
+        n.as_tag().is_some_and(|td| {
+            tag_name(td) == "td"
+                && attr(td, "class")
+                    .is_some_and(|c| c.split_whitespace().any(|t| GUTTER_CLASSES.contains(&t)))
+        })
+    })
+}
+
+/// A gutter cell has a gutter class or holds nothing but line numbers.
+fn is_gutter_cell(td: &HTMLTag, parser: &Parser) -> bool {
+    let class = attr(td, "class").unwrap_or_default();
+    if class
+        .split_whitespace()
+        .any(|t| GUTTER_CLASSES.contains(&t))
+    {
+        return true;
+    }
+    let text = td.inner_text(parser);
+    text.chars().any(|c| c.is_ascii_digit())
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || c.is_whitespace())
+}
+
+fn prompt_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^(?:\$|>>>|PS>|In \[\d+\]:|[\w.-]+@[\w.-]+:[^\s$#]*[$#])(?: |$)").unwrap()
+    })
+}
+
+fn continuation_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^(?:\.\.\.|\s+\.\.\.:)(?: |$)").unwrap())
+}
+
+/// Remove shell and REPL prompt markers (`$ `, `user@host:~$ `, `>>> `, `... `, `In [1]: `)
+/// from a code block that reads as a terminal session. Output lines are kept.
+pub fn strip_prompts(code: &str) -> String {
+    if !code.lines().any(|l| prompt_regex().is_match(l)) {
+        return code.to_string();
+    }
+    let python = code
+        .lines()
+        .any(|l| l.starts_with(">>>") || l.starts_with("In ["));
+    let mut out: Vec<&str> = Vec::new();
+    for line in code.lines() {
+        let stripped = match prompt_regex().find(line) {
+            Some(m) => &line[m.end()..],
+            None if python => match continuation_regex().find(line) {
+                Some(m) => &line[m.end()..],
+                None => line,
+            },
+            None => line,
+        };
+        out.push(stripped);
+    }
+    let mut joined = out.join("\n");
+    if code.ends_with('\n') {
+        joined.push('\n');
+    }
+    joined
+}
@@ -0,0 +1,358 @@
+//! Shell completion scripts generated from the flag and subcommand definitions in [`crate::cli`].
+//!
+//! URLs complete dynamically: the scripts call the hidden `gg __complete PREFIX`, which lists
+//! the cached site origins and crawl roots (see [`cached_urls`]), so `gg rg foo https://doc<TAB>`
+//! offers what is already in the cache.
+
+use std::fmt::Write as _;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    cache::Cache,
+    cli::{FlagKind, FlagSpec, SubcommandSpec, FLAGS, SUBCOMMANDS},
+    crawl,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl std::str::FromStr for Shell {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bash" => Ok(Self::Bash),
+            "zsh" => Ok(Self::Zsh),
+            "fish" => Ok(Self::Fish),
+            other => Err(anyhow!(
+                "unsupported shell: {other} (expected bash, zsh or fish)"
+            )),
+        }
+    }
+}
+
+/// The completion script for `shell`.
+pub fn script(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::Zsh => zsh(),
+        Shell::Fish => fish(),
+    }
+}
+
+/// Cached URLs starting with `prefix`: site origins and crawl roots, plus the pages of any
+/// crawl root `prefix` already reaches into.
+pub fn cached_urls(cache: &Cache, prefix: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    for m in crawl::cached_manifests(cache) {
+        if let Ok(root) = url::Url::parse(&m.root_url) {
+            urls.push(format!("{}/", root.origin().ascii_serialization()));
+        }
+        if prefix.starts_with(&m.root_url) {
+            urls.extend(m.pages.iter().map(|p| p.url.clone()));
+        }
+        urls.push(m.root_url);
+    }
+    urls.retain(|u| u.starts_with(prefix));
+    urls.sort();
+    urls.dedup();
+    urls
+}
+
+/// How a flag's value completes.
+enum ValueKind {
+    None,
+    File,
+    Dir,
+    Command,
+    Choices(&'static [&'static str]),
+    Other,
+}
+
+fn value_kind(f: &FlagSpec) -> ValueKind {
+    match (f.kind, f.value) {
+        (FlagKind::Switch, _) | (_, None) => ValueKind::None,
+        _ if !f.choices.is_empty() => ValueKind::Choices(f.choices),
+        (FlagKind::Path, Some("DIR")) => ValueKind::Dir,
+        (FlagKind::Path, _) => ValueKind::File,
+        (_, Some("CMD")) => ValueKind::Command,
+        _ => ValueKind::Other,
+    }
+}
+
+/// Fixed words a subcommand takes, e.g. `dir list clear` for `cache`.
+fn subcommand_words(sub: &SubcommandSpec) -> Vec<&'static str> {
+    if sub.name == "help" {
+        return SUBCOMMANDS.iter().map(|s| s.name).collect();
+    }
+    sub.args
+        .split(|c: char| c.is_whitespace() || "|()[]".contains(c))
+        .filter(|w| !w.is_empty() && w.chars().all(|c| c.is_ascii_lowercase()))
+        .collect()
+}
+
+fn flag_names() -> Vec<String> {
+    FLAGS
+        .iter()
+        .flat_map(|f| {
+            f.short
+                .into_iter()
+                .chain([f.long])
+                .map(str::to_string)
+                .chain(f.negated())
+        })
+        .collect()
+}
+
+fn value_flags(kind: impl Fn(&ValueKind) -> bool) -> Vec<&'static str> {
+    FLAGS
+        .iter()
+        .filter(|f| kind(&value_kind(f)))
+        .flat_map(|f| f.short.into_iter().chain([f.long]))
+        .collect()
+}
+
+fn bash() -> String {
+    let mut out = String::from("# bash completion for gg; generated by `gg completions bash`\n\n");
+    out.push_str("_gg() {\n    local cur prev\n");
+    out.push_str("    if declare -F _get_comp_words_by_ref >/dev/null; then\n");
+    out.push_str("        _get_comp_words_by_ref -n =: cur prev\n");
+    out.push_str(
+        "    else\n        cur=${COMP_WORDS[COMP_CWORD]}\n        prev=${COMP_WORDS[COMP_CWORD-1]}\n    fi\n\n",
+    );
+
+    out.push_str("    case \"$prev\" in\n");
+    let files = value_flags(|k| matches!(k, ValueKind::File)).join("|");
+    let _ = writeln!(
+        out,
+        "        {files})\n            COMPREPLY=($(compgen -f -- \"$cur\")); return ;;"
+    );
+    let dirs = value_flags(|k| matches!(k, ValueKind::Dir)).join("|");
+    let _ = writeln!(
+        out,
+        "        {dirs})\n            COMPREPLY=($(compgen -d -- \"$cur\")); return ;;"
+    );
+    let commands = value_flags(|k| matches!(k, ValueKind::Command)).join("|");
+    let _ = writeln!(
+        out,
+        "        {commands})\n            COMPREPLY=($(compgen -c -- \"$cur\")); return ;;"
+    );
+    for f in FLAGS {
+        if let ValueKind::Choices(choices) = value_kind(f) {
+            let _ = writeln!(
+                out,
+                "        {})\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return ;;",
+                f.long,
+                choices.join(" ")
+            );
+        }
+    }
+    let other = value_flags(|k| matches!(k, ValueKind::Other)).join("|");
+    let _ = writeln!(out, "        {other})\n            return ;;");
+    out.push_str("    esac\n\n");
+
+    out.push_str("    case \"$cur\" in\n");
+    for f in FLAGS {
+        if let ValueKind::Choices(choices) = value_kind(f) {
+            let _ = writeln!(
+                out,
+                "        {long}=*)\n            COMPREPLY=($(compgen -P {long}= -W \"{}\" -- \"${{cur#*=}}\")); return ;;",
+                choices.join(" "),
+                long = f.long
+            );
+        }
+    }
+    let _ = writeln!(
+        out,
+        "        -*)\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return ;;",
+        flag_names().join(" ")
+    );
+    out.push_str("        http*|@*|re:*)\n");
+    out.push_str("            COMPREPLY=($(gg __complete \"$cur\" 2>/dev/null))\n");
+    out.push_str(
+        "            [[ ${#COMPREPLY[@]} -eq 1 && ${COMPREPLY[0]} == */ ]] && compopt -o nospace\n",
+    );
+    out.push_str("            declare -F __ltrim_colon_completions >/dev/null && __ltrim_colon_completions \"$cur\"\n");
+    out.push_str("            return ;;\n    esac\n\n");
+
+    // The first word that is neither a flag nor a flag's value is the subcommand or host command.
+    let takes_value = value_flags(|k| !matches!(k, ValueKind::None)).join("|");
+    out.push_str("    local i word first=\n");
+    out.push_str("    for ((i = 1; i < COMP_CWORD; i++)); do\n        word=${COMP_WORDS[i]}\n");
+    out.push_str("        case \"$word\" in\n");
+    let _ = writeln!(out, "            {takes_value}) ((i++)) ;;");
+    out.push_str(
+        "            -*) ;;\n            *) first=$word; break ;;\n        esac\n    done\n\n",
+    );
+
+    out.push_str("    case \"$first\" in\n");
+    let subs: Vec<&str> = SUBCOMMANDS.iter().map(|s| s.name).collect();
+    let _ = writeln!(
+        out,
+        "        \"\")\n            COMPREPLY=($(compgen -W \"{}\" -c -- \"$cur\")) ;;",
+        subs.join(" ")
+    );
+    for sub in SUBCOMMANDS {
+        let words = subcommand_words(sub);
+        if !words.is_empty() {
+            let _ = writeln!(
+                out,
+                "        {})\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;",
+                sub.name,
+                words.join(" ")
+            );
+        }
+    }
+    out.push_str(
+        "        *)\n            COMPREPLY=($(compgen -f -- \"$cur\")) ;;\n    esac\n}\n\n",
+    );
+    out.push_str("complete -F _gg gg\n");
+    out
+}
+
+/// `text` for a single-quoted zsh `_arguments` spec.
+fn zsh_escape(text: &str) -> String {
+    let mut s = String::new();
+    for c in text.chars() {
+        match c {
+            '\'' => s.push_str("'\\''"),
+            '[' | ']' | ':' | '\\' => {
+                s.push('\\');
+                s.push(c);
+            }
+            _ => s.push(c),
+        }
+    }
+    s
+}
+
+fn zsh() -> String {
+    let mut out =
+        String::from("#compdef gg\n# zsh completion for gg; generated by `gg completions zsh`\n\n");
+    out.push_str("_gg_urls() {\n    local -a urls\n");
+    out.push_str("    urls=(${(f)\"$(gg __complete \"$PREFIX\" 2>/dev/null)\"})\n");
+    out.push_str("    compadd -Q -S '' -a urls\n}\n\n");
+
+    out.push_str(
+        "_gg_args() {\n    if (( CURRENT == 1 )); then\n        local -a subcommands\n        subcommands=(\n",
+    );
+    for sub in SUBCOMMANDS {
+        let _ = writeln!(
+            out,
+            "            '{}:{}'",
+            sub.name,
+            zsh_escape(&sub.summary())
+        );
+    }
+    out.push_str("        )\n        _describe -t subcommands 'subcommand' subcommands\n");
+    out.push_str("        _command_names -e\n        return\n    fi\n");
+    out.push_str("    case $words[1] in\n");
+    for sub in SUBCOMMANDS {
+        let words = subcommand_words(sub);
+        if !words.is_empty() {
+            let _ = writeln!(
+                out,
+                "        {}) (( CURRENT == 2 )) && {{ compadd -- {}; return }} ;;",
+                sub.name,
+                words.join(" ")
+            );
+        }
+    }
+    out.push_str("    esac\n");
+    out.push_str(
+        "    if [[ $PREFIX == (http|@|re:)* ]]; then\n        _gg_urls\n    else\n        _files\n    fi\n}\n\n",
+    );
+
+    out.push_str("_gg() {\n    _arguments -s -S \\\n");
+    for f in FLAGS {
+        let help = zsh_escape(&f.summary());
+        let action = match value_kind(f) {
+            ValueKind::None => String::new(),
+            ValueKind::File => format!(":{}:_files", f.value.unwrap_or_default()),
+            ValueKind::Dir => format!(":{}:_files -/", f.value.unwrap_or_default()),
+            ValueKind::Command => format!(":{}:_command_names -e", f.value.unwrap_or_default()),
+            ValueKind::Choices(choices) => {
+                format!(":{}:({})", f.value.unwrap_or_default(), choices.join(" "))
+            }
+            ValueKind::Other => format!(":{}: ", f.value.unwrap_or_default()),
+        };
+        let eq = if f.value.is_some() { "=" } else { "" };
+        match f.short {
+            Some(short) => {
+                let _ = writeln!(out, "        '(- *)'{{{short},{}}}'[{help}]' \\", f.long);
+            }
+            None => {
+                let _ = writeln!(out, "        '{}{eq}[{help}]{action}' \\", f.long);
+            }
+        }
+        if let Some(negated) = f.negated() {
+            let _ = writeln!(out, "        '{negated}[turn off {}]' \\", f.long);
+        }
+    }
+    out.push_str("        '*::arg:_gg_args'\n}\n\n_gg \"$@\"\n");
+    out
+}
+
+/// `text` as a single-quoted fish string.
+fn fish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish() -> String {
+    let mut out = String::from("# fish completion for gg; generated by `gg completions fish`\n\n");
+    out.push_str("function __gg_url_prefix\n    string match -qr '^(http|@|re:)' -- (commandline -ct)\nend\n\n");
+    out.push_str("complete -c gg -f\n");
+    out.push_str(
+        "complete -c gg -n '__gg_url_prefix' -a '(gg __complete (commandline -ct) 2>/dev/null)'\n",
+    );
+    out.push_str("complete -c gg -n 'not __gg_url_prefix; and not __fish_use_subcommand' -F\n");
+    out.push_str("complete -c gg -n '__fish_use_subcommand' -a '(__fish_complete_command)'\n");
+    for sub in SUBCOMMANDS {
+        let _ = writeln!(
+            out,
+            "complete -c gg -n '__fish_use_subcommand' -a {} -d {}",
+            sub.name,
+            fish_quote(&sub.summary())
+        );
+        let words = subcommand_words(sub);
+        if !words.is_empty() {
+            let _ = writeln!(
+                out,
+                "complete -c gg -n '__fish_seen_subcommand_from {}' -a {}",
+                sub.name,
+                fish_quote(&words.join(" "))
+            );
+        }
+    }
+    for f in FLAGS {
+        let mut line = String::from("complete -c gg");
+        if let Some(short) = f.short {
+            let _ = write!(line, " -s {}", short.trim_start_matches('-'));
+        }
+        let _ = write!(line, " -l {}", f.long.trim_start_matches("--"));
+        match value_kind(f) {
+            ValueKind::None => {}
+            ValueKind::File | ValueKind::Dir => line.push_str(" -r -F"),
+            ValueKind::Command => line.push_str(" -x -a '(__fish_complete_command)'"),
+            ValueKind::Choices(choices) => {
+                let _ = write!(line, " -x -a {}", fish_quote(&choices.join(" ")));
+            }
+            ValueKind::Other => line.push_str(" -x"),
+        }
+        let _ = writeln!(out, "{line} -d {}", fish_quote(&f.summary()));
+        if let Some(negated) = f.negated() {
+            let _ = writeln!(
+                out,
+                "complete -c gg -l {} -d {}",
+                negated.trim_start_matches("--"),
+                fish_quote(&format!("Turn off {}", f.long))
+            );
+        }
+    }
+    out
+}
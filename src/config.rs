@@ -0,0 +1,555 @@
+//! Layered configuration and site aliases.
+//!
+//! Settings come from, lowest precedence first: built-in defaults, the system file
+//! (`/etc/gg/config.toml`), the user file (`config.toml` in gg's config directory, e.g.
+//! `~/.config/gg/`, or the file given with `--config`), a project-local `.gg.toml` in the
+//! working directory or the nearest parent, `GG_*` environment variables, and command-line flags.
+//! A project file comes with whatever directory gg runs in, so it may not set the keys that run
+//! programs or load rules (`cmd`, `render_cmd`, `converters`, `sanitize_rules`).
+//!
+//! Files use the long flag names with `_` for `-`; `[host."..."]` sections (exact host or
+//! `*.suffix`) override the files' crawl settings for sources on matching hosts, below `GG_*`
+//! variables and flags:
+//!
+//! ```toml
+//! parallelism = 32
+//! sitemap = false
+//! allow_host = ["cdn.example.com"]
+//!
+//! [host."docs.rs"]
+//! user_agent = "gg-docs/1.0"
+//! max_depth = 3
+//! ```
+//!
+//! Environment variables use the upper-cased key: `GG_PARALLELISM=32`, `GG_USER_AGENT=...`.
+//!
+//! Site aliases name roots that are typed often:
+//!
+//! ```toml
+//! [alias]
+//! rustbook = "https://doc.rust-lang.org/book/"
+//!
+//! [alias.tokio]
+//! url = "https://docs.rs/tokio/latest/tokio/"
+//! include = ["sync/**", "task/**"]   # globs relative to url; default: the whole subtree
+//! max_depth = 3
+//! user_agent = "gg-docs/1.0"
+//! ttl = "7d"                         # refetch cached pages older than this
+//! ```
+//!
+//! `@tokio` selects the alias's pages and `rustbook:ch10-*` is `https://doc.rust-lang.org/book/ch10-*`.
+//! Alias options apply to the sources the alias expands to unless the matching flag is given.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use directories::ProjectDirs;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::{
+    cli::{self, FlagKind, FlagSpec},
+    crawl::CrawlOptions,
+    util::{parse_duration_secs, split_comma_separated, HostSet},
+};
+
+/// Names that already mean something before a `:` in a source.
+const RESERVED_ALIAS_NAMES: &[&str] = &["http", "https", "re"];
+
+/// Project-local config file, looked up from the working directory upwards.
+pub const PROJECT_CONFIG_FILE: &str = ".gg.toml";
+
+/// System-wide config file (Unix only).
+const SYSTEM_CONFIG_FILE: &str = "/etc/gg/config.toml";
+
+/// Settings that run commands or load rule files; refused in a project file.
+const COMMAND_SETTINGS: &[&str] = &["cmd", "render_cmd", "converters", "sanitize_rules"];
+
+/// Where a setting's value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    File(PathBuf),
+    Env(String),
+    CommandLine,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => f.write_str("default"),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Env(name) => f.write_str(name),
+            Self::CommandLine => f.write_str("command line"),
+        }
+    }
+}
+
+/// Setting values by key, each with its origin.
+pub type Settings = BTreeMap<&'static str, (String, Origin)>;
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub aliases: BTreeMap<String, SiteAlias>,
+    /// Settings from files; unset keys keep their defaults.
+    pub settings: Settings,
+    /// `[host."..."]` overrides by host pattern.
+    pub hosts: BTreeMap<String, Settings>,
+    /// Settings from `GG_*` variables, which override files and their host sections.
+    pub env: Settings,
+}
+
+#[derive(Debug, Clone)]
+pub struct SiteAlias {
+    /// Crawl root; always ends with `/`.
+    pub root: Url,
+    /// Globs relative to `root` selected by a bare `@alias`.
+    pub include: Vec<String>,
+    pub max_depth: Option<usize>,
+    pub user_agent: Option<String>,
+    pub ttl_secs: Option<u64>,
+    pub origin: Origin,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum AliasEntry {
+    Url(String),
+    Table {
+        url: String,
+        #[serde(default)]
+        include: Vec<String>,
+        max_depth: Option<usize>,
+        user_agent: Option<String>,
+        ttl: Option<String>,
+    },
+}
+
+impl Config {
+    /// Load every layer: the system file, the user file (`user_file`, or the default location
+    /// when `None`), the project file and `GG_*` environment variables. Missing default files
+    /// are skipped; a missing explicit file is an error.
+    pub fn load(user_file: Option<&Path>) -> Result<Self> {
+        let mut files: Vec<PathBuf> = Vec::new();
+        if cfg!(unix) {
+            files.push(PathBuf::from(SYSTEM_CONFIG_FILE));
+        }
+        match user_file {
+            Some(p) if !p.is_file() => {
+                return Err(anyhow!("config file not found: {}", p.display()))
+            }
+            Some(p) => files.push(p.to_path_buf()),
+            None => files.extend(default_config_path()),
+        }
+        files.retain(|p| p.is_file());
+        let project = std::env::current_dir()
+            .ok()
+            .and_then(|cwd| find_project_config(&cwd));
+        Self::load_from(&files, project.as_deref(), std::env::vars())
+    }
+
+    /// Merge `files` (lowest precedence first), then the untrusted `project` file, then the
+    /// `GG_*` variables in `env`.
+    pub fn load_from(
+        files: &[PathBuf],
+        project: Option<&Path>,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self> {
+        let mut config = Self::default();
+        let layers = files.iter().map(|p| (p.as_path(), true));
+        for (path, trusted) in layers.chain(project.map(|p| (p, false))) {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            config
+                .merge_file(&text, path, trusted)
+                .with_context(|| format!("invalid config in {}", path.display()))?;
+        }
+        for (name, val) in env {
+            let Some(key) = name.strip_prefix("GG_") else {
+                continue;
+            };
+            // Other GG_* variables (e.g. those set for converters) are not settings.
+            let key = key.to_ascii_lowercase();
+            if let Some(setting) = cli::settings().find(|s| s.key == key) {
+                let val = normalize(setting, &Value::String(val), None)
+                    .with_context(|| format!("invalid {name}"))?;
+                config.env.insert(setting.key, (val, Origin::Env(name)));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Parse a single config file's text.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut config = Self::default();
+        config.merge_file(text, Path::new("<config>"), true)?;
+        Ok(config)
+    }
+
+    /// Merge one file; an untrusted (project) file may not set `COMMAND_SETTINGS`.
+    fn merge_file(&mut self, text: &str, path: &Path, trusted: bool) -> Result<()> {
+        let root: Map<String, Value> = toml::from_str(text)?;
+        let origin = Origin::File(path.to_path_buf());
+        let dir = path.parent();
+        for (key, val) in root {
+            match key.as_str() {
+                "alias" => {
+                    let entries: BTreeMap<String, AliasEntry> =
+                        serde_json::from_value(val).context("invalid [alias] section")?;
+                    for (name, entry) in entries {
+                        if name.is_empty()
+                            || !name
+                                .chars()
+                                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                            || RESERVED_ALIAS_NAMES.contains(&name.as_str())
+                        {
+                            return Err(anyhow!("invalid alias name: {name}"));
+                        }
+                        let alias = SiteAlias::from_entry(entry, origin.clone())
+                            .with_context(|| format!("invalid alias {name}"))?;
+                        self.aliases.insert(name, alias);
+                    }
+                }
+                "host" => {
+                    let Value::Object(hosts) = val else {
+                        return Err(anyhow!("host must hold [host.\"HOST\"] tables"));
+                    };
+                    for (pattern, table) in hosts {
+                        let Value::Object(table) = table else {
+                            return Err(anyhow!("host.\"{pattern}\" must be a table"));
+                        };
+                        let overrides = self.hosts.entry(pattern.to_ascii_lowercase()).or_default();
+                        for (key, val) in table {
+                            let setting = find_setting(&key, trusted)?;
+                            if !setting.per_host {
+                                return Err(anyhow!(
+                                    "{key} cannot be set per host (in host.\"{pattern}\")"
+                                ));
+                            }
+                            let val = normalize(setting, &val, dir)
+                                .with_context(|| format!("invalid {key}"))?;
+                            overrides.insert(setting.key, (val, origin.clone()));
+                        }
+                    }
+                }
+                _ => {
+                    let setting = find_setting(&key, trusted)?;
+                    let val =
+                        normalize(setting, &val, dir).with_context(|| format!("invalid {key}"))?;
+                    self.settings.insert(setting.key, (val, origin.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The effective settings: those from files, then matching `[host."..."]` sections with
+    /// `host`, then `GG_*` variables, then the command-line settings in `cli`. Each key takes
+    /// the value of the last layer that sets it, so a list on the command line replaces the
+    /// configured list and `--no-flag` turns off a configured switch.
+    pub fn effective(&self, host: Option<&str>, cli: &Settings) -> Effective {
+        let mut settings = self.settings.clone();
+        let hosts = host.into_iter().flat_map(|h| self.matching_hosts(h));
+        for layer in hosts.chain([&self.env, cli]) {
+            settings.extend(layer.iter().map(|(k, v)| (*k, v.clone())));
+        }
+        Effective(settings)
+    }
+
+    /// Whether any `[host."..."]` section applies to `host`.
+    pub fn has_host_overrides(&self, host: &str) -> bool {
+        !self.matching_hosts(host).is_empty()
+    }
+
+    /// Host sections matching `host`, least specific first: wildcards by suffix length, then
+    /// the exact host.
+    fn matching_hosts(&self, host: &str) -> Vec<&Settings> {
+        let host = host.to_ascii_lowercase();
+        let mut matches: Vec<(&String, &Settings)> = self
+            .hosts
+            .iter()
+            .filter(|(pattern, _)| HostSet::from_iter([pattern]).contains(&host))
+            .collect();
+        matches.sort_by_key(|(pattern, _)| (!pattern.starts_with("*."), pattern.len()));
+        matches.into_iter().map(|(_, s)| s).collect()
+    }
+
+    /// Effective settings and their origins as TOML, for `gg config show`. `cli` holds the
+    /// command-line settings, which take precedence over everything else.
+    pub fn show(&self, cli: &Settings) -> String {
+        let Effective(settings) = self.effective(None, cli);
+
+        let mut out = String::new();
+        for setting in cli::settings() {
+            match settings.get(setting.key) {
+                Some((val, origin)) => show_line(&mut out, &toml_line(setting, val), origin),
+                None if setting.default.is_empty() => show_line(
+                    &mut out,
+                    &format!("# {} (unset)", setting.key),
+                    &Origin::Default,
+                ),
+                None => show_line(
+                    &mut out,
+                    &toml_line(setting, setting.default),
+                    &Origin::Default,
+                ),
+            }
+        }
+        for (pattern, overrides) in &self.hosts {
+            let _ = writeln!(out, "\n[host.\"{pattern}\"]");
+            for setting in cli::settings() {
+                if let Some((val, origin)) = overrides.get(setting.key) {
+                    show_line(&mut out, &toml_line(setting, val), origin);
+                }
+            }
+        }
+        if !self.aliases.is_empty() {
+            let _ = writeln!(out, "\n[alias]");
+            for (name, alias) in &self.aliases {
+                show_line(
+                    &mut out,
+                    &format!("{name} = \"{}\"", alias.root),
+                    &alias.origin,
+                );
+            }
+        }
+        out
+    }
+
+    /// Expand an alias reference (`@name` or `name:rest`) into URL or glob sources, with the
+    /// alias they came from. Other tokens are returned unchanged.
+    pub fn resolve<'a>(&'a self, token: &str) -> Vec<(String, Option<&'a SiteAlias>)> {
+        // `!alias:glob` excludes pages under the alias.
+        let (bang, reference) = match token.strip_prefix('!') {
+            Some(rest) => ("!", rest),
+            None => ("", token),
+        };
+        let Some((alias, rest)) = self.lookup(reference) else {
+            return vec![(token.to_string(), None)];
+        };
+        if !rest.is_empty() {
+            return vec![(format!("{bang}{}{rest}", alias.root), Some(alias))];
+        }
+        if alias.include.is_empty() {
+            return vec![(format!("{bang}{}", alias.root), Some(alias))];
+        }
+        alias
+            .include
+            .iter()
+            .map(|glob| {
+                (
+                    format!("{bang}{}{}", alias.root, glob.trim_start_matches('/')),
+                    Some(alias),
+                )
+            })
+            .collect()
+    }
+
+    /// Whether a token refers to a configured alias.
+    pub fn is_alias_token(&self, token: &str) -> bool {
+        self.lookup(token.strip_prefix('!').unwrap_or(token))
+            .is_some()
+    }
+
+    fn lookup<'t>(&self, token: &'t str) -> Option<(&SiteAlias, &'t str)> {
+        if let Some(name) = token.strip_prefix('@') {
+            return self.aliases.get(name).map(|a| (a, ""));
+        }
+        let (name, rest) = token.split_once(':')?;
+        self.aliases
+            .get(name)
+            .map(|a| (a, rest.trim_start_matches('/')))
+    }
+}
+
+/// `key = value` with the value quoted as TOML.
+fn toml_line(setting: &FlagSpec, val: &str) -> String {
+    let val = match setting.kind {
+        FlagKind::Switch => val.to_string(),
+        FlagKind::Value if !val.is_empty() && val.bytes().all(|b| b.is_ascii_digit()) => {
+            val.to_string()
+        }
+        FlagKind::List => {
+            let items: Vec<String> = val.split(',').map(|s| format!("{:?}", s.trim())).collect();
+            format!("[{}]", items.join(", "))
+        }
+        FlagKind::Value | FlagKind::Path => format!("{val:?}"),
+    };
+    format!("{} = {val}", setting.key)
+}
+
+impl SiteAlias {
+    fn from_entry(entry: AliasEntry, origin: Origin) -> Result<Self> {
+        let (url, include, max_depth, user_agent, ttl) = match entry {
+            AliasEntry::Url(url) => (url, Vec::new(), None, None, None),
+            AliasEntry::Table {
+                url,
+                include,
+                max_depth,
+                user_agent,
+                ttl,
+            } => (url, include, max_depth, user_agent, ttl),
+        };
+        let mut root = Url::parse(&url).with_context(|| format!("invalid URL: {url}"))?;
+        if !root.path().ends_with('/') {
+            root.set_path(&format!("{}/", root.path()));
+        }
+        let ttl_secs = ttl.as_deref().map(parse_duration_secs).transpose()?;
+        Ok(Self {
+            root,
+            include,
+            max_depth,
+            user_agent,
+            ttl_secs,
+            origin,
+        })
+    }
+
+    /// Crawl options for this alias's sources: its settings fill in what the flags left unset.
+    pub fn apply(
+        &self,
+        base: &CrawlOptions,
+        max_depth_flag: bool,
+        user_agent_flag: bool,
+    ) -> CrawlOptions {
+        let mut opts = base.clone();
+        if !max_depth_flag && self.max_depth.is_some() {
+            opts.max_depth = self.max_depth;
+        }
+        if let Some(ua) = self.user_agent.as_ref().filter(|_| !user_agent_flag) {
+            opts.http.user_agent = ua.clone();
+        }
+        if self.ttl_secs.is_some() {
+            opts.max_age_secs = self.ttl_secs;
+        }
+        opts
+    }
+}
+
+fn find_setting(key: &str, trusted: bool) -> Result<&'static FlagSpec> {
+    if !trusted && COMMAND_SETTINGS.contains(&key) {
+        return Err(anyhow!(
+            "{key} cannot be set in a project {PROJECT_CONFIG_FILE}; \
+             set it in the user config or on the command line"
+        ));
+    }
+    cli::settings()
+        .find(|s| s.key == key)
+        .ok_or_else(|| anyhow!("unknown setting: {key}"))
+}
+
+/// A file or environment value as the string its flag takes; booleans become `true`/`false`.
+fn normalize(setting: &FlagSpec, val: &Value, dir: Option<&Path>) -> Result<String> {
+    let text = match val {
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Array(items) if setting.kind == FlagKind::List => {
+            let parts = items
+                .iter()
+                .map(|v| {
+                    v.as_str()
+                        .ok_or_else(|| anyhow!("expected a list of strings"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            parts.join(",")
+        }
+        _ => return Err(anyhow!("unsupported value: {val}")),
+    };
+    Ok(match setting.kind {
+        FlagKind::Switch => match text.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => "true".to_string(),
+            "false" | "0" | "no" | "off" => "false".to_string(),
+            _ => return Err(anyhow!("expected a boolean, got {text}")),
+        },
+        FlagKind::Path => match dir {
+            Some(dir) if Path::new(&text).is_relative() => dir.join(&text).display().to_string(),
+            _ => text,
+        },
+        FlagKind::Value | FlagKind::List => text,
+    })
+}
+
+/// Record a command-line flag in `settings`: a switch as `true` or `false` (`on`), a value as
+/// given. The last value of a flag wins, except that repeated list flags add up.
+pub fn insert_cli_flag(settings: &mut Settings, flag: &'static FlagSpec, value: &str, on: bool) {
+    let val = match flag.kind {
+        FlagKind::Switch => on.to_string(),
+        FlagKind::List => match settings.get(flag.key) {
+            Some((prev, _)) => format!("{prev},{value}"),
+            None => value.to_string(),
+        },
+        FlagKind::Value | FlagKind::Path => value.to_string(),
+    };
+    settings.insert(flag.key, (val, Origin::CommandLine));
+}
+
+/// Settings resolved across every layer; unset keys take the flag table's defaults.
+#[derive(Debug, Clone)]
+pub struct Effective(Settings);
+
+impl Effective {
+    /// Whether any layer sets `key`.
+    pub fn is_set(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// The value of `key`, or its default; `None` when neither is given.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        match self.0.get(key) {
+            Some((val, _)) => Some(val.as_str()),
+            None => cli::settings()
+                .find(|s| s.key == key)
+                .map(|s| s.default)
+                .filter(|d| !d.is_empty()),
+        }
+    }
+
+    pub fn switch(&self, key: &str) -> bool {
+        self.get(key) == Some("true")
+    }
+
+    pub fn list(&self, key: &str) -> Vec<String> {
+        self.get(key).map(split_comma_separated).unwrap_or_default()
+    }
+
+    /// The value of `key` parsed as `T`; errors name the flag and where the value came from.
+    pub fn parse<T>(&self, key: &str) -> Result<Option<T>>
+    where
+        T: std::str::FromStr,
+        T::Err: fmt::Display,
+    {
+        let Some(val) = self.get(key) else {
+            return Ok(None);
+        };
+        val.parse().map(Some).map_err(|err| {
+            let origin = self.0.get(key).map_or(&Origin::Default, |(_, o)| o);
+            let flag = cli::settings()
+                .find(|s| s.key == key)
+                .map_or(key, |s| s.long);
+            anyhow!("invalid {flag} value {val:?} (from {origin}): {err}")
+        })
+    }
+}
+
+fn show_line(out: &mut String, line: &str, origin: &Origin) {
+    let _ = writeln!(out, "{line:<40}  # {origin}");
+}
+
+/// `config.toml` in the platform config directory, next to the default cache location.
+pub fn default_config_path() -> Option<PathBuf> {
+    ProjectDirs::from("dev", "gg", "gg").map(|p| p.config_dir().join("config.toml"))
+}
+
+/// The nearest `.gg.toml` in `dir` or one of its parents.
+pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(PROJECT_CONFIG_FILE))
+        .find(|p| p.is_file())
+}
@@ -1,29 +1,38 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
-    sync::OnceLock,
 };
 
 use anyhow::{anyhow, Context, Result};
+use futures_util::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tokio::task::JoinSet;
 use url::Url;
-use regex::Regex;
 
 use crate::{
     cache::Cache,
+    code,
+    external::{self, ExternalConverter},
+    feeds,
     http::{self, HttpOptions},
-    sitemap,
-    util::{host_variants, now_unix_secs, strip_fragment},
+    llms::{self, MarkdownOptions},
+    render::{self, RenderOptions},
+    sanitize::{self, SanitizeOptions},
+    sections::{self, AnchorStyle, HeadingRef, SectionEntry},
+    sitemap::{self, SitemapEntry},
+    tables::{self, TableMode},
+    util::{host_variants, invalid_choice, now_unix_secs, relative_link, strip_fragment, HostSet},
+    widgets,
 };
 
 use html_to_markdown_rs::{
     convert_with_metadata, convert_with_visitor,
     metadata::LinkMetadata,
     options::{CodeBlockStyle, ConversionOptions, HeadingStyle},
-    visitor::{HtmlVisitor, NodeContext, VisitResult},
+    visitor::{HtmlVisitor, NodeContext, VisitResult, VisitorHandle},
     MetadataConfig,
 };
 
@@ -31,8 +40,35 @@ use html_to_markdown_rs::{
 pub struct CrawlOptions {
     pub parallelism: usize,
     pub max_depth: Option<usize>,
+    /// Stop a crawl after this many pages, taking the highest sitemap priority first.
+    pub max_pages: Option<usize>,
     pub use_sitemap: bool,
+    /// Hosts besides the root's (exact or `*.suffix`) whose pages belong to the crawl, at any
+    /// path.
+    pub allow_hosts: Vec<String>,
+    /// Where pages beyond the root come from: published lists, in-page links, or neither.
+    pub discover: DiscoverMode,
+    /// Seed from RSS/Atom feeds and skip refetching pages they report as unchanged.
+    pub use_feeds: bool,
     pub http: HttpOptions,
+    pub sanitize: SanitizeOptions,
+    pub images: ImageMode,
+    pub tables: TableMode,
+    pub heading_anchors: AnchorStyle,
+    /// Split pages whose Markdown is at least this many bytes into per-section files.
+    pub split_min_bytes: Option<usize>,
+    /// Tag unlabeled code blocks with a language guessed from their content.
+    pub guess_code_language: bool,
+    /// Strip `$ ` / `>>> ` style prompt markers from code blocks.
+    pub strip_prompts: bool,
+    /// External commands that replace the built-in conversion for matching pages.
+    pub converters: Vec<ExternalConverter>,
+    /// Headless-browser rendering for JavaScript-driven pages.
+    pub render: RenderOptions,
+    /// Prefer Markdown published by the server (`llms.txt`, `.md` variants).
+    pub markdown: MarkdownOptions,
+    /// Cached pages and crawls older than this are refetched as with `refresh`.
+    pub max_age_secs: Option<u64>,
 }
 
 impl Default for CrawlOptions {
@@ -43,8 +79,119 @@ impl Default for CrawlOptions {
         Self {
             parallelism: (cpu * 8).clamp(8, 256),
             max_depth: None,
+            max_pages: None,
             use_sitemap: true,
+            allow_hosts: Vec::new(),
+            discover: DiscoverMode::default(),
+            use_feeds: true,
             http: HttpOptions::default(),
+            sanitize: SanitizeOptions::default(),
+            images: ImageMode::default(),
+            tables: TableMode::default(),
+            heading_anchors: AnchorStyle::default(),
+            split_min_bytes: None,
+            guess_code_language: false,
+            strip_prompts: false,
+            converters: Vec::new(),
+            render: RenderOptions::default(),
+            markdown: MarkdownOptions::default(),
+            max_age_secs: None,
+        }
+    }
+}
+
+impl CrawlOptions {
+    /// Fingerprint of the options that shape the cached Markdown: sanitize rules, images,
+    /// tables, heading anchors, section splitting and code-block cleanup.
+    pub fn conversion_fingerprint(&self) -> String {
+        let mut disabled: Vec<&String> = self.sanitize.disabled.iter().collect();
+        disabled.sort();
+        let options = format!(
+            "{:?}",
+            (
+                self.sanitize.enabled,
+                disabled,
+                &self.sanitize.user_rules,
+                self.images,
+                self.tables,
+                self.heading_anchors,
+                self.split_min_bytes,
+                self.guess_code_language,
+                self.strip_prompts,
+            )
+        );
+        blake3::hash(options.as_bytes()).to_hex()[..16].to_string()
+    }
+}
+
+/// How a subtree crawl finds pages besides its root.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscoverMode {
+    /// Only the pages the site lists: sitemaps, plus feeds and `llms.txt` when enabled.
+    Sitemap,
+    /// Only pages reachable by following in-page links.
+    Links,
+    /// Listed pages as seeds, then follow links from every page.
+    #[default]
+    Both,
+    /// Nothing: cache only the URLs given explicitly.
+    None,
+}
+
+impl DiscoverMode {
+    /// Accepted names, the default first.
+    pub const CHOICES: &'static [&'static str] = &["both", "sitemap", "links", "none"];
+
+    pub fn uses_lists(self) -> bool {
+        matches!(self, Self::Sitemap | Self::Both)
+    }
+
+    pub fn follows_links(self) -> bool {
+        matches!(self, Self::Links | Self::Both)
+    }
+}
+
+impl std::str::FromStr for DiscoverMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "sitemap" => Ok(Self::Sitemap),
+            "links" => Ok(Self::Links),
+            "both" => Ok(Self::Both),
+            "none" => Ok(Self::None),
+            other => Err(invalid_choice("discover mode", other, Self::CHOICES)),
+        }
+    }
+}
+
+/// How `<img>` elements end up in the cached Markdown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageMode {
+    /// Remove images entirely.
+    #[default]
+    Drop,
+    /// Replace images with a greppable `[Image: alt]` placeholder.
+    Alt,
+    /// Download images under `<site>/.gg/assets/` and link them relatively.
+    Download,
+}
+
+impl ImageMode {
+    /// Accepted names, the default first.
+    pub const CHOICES: &'static [&'static str] = &["drop", "alt", "download"];
+}
+
+impl std::str::FromStr for ImageMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "drop" => Ok(Self::Drop),
+            "alt" => Ok(Self::Alt),
+            "download" => Ok(Self::Download),
+            other => Err(invalid_choice("image mode", other, Self::CHOICES)),
         }
     }
 }
@@ -54,9 +201,55 @@ pub struct CrawlManifest {
     pub version: u32,
     pub root_url: String,
     pub generated_at: i64,
+    /// Page budget the crawl ran under; a crawl with another budget does not reuse it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pages: Option<usize>,
+    /// How the crawl found its pages; a crawl in another mode does not reuse it.
+    #[serde(default)]
+    pub discover: DiscoverMode,
+    /// Extra hosts the crawl followed links into; a crawl allowing others does not reuse it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_hosts: Vec<String>,
+    /// `CrawlOptions::conversion_fingerprint` of the crawl; a crawl converting pages otherwise
+    /// reuses neither the manifest nor its pages.
+    #[serde(default)]
+    pub conversion: String,
     pub pages: Vec<PageEntry>,
 }
 
+/// A page waiting to be crawled. The queue pops the highest sitemap priority first (pages no
+/// sitemap lists get the protocol default of 0.5), then the shallowest, then the oldest.
+#[derive(Debug)]
+struct QueuedPage {
+    priority: f32,
+    depth: usize,
+    seq: usize,
+    url: Url,
+}
+
+impl Ord for QueuedPage {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .total_cmp(&other.priority)
+            .then_with(|| other.depth.cmp(&self.depth))
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for QueuedPage {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueuedPage {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedPage {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PageEntry {
     pub url: String,
@@ -67,6 +260,20 @@ pub struct PageEntry {
     pub bytes: usize,
     pub markdown_bytes: usize,
     pub error: Option<String>,
+    /// Per-section files when the page was split (see `CrawlOptions::split_min_bytes`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sections: Vec<SectionEntry>,
+    /// Sitemap `<lastmod>` (Unix seconds), `<changefreq>` and `<priority>` for the page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lastmod: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changefreq: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f32>,
+    /// In-crawl links found on the page, so a page reused on refresh yields the same children
+    /// as a fresh fetch. `None` when links were not followed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Vec<String>>,
 }
 
 #[derive(Debug)]
@@ -79,6 +286,9 @@ struct PageFetch {
     cache_path: Option<String>,
     links: Vec<Url>,
     error: Option<String>,
+    sections: Vec<SectionEntry>,
+    /// When the page was reused unchanged from the previous crawl, its original fetch time.
+    reused_from: Option<i64>,
 }
 
 /// Ensure a single page is present in the cache. Returns the local Markdown path.
@@ -90,12 +300,19 @@ pub async fn ensure_page_cached(
     refresh: bool,
 ) -> Result<PathBuf> {
     let path = cache.page_path(&url)?;
-    if !refresh && cache.is_cached_file(&path) {
+    let conversion_path = cache.conversion_path(&url)?;
+    let conversion = opts.conversion_fingerprint();
+    if !refresh
+        && cache.is_cached_file(&path)
+        && !expired(opts, modified_unix_secs(&path))
+        && fs::read_to_string(&conversion_path).is_ok_and(|c| c == conversion)
+    {
         return Ok(path);
     }
 
     let fetch = fetch_and_convert_page(client, opts, url.clone(), false, cache).await?;
     if let Some(rel) = fetch.cache_path {
+        cache.write_atomic(&conversion_path, conversion.as_bytes())?;
         return Ok(cache.root().join(rel));
     }
 
@@ -115,10 +332,17 @@ pub async fn ensure_subtree_cached(
     refresh: bool,
 ) -> Result<CrawlManifest> {
     let manifest_path = cache.manifest_path_for_subtree(&root)?;
+    let conversion = opts.conversion_fingerprint();
     if !refresh && manifest_path.is_file() {
         if let Ok(m) = read_manifest(&manifest_path) {
             // Basic sanity check; if it fails, we recrawl.
-            if m.root_url == root.as_str() {
+            if m.root_url == root.as_str()
+                && m.max_pages == opts.max_pages
+                && m.discover == opts.discover
+                && m.allow_hosts == opts.allow_hosts
+                && m.conversion == conversion
+                && !expired(opts, Some(m.generated_at))
+            {
                 return Ok(m);
             }
         }
@@ -129,33 +353,91 @@ pub async fn ensure_subtree_cached(
         .map(|h| host_variants(h).into_iter().collect())
         .unwrap_or_default();
 
-    let client = http::build_client_internal(&opts.http, allowed_hosts.clone())?;
+    let extra_hosts: HostSet = opts.allow_hosts.iter().collect();
+    let mut client_hosts = extra_hosts.clone();
+    for h in &allowed_hosts {
+        client_hosts.insert(h);
+    }
+    let client = http::build_client_internal(&opts.http, client_hosts)?;
+
+    // On refresh, the previous crawl supplies pages that have not changed since.
+    let previous: HashMap<String, PageEntry> = read_manifest(&manifest_path)
+        .ok()
+        .filter(|m| m.root_url == root.as_str() && m.conversion == conversion)
+        .map(|m| {
+            m.pages
+                .into_iter()
+                .map(|p| (canonical_key_str(&p.url), p))
+                .collect()
+        })
+        .unwrap_or_default();
+    // Last-updated times reported by sitemaps and feeds, keyed like `seen`.
+    let mut updated_at: HashMap<String, i64> = HashMap::new();
+    // Sitemap metadata recorded with each page, keyed like `seen`.
+    let mut sitemap_meta: HashMap<String, SitemapEntry> = HashMap::new();
 
-    // Optionally seed from sitemap(s).
+    // Optionally seed from sitemap(s), feeds and llms.txt.
     let mut seeds: Vec<Url> = Vec::new();
-    if opts.use_sitemap {
-        // Keep sitemap fetch smaller than full pages.
-        let max = (opts.http.max_body_bytes / 2).max(1024 * 1024);
-        if let Ok(urls) = sitemap::discover_sitemap_urls(&client, &root, max).await {
-            seeds = urls;
+    // Keep sitemap fetch smaller than full pages.
+    let max = (opts.http.max_body_bytes / 2).max(1024 * 1024);
+    let use_lists = opts.discover.uses_lists();
+    if use_lists && opts.use_sitemap {
+        if let Ok(entries) = sitemap::discover_sitemap_entries(&client, &root, max).await {
+            for entry in entries {
+                let k = canonical_key(&entry.url);
+                if let Some(t) = entry.lastmod {
+                    record_updated(&mut updated_at, k.clone(), t);
+                }
+                seeds.push(entry.url.clone());
+                sitemap_meta.insert(k, entry);
+            }
         }
     }
+    if use_lists && opts.use_feeds {
+        for entry in feeds::discover_feed_entries(&client, &root, max).await {
+            if let Some(t) = entry.updated {
+                record_updated(&mut updated_at, canonical_key(&entry.url), t);
+            }
+            seeds.push(entry.url);
+        }
+    }
+    if use_lists && opts.markdown.enabled {
+        seeds.extend(llms::discover_llms_txt(&client, &root, max).await);
+    }
 
     let prefix = path_prefix(&root);
 
     let mut seen: HashSet<String> = HashSet::new();
-    let mut queue: VecDeque<(Url, usize)> = VecDeque::new();
+    let mut queue: BinaryHeap<QueuedPage> = BinaryHeap::new();
+    let mut next_seq = 1;
+    let mut enqueue = |queue: &mut BinaryHeap<QueuedPage>, url: Url, depth: usize| {
+        let priority = sitemap_meta
+            .get(&canonical_key(&url))
+            .map_or(0.5, SitemapEntry::effective_priority);
+        let seq = next_seq;
+        next_seq += 1;
+        queue.push(QueuedPage {
+            priority,
+            depth,
+            seq,
+            url,
+        });
+    };
 
-    // Always include the root URL.
+    // Always include the root URL, ahead of everything else.
     seen.insert(canonical_key(&root));
-    queue.push_back((root.clone(), 0));
+    queue.push(QueuedPage {
+        priority: f32::INFINITY,
+        depth: 0,
+        seq: 0,
+        url: root.clone(),
+    });
 
     for u in seeds {
-        if is_allowed_child(&u, &allowed_hosts, &prefix) {
-            let k = canonical_key(&u);
-            if seen.insert(k) {
-                queue.push_back((u, 0));
-            }
+        if is_allowed_child(&u, &allowed_hosts, &extra_hosts, &prefix)
+            && seen.insert(canonical_key(&u))
+        {
+            enqueue(&mut queue, u, 0);
         }
     }
 
@@ -199,12 +481,23 @@ pub async fn ensure_subtree_cached(
         fs::create_dir_all(parent).ok();
     }
 
-    let mut joinset: JoinSet<Result<(usize, PageFetch)>> = JoinSet::new();
+    let follow_links = opts.discover.follows_links();
+    let mut joinset: JoinSet<Result<(usize, Url, PageFetch)>> = JoinSet::new();
     let mut pages: Vec<PageEntry> = Vec::new();
 
-    while !queue.is_empty() || !joinset.is_empty() {
-        while joinset.len() < opts.parallelism && !queue.is_empty() {
-            let (url, depth) = queue.pop_front().unwrap();
+    let mut started = 0;
+    let budget = opts.max_pages.unwrap_or(usize::MAX);
+
+    while (!queue.is_empty() && started < budget) || !joinset.is_empty() {
+        while joinset.len() < opts.parallelism && started < budget {
+            let Some(QueuedPage { url, depth, .. }) = queue.pop() else {
+                break;
+            };
+            started += 1;
+            if let Some(pf) = reuse_unchanged(cache, &previous, &updated_at, &url, follow_links) {
+                joinset.spawn(async move { Ok((depth, url, pf)) });
+                continue;
+            }
             let client = client.clone();
             let cache = cache.clone();
             let opts = opts.clone();
@@ -214,34 +507,51 @@ pub async fn ensure_subtree_cached(
                 let f = fetch_and_convert_page_with_options(
                     &client,
                     &opts,
-                    url,
-                    true,
+                    url.clone(),
+                    follow_links,
                     &cache,
                     Some(conv_options),
                     Some(md_cfg),
                 )
                 .await?;
-                Ok((depth, f))
+                Ok((depth, url, f))
             });
         }
 
         if let Some(res) = joinset.join_next().await {
-            let (depth, pf) = res.context("crawl task panicked")??;
+            let (depth, url, pf) = res.context("crawl task panicked")??;
+            let children: Vec<&Url> = pf
+                .links
+                .iter()
+                .filter(|u| is_allowed_child(u, &allowed_hosts, &extra_hosts, &prefix))
+                .collect();
 
             // Record manifest entry for pages that produced Markdown.
             if let Some(rel) = &pf.cache_path {
+                let meta = sitemap_meta
+                    .get(&canonical_key(&url))
+                    .or_else(|| sitemap_meta.get(&canonical_key(&pf.final_url)));
                 pages.push(PageEntry {
                     url: pf.final_url.as_str().to_string(),
                     cache_path: rel.clone(),
                     status: pf.status,
                     content_type: pf.content_type.clone(),
-                    fetched_at: now_unix_secs(),
+                    fetched_at: pf.reused_from.unwrap_or_else(now_unix_secs),
                     bytes: pf.bytes,
                     markdown_bytes: pf.markdown_bytes,
                     error: pf.error.clone(),
+                    sections: pf.sections.clone(),
+                    lastmod: meta.and_then(|m| m.lastmod),
+                    changefreq: meta.and_then(|m| m.changefreq.clone()),
+                    priority: meta.and_then(|m| m.priority),
+                    links: follow_links
+                        .then(|| children.iter().map(|u| u.as_str().to_string()).collect()),
                 });
             }
 
+            if !follow_links {
+                continue;
+            }
             let next_depth = depth.saturating_add(1);
             if let Some(max) = opts.max_depth {
                 if next_depth > max {
@@ -249,13 +559,9 @@ pub async fn ensure_subtree_cached(
                 }
             }
 
-            for u in pf.links {
-                if !is_allowed_child(&u, &allowed_hosts, &prefix) {
-                    continue;
-                }
-                let k = canonical_key(&u);
-                if seen.insert(k) {
-                    queue.push_back((u, next_depth));
+            for u in children {
+                if seen.insert(canonical_key(u)) {
+                    enqueue(&mut queue, u.clone(), next_depth);
                 }
             }
         }
@@ -265,6 +571,10 @@ pub async fn ensure_subtree_cached(
         version: 1,
         root_url: root.as_str().to_string(),
         generated_at,
+        max_pages: opts.max_pages,
+        discover: opts.discover,
+        allow_hosts: opts.allow_hosts.clone(),
+        conversion,
         pages,
     };
 
@@ -291,7 +601,30 @@ async fn fetch_and_convert_page_with_options(
     conv_options: Option<ConversionOptions>,
     md_cfg: Option<MetadataConfig>,
 ) -> Result<PageFetch> {
-    let fetch = http::fetch_limited(client, url.clone(), opts.http.max_body_bytes).await?;
+    if opts.markdown.enabled {
+        if let Some(page) = fetch_markdown_variant(client, opts, &url, extract_links, cache).await?
+        {
+            return Ok(page);
+        }
+    }
+
+    let fetch = if opts.markdown.enabled {
+        http::fetch_limited_accept(
+            client,
+            url.clone(),
+            opts.http.max_body_bytes,
+            llms::MARKDOWN_ACCEPT,
+        )
+        .await?
+    } else {
+        http::fetch_limited(client, url.clone(), opts.http.max_body_bytes).await?
+    };
+    if opts.markdown.enabled
+        && fetch.status.is_success()
+        && llms::is_markdown_response(fetch.content_type.as_deref(), &fetch.body)
+    {
+        return server_markdown_page(client, opts, cache, fetch, extract_links).await;
+    }
 
     let final_url = fetch.final_url.clone();
     let status = fetch.status.as_u16();
@@ -300,7 +633,28 @@ async fn fetch_and_convert_page_with_options(
 
     let is_html = http::is_probably_html(content_type.as_deref(), &fetch.body);
 
-    if !is_html {
+    // Client-rendered pages are replaced by what the render command sees; a failed render
+    // falls back to the fetched HTML.
+    let mut render_err: Option<String> = None;
+    let rendered = if is_html {
+        match render::maybe_render(&opts.render, &final_url, &fetch.body).await {
+            Ok(r) => r,
+            Err(e) => {
+                render_err = Some(format!("render failed: {e}"));
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let body: &[u8] = rendered
+        .as_deref()
+        .map(str::as_bytes)
+        .unwrap_or(&fetch.body);
+
+    let converter = external::find_converter(&opts.converters, &final_url, content_type.as_deref());
+
+    if !is_html && converter.is_none() {
         return Ok(PageFetch {
             final_url,
             status,
@@ -310,16 +664,30 @@ async fn fetch_and_convert_page_with_options(
             cache_path: None,
             links: Vec::new(),
             error: Some("non-HTML content".to_string()),
+            sections: Vec::new(),
+            reused_from: None,
         });
     }
 
-    let html = String::from_utf8_lossy(&fetch.body).to_string();
+    let html = if is_html {
+        String::from_utf8_lossy(body).to_string()
+    } else {
+        String::new()
+    };
 
     let mut links_out: Vec<Url> = Vec::new();
     let mut markdown: String = String::new();
     let mut md_err: Option<String> = None;
+    let mut images: Vec<ImageRef> = Vec::new();
+    let mut headings: Vec<HeadingRef> = Vec::new();
 
-    if extract_links {
+    // Downloaded images are kept as Markdown image links, so the `images` rule must not run.
+    let mut sanitize_opts = opts.sanitize.clone();
+    if opts.images == ImageMode::Download {
+        sanitize_opts.disabled.insert("images".to_string());
+    }
+
+    if extract_links && is_html {
         let cfg = md_cfg.unwrap_or(MetadataConfig {
             extract_document: false,
             extract_headers: false,
@@ -329,48 +697,53 @@ async fn fetch_and_convert_page_with_options(
             max_structured_data_size: 0,
         });
         match convert_with_metadata(&html, conv_options.clone(), cfg) {
-            Ok((_md, meta)) => {
-                links_out = resolve_links(&final_url, meta.links);
-                match convert_with_code_visitor(&html, conv_options) {
-                    Ok(md) => markdown = sanitize_markdown(&md),
-                    Err(e) => md_err = Some(format!("markdown conversion failed: {e}")),
-                }
-            }
-            Err(e) => {
-                md_err = Some(format!("markdown conversion failed: {e}"));
-            }
-        }
-    } else {
-        match convert_with_code_visitor(&html, conv_options) {
-            Ok(md) => markdown = sanitize_markdown(&md),
+            Ok((_md, meta)) => links_out = resolve_links(&final_url, meta.links),
             Err(e) => md_err = Some(format!("markdown conversion failed: {e}")),
         }
     }
 
-    // Cache markdown if present.
-    let mut cache_rel: Option<String> = None;
-    let mut md_bytes = 0usize;
     if md_err.is_none() {
-        // Always ensure a trailing newline for POSIX tools.
-        if !markdown.ends_with('\n') {
-            markdown.push('\n');
+        match converter {
+            // External output is cached as is; the command owns the cleanup.
+            Some(c) => {
+                match external::run_converter(
+                    c,
+                    &final_url,
+                    content_type.as_deref(),
+                    body,
+                    opts.http.timeout,
+                )
+                .await
+                {
+                    Ok(md) => markdown = md,
+                    Err(e) => md_err = Some(format!("external converter failed: {e}")),
+                }
+            }
+            None => match convert_page(&html, conv_options, opts, Some(&final_url)) {
+                Ok(page) => {
+                    markdown = sanitize::sanitize_markdown(&page.markdown, &sanitize_opts);
+                    images = page.images;
+                    headings = page.headings;
+                }
+                Err(e) => md_err = Some(format!("markdown conversion failed: {e}")),
+            },
         }
-        md_bytes = markdown.len();
-
-        let path = cache.page_path(&final_url)?;
-        cache.write_atomic(&path, markdown.as_bytes())?;
-
-        // Store relative to cache root.
-        let rel = path
-            .strip_prefix(cache.root())
-            .unwrap_or(&path)
-            .to_string_lossy()
-            .to_string();
-        cache_rel = Some(rel);
     }
 
+    // Cache markdown if present.
+    let (cache_rel, md_bytes, sections) = if md_err.is_none() {
+        let (rel, md_bytes, sections) =
+            cache_markdown(client, opts, cache, &final_url, markdown, images, &headings).await?;
+        (Some(rel), md_bytes, sections)
+    } else {
+        (None, 0, Vec::new())
+    };
+
     // Treat HTTP error status as error but still keep markdown.
-    let mut error: Option<String> = md_err;
+    let mut error: Option<String> = match (md_err, render_err) {
+        (Some(m), Some(r)) => Some(format!("{r}; {m}")),
+        (m, r) => m.or(r),
+    };
     if fetch.status.is_client_error() || fetch.status.is_server_error() {
         let status_err = format!("HTTP status {}", status);
         error = Some(match error {
@@ -388,213 +761,227 @@ async fn fetch_and_convert_page_with_options(
         cache_path: cache_rel,
         links: links_out,
         error,
+        sections,
+        reused_from: None,
     })
 }
 
-fn image_md_regex() -> &'static Regex {
-    static RE: OnceLock<Regex> = OnceLock::new();
-    RE.get_or_init(|| Regex::new(r"!\[[^\]]*\]\([^)]+\)").unwrap())
-}
-
-fn img_tag_regex() -> &'static Regex {
-    static RE: OnceLock<Regex> = OnceLock::new();
-    RE.get_or_init(|| Regex::new(r"(?i)<img[^>]*>").unwrap())
-}
-
-fn footer_heading_regex() -> &'static Regex {
-    static RE: OnceLock<Regex> = OnceLock::new();
-    RE.get_or_init(|| Regex::new(r"(?i)^#{1,6}\s*footer\b").unwrap())
-}
-
-fn link_only_line_regex() -> &'static Regex {
-    static RE: OnceLock<Regex> = OnceLock::new();
-    RE.get_or_init(|| Regex::new(r"^\s*(\[[^\]]+\]\([^)]+\)\s*)+$").unwrap())
-}
-
-fn junk_only_line_regex() -> &'static Regex {
-    static RE: OnceLock<Regex> = OnceLock::new();
-    RE.get_or_init(|| Regex::new(r"^[\[\]\(\)\{\}\|\\/\-_.*•·\s]+$").unwrap())
-}
-
-fn copyright_line_regex() -> &'static Regex {
-    static RE: OnceLock<Regex> = OnceLock::new();
-    RE.get_or_init(|| Regex::new(r"(?i)^(©|\(c\))\s+.*\b(19|20)\d{2}\b.*$").unwrap())
-}
-
-pub fn sanitize_markdown_for_test(input: &str) -> String {
-    sanitize_markdown(input)
-}
-
-fn sanitize_markdown(input: &str) -> String {
-    let mut out = String::with_capacity(input.len());
-    let mut in_code = false;
-    let mut in_svg = false;
-    let mut prev_blank = false;
-    let mut skipping_frontmatter = false;
-    let mut frontmatter_checked = false;
-    let mut in_footer = false;
-    let mut saw_content = false;
-    let mut saw_heading = false;
-    let mut in_trailing_links = false;
-
-    for raw_line in input.lines() {
-        let line = raw_line.trim_end();
-        let trimmed = line.trim_start();
-
-        if !frontmatter_checked {
-            frontmatter_checked = true;
-            if trimmed == "---" {
-                skipping_frontmatter = true;
-                continue;
-            }
-        }
+/// Write a page's Markdown (and its section files when split) to the cache, downloading
+/// images first. Returns the cache-relative path, the Markdown size and the sections.
+async fn cache_markdown(
+    client: &Client,
+    opts: &CrawlOptions,
+    cache: &Cache,
+    final_url: &Url,
+    mut markdown: String,
+    images: Vec<ImageRef>,
+    headings: &[HeadingRef],
+) -> Result<(String, usize, Vec<SectionEntry>)> {
+    let mut sections: Vec<SectionEntry> = Vec::new();
+
+    // Always ensure a trailing newline for POSIX tools.
+    if !markdown.ends_with('\n') {
+        markdown.push('\n');
+    }
+    let md_bytes = markdown.len();
 
-        if skipping_frontmatter {
-            if trimmed == "---" {
-                skipping_frontmatter = false;
-            }
-            continue;
-        }
+    let path = cache.page_path(final_url)?;
+    let local_images = download_images(client, opts, cache, final_url, images).await;
 
-        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
-            in_code = !in_code;
-            out.push_str(line);
-            out.push('\n');
-            prev_blank = false;
-            continue;
+    let split = match opts.split_min_bytes {
+        Some(min) if markdown.len() >= min => sections::split_markdown(&markdown, headings),
+        _ => None,
+    };
+    sections::clear_section_files(&path);
+    if let Some((preamble, parts)) = split {
+        let dir = sections::section_dir(&path);
+        let dir_name = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        for part in &parts {
+            let section_path = dir.join(&part.file_name);
+            let body = link_images(&part.markdown, &dir, &local_images);
+            cache.write_atomic(&section_path, body.as_bytes())?;
+
+            let mut url = final_url.clone();
+            url.set_fragment(part.anchor.as_deref());
+            sections.push(SectionEntry {
+                title: part.title.clone(),
+                anchor: part.anchor.clone(),
+                url: url.as_str().to_string(),
+                cache_path: cache_relative(cache, &section_path),
+            });
         }
+        sections::write_section_list(&path, &parts)
+            .with_context(|| format!("failed to record sections of {}", path.display()))?;
+        markdown = sections::index_markdown(&preamble, &dir_name, &parts);
+    }
 
-        if !in_code {
-            if trimmed.contains("<svg") {
-                in_svg = true;
-            }
-            if in_svg {
-                if trimmed.contains("</svg>") {
-                    in_svg = false;
-                }
-                continue;
-            }
-        }
+    let page_dir = path.parent().unwrap_or(cache.root()).to_path_buf();
+    let markdown = link_images(&markdown, &page_dir, &local_images);
+    cache.write_atomic(&path, markdown.as_bytes())?;
 
-        if !in_code && footer_heading_regex().is_match(trimmed) {
-            in_footer = true;
-            continue;
-        }
+    Ok((cache_relative(cache, &path), md_bytes, sections))
+}
 
-        if !in_code && footer_heading_regex().is_match(line) {
-            in_footer = true;
+/// Try the page's `.md` variants (see `llms::markdown_variants`) and cache the first one the
+/// server provides. Hosts that keep missing are not probed further.
+async fn fetch_markdown_variant(
+    client: &Client,
+    opts: &CrawlOptions,
+    url: &Url,
+    extract_links: bool,
+    cache: &Cache,
+) -> Result<Option<PageFetch>> {
+    let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+    if !opts.markdown.should_probe(&host) {
+        return Ok(None);
+    }
+    for variant in llms::markdown_variants(url) {
+        let Ok(fetch) = http::fetch_limited_accept(
+            client,
+            variant,
+            opts.http.max_body_bytes,
+            llms::MARKDOWN_ACCEPT,
+        )
+        .await
+        else {
             continue;
+        };
+        if fetch.status.is_success()
+            && llms::is_markdown_response(fetch.content_type.as_deref(), &fetch.body)
+        {
+            opts.markdown.record(&host, true);
+            return server_markdown_page(client, opts, cache, fetch, extract_links)
+                .await
+                .map(Some);
         }
+    }
+    opts.markdown.record(&host, false);
+    Ok(None)
+}
 
-        if in_footer {
-            if trimmed.starts_with('#') {
-                in_footer = false;
-            } else {
-                continue;
-            }
-        }
-
-        if !in_code && trimmed.starts_with('#') {
-            saw_heading = true;
-        }
-
-        if !in_code && !saw_content {
-            if trimmed == "---" || trimmed == "***" || trimmed == "___" {
-                continue;
-            }
-            if trimmed.is_empty() {
-                continue;
-            }
-        }
-
-        let mut cleaned = line.to_string();
-        if !in_code {
-            cleaned = image_md_regex().replace_all(&cleaned, "").to_string();
-            cleaned = img_tag_regex().replace_all(&cleaned, "").to_string();
-        }
-
-        if !in_code {
-            let trimmed = cleaned.trim();
-            if !saw_heading && link_only_line_regex().is_match(trimmed) {
-                continue;
-            }
-            if in_trailing_links {
-                if trimmed.starts_with('#') {
-                    in_trailing_links = false;
-                } else if link_only_line_regex().is_match(trimmed) || trimmed.is_empty() {
-                    continue;
-                } else {
-                    in_trailing_links = false;
-                }
-            }
-            if !in_trailing_links && link_only_line_regex().is_match(trimmed) {
-                in_trailing_links = true;
-                continue;
-            }
-            if copyright_line_regex().is_match(trimmed) {
-                continue;
-            }
-            if junk_only_line_regex().is_match(trimmed) {
-                continue;
-            }
-            if trimmed == "---" || trimmed == "***" || trimmed == "___" {
-                continue;
-            }
-            if trimmed.eq_ignore_ascii_case("copy")
-                || trimmed.eq_ignore_ascii_case("copy page")
-                || trimmed.eq_ignore_ascii_case("copied")
-            {
-                continue;
-            }
-
-            if trimmed.contains("[SVG Image]") {
-                cleaned = cleaned.replace("[SVG Image]", "");
-                if cleaned.trim().is_empty() {
-                    continue;
-                }
-            }
-        }
-
-        if cleaned.trim().is_empty() {
-            if !prev_blank {
-                out.push('\n');
-                prev_blank = true;
-            }
-            continue;
-        }
+/// Cache a successful Markdown response as is, under the URL of the page it stands for.
+async fn server_markdown_page(
+    client: &Client,
+    opts: &CrawlOptions,
+    cache: &Cache,
+    fetch: http::HttpFetch,
+    extract_links: bool,
+) -> Result<PageFetch> {
+    let page_url = llms::page_url(&fetch.final_url);
+    let markdown = String::from_utf8_lossy(&fetch.body).to_string();
+    let links = if extract_links {
+        llms::markdown_links(&markdown, &page_url)
+    } else {
+        Vec::new()
+    };
+    let (cache_rel, md_bytes, sections) =
+        cache_markdown(client, opts, cache, &page_url, markdown, Vec::new(), &[]).await?;
 
-        prev_blank = false;
-        saw_content = true;
-        out.push_str(cleaned.trim_end());
-        out.push('\n');
-    }
+    Ok(PageFetch {
+        final_url: page_url,
+        status: fetch.status.as_u16(),
+        content_type: fetch.content_type,
+        bytes: fetch.body.len(),
+        markdown_bytes: md_bytes,
+        cache_path: Some(cache_rel),
+        links,
+        error: None,
+        sections,
+        reused_from: None,
+    })
+}
 
-    out
+pub fn sanitize_markdown_for_test(input: &str) -> String {
+    sanitize::sanitize_markdown(input, &SanitizeOptions::default())
 }
 
 #[derive(Debug)]
 struct CodeBlockVisitor {
     code_block_style: CodeBlockStyle,
     default_language: String,
+    /// Guess the language from the code when no markup names one.
+    guess_language: bool,
+    /// Language hints from open elements: (depth, language).
+    hints: Vec<(usize, String)>,
+    /// Strip shell/REPL prompt markers from code.
+    strip_prompts: bool,
+    /// Depth of the `<pre>` being walked, if any.
+    pre_depth: Option<usize>,
 }
 
 impl HtmlVisitor for CodeBlockVisitor {
-    fn visit_code_block(&mut self, _ctx: &NodeContext, lang: Option<&str>, code: &str) -> VisitResult {
-        let raw = lang.unwrap_or("").trim().to_ascii_lowercase();
-        let lang = match raw.as_str() {
-            "ts" => "typescript".to_string(),
-            "js" => "javascript".to_string(),
-            "py" => "python".to_string(),
-            "sh" | "shell" => "bash".to_string(),
-            _ => raw,
-        };
+    fn visit_element_start(&mut self, ctx: &NodeContext) -> VisitResult {
+        // Hints at this depth or deeper belong to elements that have already been closed.
+        while self
+            .hints
+            .last()
+            .is_some_and(|(depth, _)| *depth >= ctx.depth)
+        {
+            self.hints.pop();
+        }
+        if let Some(lang) = code::language_hint(&ctx.attributes) {
+            self.hints.push((ctx.depth, lang));
+        }
 
-        let lang = if !lang.is_empty() {
-            lang
-        } else if !self.default_language.is_empty() {
-            self.default_language.clone()
+        if self.pre_depth.is_some_and(|d| ctx.depth <= d) {
+            self.pre_depth = None;
+        }
+        if ctx.tag_name == "pre" {
+            self.pre_depth = Some(ctx.depth);
+        } else if self.pre_depth.is_some()
+            && (code::is_gutter(&ctx.attributes)
+                || (self.strip_prompts && code::is_prompt(&ctx.attributes)))
+        {
+            return VisitResult::Skip;
+        }
+        VisitResult::Continue
+    }
+
+    fn visit_code_block(
+        &mut self,
+        ctx: &NodeContext,
+        lang: Option<&str>,
+        code: &str,
+    ) -> VisitResult {
+        self.pre_depth = None;
+        let code = if self.strip_prompts {
+            code::strip_prompts(code)
         } else {
-            String::new()
+            code.to_string()
+        };
+        let code = code.as_str();
+
+        // The block's own markup (`<pre>` and its `<code>`) wins; wrapper hints such as
+        // `<div class="highlight-source-go">` only count when they name a known language.
+        let own = self
+            .hints
+            .iter()
+            .rev()
+            .find(|(depth, _)| *depth >= ctx.depth);
+        let wrapper =
+            self.hints.iter().rev().find(|(depth, lang)| {
+                *depth < ctx.depth && code::canonical_language(lang).is_some()
+            });
+
+        let lang = lang
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(code::normalize_language)
+            .or_else(|| own.or(wrapper).map(|(_, l)| l.clone()))
+            .or_else(|| {
+                self.guess_language
+                    .then(|| code::guess_language(code))
+                    .flatten()
+                    .map(str::to_string)
+            });
+
+        let lang = match lang {
+            Some(lang) => lang,
+            None if !self.default_language.is_empty() => self.default_language.clone(),
+            None => String::new(),
         };
 
         let fence = if self.code_block_style == CodeBlockStyle::Tildes {
@@ -616,14 +1003,302 @@ impl HtmlVisitor for CodeBlockVisitor {
     }
 }
 
+/// An image reference collected during conversion in `ImageMode::Download`.
+#[derive(Debug, Clone)]
+pub struct ImageRef {
+    pub url: Url,
+    pub alt: String,
+}
+
+/// Output of `convert_page`: unsanitized Markdown plus what the visitors collected.
+#[derive(Debug, Clone, Default)]
+pub struct ConvertedPage {
+    pub markdown: String,
+    /// Images to download in `ImageMode::Download`.
+    pub images: Vec<ImageRef>,
+    /// Headings in document order, with source ids.
+    pub headings: Vec<HeadingRef>,
+}
+
+/// Top-level visitor: delegates code blocks to `CodeBlockVisitor`, handles images, records
+/// headings and reshapes admonitions.
+#[derive(Debug)]
+struct PageVisitor {
+    code: CodeBlockVisitor,
+    images: ImageMode,
+    base_url: Option<Url>,
+    image_refs: Vec<ImageRef>,
+    headings: Vec<HeadingRef>,
+    heading_anchors: AnchorStyle,
+    /// Open admonitions: kind and title (once seen).
+    admonitions: Vec<(String, Option<String>)>,
+}
+
+impl HtmlVisitor for PageVisitor {
+    fn visit_element_start(&mut self, ctx: &NodeContext) -> VisitResult {
+        if let VisitResult::Skip = self.code.visit_element_start(ctx) {
+            return VisitResult::Skip;
+        }
+        if let Some(kind) = widgets::admonition_kind(&ctx.tag_name, &ctx.attributes) {
+            self.admonitions.push((kind, None));
+        }
+        VisitResult::Continue
+    }
+
+    fn visit_element_end(&mut self, ctx: &NodeContext, output: &str) -> VisitResult {
+        if widgets::admonition_kind(&ctx.tag_name, &ctx.attributes).is_some() {
+            if let Some((kind, title)) = self.admonitions.pop() {
+                return VisitResult::Custom(widgets::format_admonition(
+                    &kind,
+                    title.as_deref(),
+                    output,
+                ));
+            }
+        }
+        if let Some((_, title @ None)) = self.admonitions.last_mut() {
+            if widgets::is_admonition_title(&ctx.tag_name, &ctx.attributes) {
+                *title = Some(collapse_whitespace(output));
+                return VisitResult::Custom(String::new());
+            }
+        }
+        if matches!(
+            ctx.tag_name.as_str(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+        ) {
+            let id = ctx.attributes.get("id").map(|i| i.trim()).unwrap_or("");
+            if !id.is_empty() {
+                if let Some(md) = sections::annotate_heading(output, id, self.heading_anchors) {
+                    return VisitResult::Custom(md);
+                }
+            }
+        }
+        VisitResult::Continue
+    }
+
+    fn visit_code_block(
+        &mut self,
+        ctx: &NodeContext,
+        lang: Option<&str>,
+        code: &str,
+    ) -> VisitResult {
+        self.code.visit_code_block(ctx, lang, code)
+    }
+
+    fn visit_heading(
+        &mut self,
+        _ctx: &NodeContext,
+        level: u32,
+        text: &str,
+        id: Option<&str>,
+    ) -> VisitResult {
+        self.headings.push(HeadingRef {
+            level,
+            text: text.trim().to_string(),
+            id: id
+                .map(str::trim)
+                .filter(|i| !i.is_empty())
+                .map(str::to_string),
+        });
+        VisitResult::Continue
+    }
+
+    fn visit_image(
+        &mut self,
+        _ctx: &NodeContext,
+        src: &str,
+        alt: &str,
+        title: Option<&str>,
+    ) -> VisitResult {
+        match self.images {
+            ImageMode::Drop => VisitResult::Continue,
+            ImageMode::Alt => match image_placeholder(alt, title) {
+                Some(p) => VisitResult::Custom(p),
+                None => VisitResult::Skip,
+            },
+            ImageMode::Download => {
+                let resolved = self
+                    .base_url
+                    .as_ref()
+                    .and_then(|b| b.join(src.trim()).ok())
+                    .or_else(|| Url::parse(src.trim()).ok())
+                    .filter(|u| matches!(u.scheme(), "http" | "https"));
+                match resolved {
+                    Some(url) => {
+                        let alt = collapse_whitespace(alt);
+                        let out = format!("![{alt}]({url})");
+                        self.image_refs.push(ImageRef { url, alt });
+                        VisitResult::Custom(out)
+                    }
+                    // data: URIs and unresolvable sources keep only their text.
+                    None => match image_placeholder(alt, title) {
+                        Some(p) => VisitResult::Custom(p),
+                        None => VisitResult::Skip,
+                    },
+                }
+            }
+        }
+    }
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Textual stand-in for an image, e.g. `[Image: Request lifecycle — overview]`.
+fn image_placeholder(alt: &str, title: Option<&str>) -> Option<String> {
+    let alt = collapse_whitespace(alt);
+    let title = collapse_whitespace(title.unwrap_or(""));
+    let text = match (alt.is_empty(), title.is_empty() || title == alt) {
+        (true, true) => return None,
+        (false, true) => alt,
+        (true, false) => title,
+        (false, false) => format!("{alt} — {title}"),
+    };
+    Some(format!("[Image: {text}]"))
+}
+
 fn convert_with_code_visitor(html: &str, options: Option<ConversionOptions>) -> Result<String> {
-    let options = options.unwrap_or_default();
-    let visitor = CodeBlockVisitor {
-        code_block_style: options.code_block_style,
-        default_language: options.code_language.clone(),
+    Ok(convert_page(html, options, &CrawlOptions::default(), None)?.markdown)
+}
+
+/// Convert HTML through the visitor pipeline, returning Markdown and any images to download.
+fn convert_page(
+    html: &str,
+    options: Option<ConversionOptions>,
+    opts: &CrawlOptions,
+    base_url: Option<&Url>,
+) -> Result<ConvertedPage> {
+    let mut options = options.unwrap_or_default();
+    if opts.images != ImageMode::Drop {
+        options.strip_tags.retain(|t| t != "img");
+    }
+
+    // Widgets the converter would drop or mangle are rewritten first (see `widgets`).
+    let html = code::strip_gutter_tables(html);
+    let html = widgets::expand_tabs(&html);
+    let (html, tex) = widgets::extract_math(&html);
+
+    // Tables are rendered from the DOM; their cells go through this same pipeline.
+    let mut refs: Vec<ImageRef> = Vec::new();
+    let mut cell_err: Option<anyhow::Error> = None;
+    let (html, rendered_tables) = tables::extract_tables(&html, opts.tables, |cell| {
+        match convert_page(cell, Some(options.clone()), opts, base_url) {
+            Ok(cell) => {
+                refs.extend(cell.images);
+                cell.markdown
+            }
+            Err(e) => {
+                cell_err.get_or_insert(e);
+                String::new()
+            }
+        }
+    });
+    if let Some(e) = cell_err {
+        return Err(e);
+    }
+
+    let visitor = PageVisitor {
+        code: CodeBlockVisitor {
+            code_block_style: options.code_block_style,
+            default_language: options.code_language.clone(),
+            guess_language: opts.guess_code_language,
+            hints: Vec::new(),
+            strip_prompts: opts.strip_prompts,
+            pre_depth: None,
+        },
+        images: opts.images,
+        base_url: base_url.cloned(),
+        image_refs: Vec::new(),
+        headings: Vec::new(),
+        heading_anchors: opts.heading_anchors,
+        admonitions: Vec::new(),
     };
     let handle = std::rc::Rc::new(std::cell::RefCell::new(visitor));
-    Ok(convert_with_visitor(html, Some(options), Some(handle))?)
+    let dyn_handle: VisitorHandle = handle.clone();
+    let md = convert_with_visitor(&html, Some(options), Some(dyn_handle))?;
+    let mut visitor = handle.borrow_mut();
+    refs.append(&mut visitor.image_refs);
+    let headings = std::mem::take(&mut visitor.headings);
+    let md = tables::restore_tables(&md, &rendered_tables);
+    let md = widgets::restore_math(&md, &tex);
+    Ok(ConvertedPage {
+        markdown: md,
+        images: refs,
+        headings,
+    })
+}
+
+/// Download images referenced by a page into the site's asset tree. Failed downloads map to
+/// `None` so `link_images` can fall back to the alt placeholder.
+async fn download_images(
+    client: &Client,
+    opts: &CrawlOptions,
+    cache: &Cache,
+    page_url: &Url,
+    images: Vec<ImageRef>,
+) -> Vec<(ImageRef, Option<PathBuf>)> {
+    futures_util::stream::iter(images)
+        .map(|img| async move {
+            let res = download_asset(client, opts, cache, page_url, &img.url).await;
+            (img, res.ok())
+        })
+        .buffer_unordered(8)
+        .collect()
+        .await
+}
+
+/// Point image links at the downloaded copies, relative to `dir` (the Markdown file's directory).
+fn link_images(markdown: &str, dir: &Path, images: &[(ImageRef, Option<PathBuf>)]) -> String {
+    let mut out = markdown.to_string();
+    for (img, local) in images {
+        let remote = format!("![{}]({})", img.alt, img.url);
+        let replacement = match local {
+            Some(path) => format!("![{}]({})", img.alt, relative_link(dir, path)),
+            None => image_placeholder(&img.alt, None).unwrap_or_default(),
+        };
+        out = out.replace(&remote, &replacement);
+    }
+    out
+}
+
+async fn download_asset(
+    client: &Client,
+    opts: &CrawlOptions,
+    cache: &Cache,
+    page_url: &Url,
+    url: &Url,
+) -> Result<PathBuf> {
+    let path = cache.asset_path(page_url, url)?;
+    if cache.is_cached_file(&path) {
+        return Ok(path);
+    }
+    let fetch = http::fetch_limited(client, url.clone(), opts.http.max_body_bytes).await?;
+    if !fetch.status.is_success() {
+        return Err(anyhow!(
+            "HTTP status {} for image {url}",
+            fetch.status.as_u16()
+        ));
+    }
+    // HTML error pages and soft 404s often come back as 200s.
+    let content_type = fetch.content_type.as_deref().unwrap_or("");
+    if !content_type
+        .trim()
+        .to_ascii_lowercase()
+        .starts_with("image/")
+    {
+        return Err(anyhow!("not an image ({content_type}): {url}"));
+    }
+    cache.write_atomic(&path, &fetch.body)?;
+    Ok(path)
+}
+
+pub fn convert_page_for_test(
+    html: &str,
+    options: Option<ConversionOptions>,
+    opts: &CrawlOptions,
+    base_url: Option<&Url>,
+) -> Result<ConvertedPage> {
+    convert_page(html, options, opts, base_url)
 }
 
 pub fn convert_with_code_visitor_for_test(html: &str, options: Option<ConversionOptions>) -> Result<String> {
@@ -663,6 +1338,89 @@ fn canonical_key(url: &Url) -> String {
     u.as_str().to_string()
 }
 
+/// Whether something cached at `cached_at` (Unix seconds) is older than `max_age_secs`.
+fn expired(opts: &CrawlOptions, cached_at: Option<i64>) -> bool {
+    match (opts.max_age_secs, cached_at) {
+        (Some(max_age), Some(at)) => {
+            now_unix_secs().saturating_sub(at) > i64::try_from(max_age).unwrap_or(i64::MAX)
+        }
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+fn modified_unix_secs(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(
+        modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?
+            .as_secs() as i64,
+    )
+}
+
+fn canonical_key_str(url: &str) -> String {
+    Url::parse(url)
+        .map(|u| canonical_key(&u))
+        .unwrap_or_else(|_| url.to_string())
+}
+
+/// The previous crawl's entry for `url`, if a sitemap or feed reports the page unchanged since it
+/// was fetched, its Markdown is still cached and, when `follow_links`, its links were recorded.
+fn reuse_unchanged(
+    cache: &Cache,
+    previous: &HashMap<String, PageEntry>,
+    updated_at: &HashMap<String, i64>,
+    url: &Url,
+    follow_links: bool,
+) -> Option<PageFetch> {
+    let key = canonical_key(url);
+    let updated = *updated_at.get(&key)?;
+    let prev = previous.get(&key)?;
+    if prev.error.is_some() || updated > prev.fetched_at {
+        return None;
+    }
+
+    if !cache.is_cached_file(&cache.root().join(&prev.cache_path))
+        || !prev
+            .sections
+            .iter()
+            .all(|s| cache.is_cached_file(&cache.root().join(&s.cache_path)))
+    {
+        return None;
+    }
+    let links = if follow_links {
+        prev.links
+            .as_ref()?
+            .iter()
+            .filter_map(|u| Url::parse(u).ok())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let final_url = Url::parse(&prev.url).ok()?;
+    Some(PageFetch {
+        links,
+        final_url,
+        status: prev.status,
+        content_type: prev.content_type.clone(),
+        bytes: prev.bytes,
+        markdown_bytes: prev.markdown_bytes,
+        cache_path: Some(prev.cache_path.clone()),
+        error: None,
+        sections: prev.sections.clone(),
+        reused_from: Some(prev.fetched_at),
+    })
+}
+
+/// Keep the latest of the times reported for a page by different sources.
+fn record_updated(updated_at: &mut HashMap<String, i64>, key: String, t: i64) {
+    updated_at
+        .entry(key)
+        .and_modify(|u| *u = (*u).max(t))
+        .or_insert(t);
+}
+
 fn path_prefix(root: &Url) -> String {
     let mut p = root.path().to_string();
     if !p.ends_with('/') {
@@ -671,12 +1429,18 @@ fn path_prefix(root: &Url) -> String {
     p
 }
 
-fn is_allowed_child(url: &Url, allowed_hosts: &HashSet<String>, prefix: &str) -> bool {
+/// Whether a URL is in the crawl: under `prefix` on the root's hosts or an extra host.
+fn is_allowed_child(
+    url: &Url,
+    allowed_hosts: &HashSet<String>,
+    extra_hosts: &HostSet,
+    prefix: &str,
+) -> bool {
     let host = match url.host_str() {
         Some(h) => h.to_ascii_lowercase(),
         None => return false,
     };
-    if !allowed_hosts.contains(&host) {
+    if !allowed_hosts.contains(&host) && !extra_hosts.contains(&host) {
         return false;
     }
 
@@ -689,6 +1453,40 @@ fn is_allowed_child(url: &Url, allowed_hosts: &HashSet<String>, prefix: &str) ->
     path == prefix_no_slash || path.starts_with(prefix)
 }
 
+fn cache_relative(cache: &Cache, path: &Path) -> String {
+    path.strip_prefix(cache.root())
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Manifests of every crawl in the cache, ordered by root URL.
+pub fn cached_manifests(cache: &Cache) -> Vec<CrawlManifest> {
+    let mut manifests = Vec::new();
+    let mut dirs = vec![cache.root().join("sites")];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            if entry.file_name() == ".gg" {
+                // Holds the manifest and assets, never nested crawls.
+                if let Ok(m) = read_manifest(&path.join("manifest.json")) {
+                    manifests.push(m);
+                }
+            } else {
+                dirs.push(path);
+            }
+        }
+    }
+    manifests.sort_by(|a, b| a.root_url.cmp(&b.root_url));
+    manifests
+}
+
 fn read_manifest(path: &Path) -> Result<CrawlManifest> {
     let bytes = fs::read(path).with_context(|| format!("failed to read manifest: {}", path.display()))?;
     let m: CrawlManifest = serde_json::from_slice(&bytes).context("failed to parse manifest JSON")?;
@@ -0,0 +1,157 @@
+//! External commands plugged into the fetch path.
+//!
+//! Converters replace the built-in HTML → Markdown conversion for matching hosts or content
+//! types. They are declared in a TOML file passed with `--converters`:
+//!
+//! ```toml
+//! [[converter]]
+//! host = "*.example.com"          # optional; exact host or `*.` suffix
+//! content_type = "text/html"      # optional; MIME type, `text/*` allowed
+//! command = ["pandoc", "-f", "html", "-t", "gfm"]
+//! ```
+//!
+//! The first matching converter wins. It receives the raw response body on stdin and the page
+//! in `GG_URL`, `GG_HOST` and `GG_CONTENT_TYPE`; its stdout is cached as the page's Markdown.
+
+use std::{path::Path, process::Stdio, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use tokio::{io::AsyncWriteExt, process::Command};
+use url::Url;
+
+use crate::util::{read_toml, HostSet};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExternalConverter {
+    pub host: Option<String>,
+    pub content_type: Option<String>,
+    /// Program and arguments; run directly, not through a shell.
+    pub command: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConvertersFile {
+    #[serde(default)]
+    converter: Vec<ExternalConverter>,
+}
+
+impl ExternalConverter {
+    /// Whether this converter applies to a page at `url` served as `content_type`.
+    pub fn matches(&self, url: &Url, content_type: Option<&str>) -> bool {
+        if let Some(pattern) = &self.host {
+            let host = url.host_str().unwrap_or("");
+            if !HostSet::from_iter([pattern]).contains(host) {
+                return false;
+            }
+        }
+        if let Some(want) = &self.content_type {
+            let got = content_type.map(mime_essence).unwrap_or_default();
+            let want = mime_essence(want);
+            let ok = match want.strip_suffix("/*") {
+                Some(kind) => got.split('/').next() == Some(kind),
+                None => got == want,
+            };
+            if !ok {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Load `[[converter]]` entries from a TOML file.
+pub fn load_converters(path: &Path) -> Result<Vec<ExternalConverter>> {
+    let file: ConvertersFile = read_toml(path)?;
+    for c in &file.converter {
+        if c.command.is_empty() {
+            return Err(anyhow!(
+                "converter with empty command in {}",
+                path.display()
+            ));
+        }
+    }
+    Ok(file.converter)
+}
+
+/// First converter matching the page, if any.
+pub fn find_converter<'a>(
+    converters: &'a [ExternalConverter],
+    url: &Url,
+    content_type: Option<&str>,
+) -> Option<&'a ExternalConverter> {
+    converters.iter().find(|c| c.matches(url, content_type))
+}
+
+/// Run a converter on a response body and return its stdout as Markdown.
+pub async fn run_converter(
+    converter: &ExternalConverter,
+    url: &Url,
+    content_type: Option<&str>,
+    body: &[u8],
+    timeout: Duration,
+) -> Result<String> {
+    let env = [
+        ("GG_URL", url.as_str().to_string()),
+        ("GG_HOST", url.host_str().unwrap_or("").to_string()),
+        ("GG_CONTENT_TYPE", content_type.unwrap_or("").to_string()),
+    ];
+    let out = run_command(&converter.command, &env, body, timeout).await?;
+    Ok(String::from_utf8_lossy(&out).to_string())
+}
+
+/// Run `argv` with `stdin` piped in and return stdout; non-zero exit is an error carrying the
+/// first line of stderr.
+pub async fn run_command(
+    argv: &[String],
+    env: &[(&str, String)],
+    stdin: &[u8],
+    timeout: Duration,
+) -> Result<Vec<u8>> {
+    let (program, args) = argv.split_first().ok_or_else(|| anyhow!("empty command"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("failed to run {program}"))?;
+
+    // Feed stdin concurrently so a command that writes before reading cannot deadlock.
+    let mut pipe = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("no stdin for {program}"))?;
+    let input = stdin.to_vec();
+    let writer = tokio::spawn(async move {
+        // A command may exit without reading all of its input; that is not an error here.
+        let _ = pipe.write_all(&input).await;
+    });
+
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| anyhow!("{program} timed out after {}s", timeout.as_secs()))?
+        .with_context(|| format!("failed to wait for {program}"))?;
+    writer.await.ok();
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr
+            .lines()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("")
+            .trim();
+        return Err(anyhow!("{program} exited with {}: {reason}", output.status));
+    }
+    Ok(output.stdout)
+}
+
+fn mime_essence(ct: &str) -> String {
+    ct.split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
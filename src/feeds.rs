@@ -0,0 +1,249 @@
+//! RSS 2.0 and Atom feeds as crawl seeds.
+//!
+//! Blogs, changelogs and release notes often publish a feed listing recent pages together with
+//! their publication or update times. Entries seed the crawl like sitemap URLs do, and their
+//! timestamps let a refresh skip pages that have not changed since they were cached.
+
+use anyhow::{anyhow, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use reqwest::Client;
+use tl::ParserOptions;
+use url::Url;
+
+use crate::http;
+use crate::util::parse_datetime;
+
+/// Paths probed when the root page does not advertise a feed.
+const COMMON_FEED_PATHS: &[&str] = &["feed.xml", "atom.xml", "rss.xml", "index.xml"];
+
+/// Feeds fetched per crawl at most; sites sometimes advertise one per tag or category.
+const MAX_FEEDS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedEntry {
+    pub url: Url,
+    /// Latest of the entry's update and publication times, in Unix seconds.
+    pub updated: Option<i64>,
+}
+
+/// Feed URLs advertised with `<link rel="alternate" type="application/rss+xml|atom+xml">`.
+pub fn feed_links(html: &str, base: &Url) -> Vec<Url> {
+    let Ok(dom) = tl::parse(html, ParserOptions::default()) else {
+        return Vec::new();
+    };
+    let parser = dom.parser();
+    let Some(links) = dom.query_selector("link") else {
+        return Vec::new();
+    };
+
+    let mut out: Vec<Url> = Vec::new();
+    for h in links {
+        let Some(tag) = h.get(parser).and_then(|n| n.as_tag()) else {
+            continue;
+        };
+        let get = |name: &str| {
+            tag.attributes()
+                .get(name)
+                .flatten()
+                .map(|v| v.as_utf8_str().trim().to_ascii_lowercase())
+        };
+        let rel = get("rel").unwrap_or_default();
+        let kind = get("type").unwrap_or_default();
+        if !rel.split_whitespace().any(|r| r == "alternate")
+            || !matches!(
+                kind.as_str(),
+                "application/rss+xml" | "application/atom+xml"
+            )
+        {
+            continue;
+        }
+        let Some(href) = tag.attributes().get("href").flatten() else {
+            continue;
+        };
+        if let Ok(u) = base.join(href.as_utf8_str().trim()) {
+            if !out.contains(&u) {
+                out.push(u);
+            }
+        }
+    }
+    out
+}
+
+/// Parse an RSS 2.0 or Atom document into its entries.
+pub fn parse_feed(bytes: &[u8], base: &Url) -> Result<Vec<FeedEntry>> {
+    let mut reader = Reader::from_reader(bytes);
+    reader.config_mut().trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut entries: Vec<FeedEntry> = Vec::new();
+
+    // Inside an RSS `<item>` or Atom `<entry>`.
+    let mut in_entry = false;
+    let mut link: Option<Url> = None;
+    let mut updated: Option<i64> = None;
+    // Element whose text is being collected, and the text so far.
+    let mut field: Option<Vec<u8>> = None;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = e.local_name().as_ref().to_vec();
+                match name.as_slice() {
+                    b"item" | b"entry" => {
+                        in_entry = true;
+                        link = None;
+                        updated = None;
+                    }
+                    b"link" if in_entry => {
+                        if e.try_get_attribute("href").ok().flatten().is_some() {
+                            if let Some(u) = atom_link(&e, base) {
+                                link = link.or(Some(u));
+                            }
+                        } else {
+                            // RSS: the URL is the element text.
+                            field = Some(name);
+                            text.clear();
+                        }
+                    }
+                    b"guid" | b"pubDate" | b"date" | b"updated" | b"published" if in_entry => {
+                        // A guid is only a URL when it says so (the default is true).
+                        let permalink = name != b"guid"
+                            || e.try_get_attribute("isPermaLink")
+                                .ok()
+                                .flatten()
+                                .is_none_or(|a| a.value.as_ref() != b"false");
+                        if permalink {
+                            field = Some(name);
+                            text.clear();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Empty(e)) if in_entry && e.local_name().as_ref() == b"link" => {
+                if let Some(u) = atom_link(&e, base) {
+                    link = link.or(Some(u));
+                }
+            }
+            Ok(Event::Text(e)) if field.is_some() => {
+                text.push_str(&e.unescape().unwrap_or_default());
+            }
+            Ok(Event::CData(e)) if field.is_some() => {
+                text.push_str(&String::from_utf8_lossy(&e));
+            }
+            Ok(Event::End(e)) => {
+                let name = e.local_name();
+                if field.as_deref() == Some(name.as_ref()) {
+                    let value = text.trim();
+                    match name.as_ref() {
+                        b"link" if !value.is_empty() => {
+                            if let Ok(u) = base.join(value) {
+                                link = Some(u);
+                            }
+                        }
+                        b"link" => {}
+                        // Only used when the item has no `<link>`.
+                        b"guid" => {
+                            if link.is_none() {
+                                link = Url::parse(value)
+                                    .ok()
+                                    .filter(|u| u.scheme().starts_with("http"));
+                            }
+                        }
+                        _ => {
+                            if let Some(t) = parse_datetime(value) {
+                                updated = Some(updated.map_or(t, |u| u.max(t)));
+                            }
+                        }
+                    }
+                    field = None;
+                } else if matches!(name.as_ref(), b"item" | b"entry") && in_entry {
+                    in_entry = false;
+                    if let Some(mut url) = link.take() {
+                        url.set_fragment(None);
+                        if matches!(url.scheme(), "http" | "https") {
+                            entries.push(FeedEntry { url, updated });
+                        }
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(anyhow!("feed XML parse error: {e}")),
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(entries)
+}
+
+/// The page URL of an Atom `<link>`: `rel` absent or `alternate`, with an `href`.
+fn atom_link(e: &BytesStart, base: &Url) -> Option<Url> {
+    let href = e.try_get_attribute("href").ok().flatten()?;
+    let rel = e.try_get_attribute("rel").ok().flatten();
+    if rel.is_some_and(|r| r.value.as_ref() != b"alternate") {
+        return None;
+    }
+    let href = href.unescape_value().ok()?;
+    base.join(href.trim()).ok()
+}
+
+/// Discover a site's feeds (advertised on the root page, else at common paths next to the root
+/// and at the origin) and return their entries.
+pub async fn discover_feed_entries(
+    client: &Client,
+    root: &Url,
+    max_bytes: usize,
+) -> Vec<FeedEntry> {
+    let mut feeds: Vec<Url> = Vec::new();
+    if let Ok(resp) = http::fetch_limited(client, root.clone(), max_bytes).await {
+        if resp.status.is_success()
+            && http::is_probably_html(resp.content_type.as_deref(), &resp.body)
+        {
+            feeds = feed_links(&String::from_utf8_lossy(&resp.body), &resp.final_url);
+        }
+    }
+    let advertised = !feeds.is_empty();
+    if !advertised {
+        for base in [root.join("./").ok(), root.join("/").ok()]
+            .into_iter()
+            .flatten()
+        {
+            for path in COMMON_FEED_PATHS {
+                if let Ok(u) = base.join(path) {
+                    if !feeds.contains(&u) {
+                        feeds.push(u);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut entries: Vec<FeedEntry> = Vec::new();
+    let mut fetched = 0;
+    for url in feeds {
+        if fetched >= MAX_FEEDS {
+            break;
+        }
+        let Ok(resp) = http::fetch_limited(client, url.clone(), max_bytes).await else {
+            continue;
+        };
+        if !resp.status.is_success()
+            || http::is_probably_html(resp.content_type.as_deref(), &resp.body)
+        {
+            continue;
+        }
+        let Ok(parsed) = parse_feed(&resp.body, &resp.final_url) else {
+            continue;
+        };
+        fetched += 1;
+        entries.extend(parsed);
+        // Probed paths usually alias one feed (`feed.xml` / `atom.xml`); the first is enough.
+        if !advertised {
+            break;
+        }
+    }
+    entries
+}
@@ -2,9 +2,11 @@ use anyhow::{anyhow, Context, Result};
 use bytes::BytesMut;
 use futures_util::StreamExt;
 use reqwest::{header, redirect, Client, StatusCode};
-use std::{collections::HashSet, sync::Arc, time::Duration};
+use std::{sync::Arc, time::Duration};
 use url::Url;
 
+use crate::util::HostSet;
+
 #[derive(Debug, Clone)]
 pub struct HttpOptions {
     pub user_agent: String,
@@ -47,7 +49,8 @@ pub fn build_client_all(opts: &HttpOptions) -> Result<Client> {
     Ok(c)
 }
 
-pub fn build_client_internal(opts: &HttpOptions, allowed_hosts: HashSet<String>) -> Result<Client> {
+/// Client that only follows redirects to `allowed_hosts`.
+pub fn build_client_internal(opts: &HttpOptions, allowed_hosts: HostSet) -> Result<Client> {
     let allowed_hosts = Arc::new(allowed_hosts);
 
     let policy = redirect::Policy::custom(move |attempt| {
@@ -55,8 +58,7 @@ pub fn build_client_internal(opts: &HttpOptions, allowed_hosts: HashSet<String>)
             return attempt.stop();
         }
         if let Some(host) = attempt.url().host_str() {
-            let host_l = host.to_ascii_lowercase();
-            if allowed_hosts.contains(&host_l) {
+            if allowed_hosts.contains(host) {
                 return attempt.follow();
             }
         }
@@ -77,10 +79,26 @@ pub fn build_client_internal(opts: &HttpOptions, allowed_hosts: HashSet<String>)
 }
 
 pub async fn fetch_limited(client: &Client, url: Url, max_bytes: usize) -> Result<HttpFetch> {
+    fetch_limited_accept(
+        client,
+        url,
+        max_bytes,
+        "text/html,application/xhtml+xml;q=0.9,*/*;q=0.1",
+    )
+    .await
+}
+
+/// `fetch_limited` with an explicit `Accept` header.
+pub async fn fetch_limited_accept(
+    client: &Client,
+    url: Url,
+    max_bytes: usize,
+    accept: &str,
+) -> Result<HttpFetch> {
     let requested = url.clone();
     let resp = client
         .get(url)
-        .header(header::ACCEPT, "text/html,application/xhtml+xml;q=0.9,*/*;q=0.1")
+        .header(header::ACCEPT, accept)
         .send()
         .await
         .with_context(|| format!("HTTP request failed: {requested}"))?;
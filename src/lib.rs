@@ -2,8 +2,21 @@
 
 pub mod app;
 pub mod cache;
+pub mod cli;
+pub mod code;
+pub mod completions;
+pub mod config;
 pub mod crawl;
+pub mod external;
+pub mod feeds;
 pub mod http;
+pub mod llms;
+pub mod preprocess;
+pub mod render;
+pub mod sanitize;
+pub mod sections;
 pub mod sitemap;
+pub mod tables;
 pub mod urlspec;
 pub mod util;
+pub mod widgets;
@@ -0,0 +1,176 @@
+//! Server-provided Markdown: `llms.txt` seed lists and `.md` page variants.
+//!
+//! Many doc sites publish `/llms.txt` (a Markdown list of links to their pages) and serve a
+//! Markdown rendition of each page at `<page>.md` or on `Accept: text/markdown`. That output is
+//! written by the site itself and is much cleaner than converted HTML, so when enabled it is
+//! cached directly.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use regex::Regex;
+use reqwest::Client;
+use url::Url;
+
+use crate::http;
+
+/// `Accept` header preferring Markdown but taking HTML from servers that have none.
+pub const MARKDOWN_ACCEPT: &str =
+    "text/markdown,text/x-markdown;q=0.95,text/html;q=0.9,application/xhtml+xml;q=0.8,*/*;q=0.1";
+
+/// Variant probes per host before giving up on a host that has never served one.
+const MAX_MISSES: usize = 3;
+
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownOptions {
+    /// Use `llms.txt` seeds and prefer server Markdown over converting HTML.
+    pub enabled: bool,
+    /// Per-host `.md` variant hits and misses, shared across crawl tasks.
+    stats: Arc<Mutex<HashMap<String, (usize, usize)>>>,
+}
+
+impl MarkdownOptions {
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// Whether `.md` variants are still worth probing on `host`.
+    pub fn should_probe(&self, host: &str) -> bool {
+        let stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let (hits, misses) = stats.get(host).copied().unwrap_or_default();
+        hits > 0 || misses < MAX_MISSES
+    }
+
+    pub fn record(&self, host: &str, hit: bool) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = stats.entry(host.to_string()).or_default();
+        if hit {
+            entry.0 += 1;
+        } else {
+            entry.1 += 1;
+        }
+    }
+}
+
+/// Markdown variants of a page URL, most specific first: `/a/b` → `/a/b.md`, `/a/` →
+/// `/a/index.md`, `/a/b.html` → `/a/b.html.md` and `/a/b.md`.
+pub fn markdown_variants(page: &Url) -> Vec<Url> {
+    let path = page.path();
+    if path.ends_with(".md") {
+        return Vec::new();
+    }
+    let mut paths: Vec<String> = Vec::new();
+    if path.ends_with('/') {
+        paths.push(format!("{path}index.md"));
+    } else if let Some(stem) = path
+        .strip_suffix(".html")
+        .or_else(|| path.strip_suffix(".htm"))
+    {
+        paths.push(format!("{path}.md"));
+        paths.push(format!("{stem}.md"));
+    } else {
+        paths.push(format!("{path}.md"));
+    }
+    paths
+        .into_iter()
+        .map(|p| {
+            let mut u = page.clone();
+            u.set_path(&p);
+            u.set_fragment(None);
+            u
+        })
+        .collect()
+}
+
+/// Page URL for a link to a Markdown variant (inverse of `markdown_variants`), so seeds and
+/// links taken from Markdown land on the same cache entries as the HTML pages.
+pub fn page_url(url: &Url) -> Url {
+    let path = url.path();
+    let page_path = if let Some(dir) = path
+        .strip_suffix("/index.md")
+        .or_else(|| path.strip_suffix("/index.html.md"))
+    {
+        format!("{dir}/")
+    } else if let Some(page) = path.strip_suffix(".html.md") {
+        format!("{page}.html")
+    } else if let Some(page) = path.strip_suffix(".md") {
+        page.to_string()
+    } else {
+        return url.clone();
+    };
+    let mut u = url.clone();
+    u.set_path(&page_path);
+    u
+}
+
+/// Whether a response is Markdown: a Markdown content type, or plain text that is not HTML.
+pub fn is_markdown_response(content_type: Option<&str>, body: &[u8]) -> bool {
+    let ct = content_type.unwrap_or("").to_ascii_lowercase();
+    let essence = ct.split(';').next().unwrap_or("").trim();
+    match essence {
+        "text/markdown" | "text/x-markdown" => true,
+        "text/plain" => !body.is_empty() && !http::is_probably_html(None, body),
+        _ => false,
+    }
+}
+
+fn link_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\]\(\s*<?([^)\s>]+)>?(?:\s+[^)]*)?\)").unwrap())
+}
+
+/// Absolute http(s) URLs of the Markdown links in `markdown`, resolved against `base`, with
+/// Markdown variants mapped back to their pages.
+pub fn markdown_links(markdown: &str, base: &Url) -> Vec<Url> {
+    let mut out: Vec<Url> = Vec::new();
+    for caps in link_regex().captures_iter(markdown) {
+        let Ok(mut u) = base.join(&caps[1]) else {
+            continue;
+        };
+        if !matches!(u.scheme(), "http" | "https") {
+            continue;
+        }
+        u.set_fragment(None);
+        let u = page_url(&u);
+        if !out.contains(&u) {
+            out.push(u);
+        }
+    }
+    out
+}
+
+/// Fetch `llms.txt` for a crawl root (next to the root first, then at the origin) and return
+/// the pages it lists.
+pub async fn discover_llms_txt(client: &Client, root: &Url, max_bytes: usize) -> Vec<Url> {
+    let mut candidates: Vec<Url> = Vec::new();
+    if let Ok(u) = root.join("llms.txt") {
+        candidates.push(u);
+    }
+    if let Ok(u) = root.join("/llms.txt") {
+        if !candidates.contains(&u) {
+            candidates.push(u);
+        }
+    }
+
+    for url in candidates {
+        let Ok(resp) = http::fetch_limited(client, url.clone(), max_bytes).await else {
+            continue;
+        };
+        if !resp.status.is_success()
+            || http::is_probably_html(resp.content_type.as_deref(), &resp.body)
+        {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&resp.body);
+        let links = markdown_links(&text, &resp.final_url);
+        if !links.is_empty() {
+            return links;
+        }
+    }
+    Vec::new()
+}
@@ -0,0 +1,103 @@
+//! HTML rewriting ahead of Markdown conversion.
+//!
+//! Some structures (complex tables, site-specific widgets) are easier to render from the DOM
+//! than to fix up afterwards. These helpers find elements in the source HTML and splice in
+//! replacement markup, leaving everything else byte-for-byte untouched.
+
+use tl::{HTMLTag, Node, NodeHandle, Parser, ParserOptions};
+
+/// Replace every outermost element matching `select` with the markup returned by `render`.
+///
+/// Matching elements nested inside an already-matched element are not visited separately;
+/// `render` sees the whole subtree. Returning `None` leaves the element as is. If the HTML
+/// cannot be parsed, it is returned unchanged.
+pub fn replace_elements<S, R>(html: &str, mut select: S, mut render: R) -> String
+where
+    S: FnMut(&HTMLTag, &Parser) -> bool,
+    R: FnMut(&HTMLTag, &Parser) -> Option<String>,
+{
+    let dom = match tl::parse(html, ParserOptions::default()) {
+        Ok(d) => d,
+        Err(_) => return html.to_string(),
+    };
+    let parser = dom.parser();
+
+    let mut found: Vec<NodeHandle> = Vec::new();
+    for h in dom.children() {
+        collect_outermost(*h, parser, &mut select, &mut found);
+    }
+
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    for h in found {
+        if let Some(Node::Tag(tag)) = h.get(parser) {
+            if let Some(markup) = render(tag, parser) {
+                let (start, end) = tag.boundaries(parser);
+                edits.push((start, end + 1, markup));
+            }
+        }
+    }
+
+    splice(html, edits)
+}
+
+fn collect_outermost<S>(h: NodeHandle, parser: &Parser, select: &mut S, out: &mut Vec<NodeHandle>)
+where
+    S: FnMut(&HTMLTag, &Parser) -> bool,
+{
+    let Some(Node::Tag(tag)) = h.get(parser) else {
+        return;
+    };
+    if select(tag, parser) {
+        out.push(h);
+        return;
+    }
+    for child in tag.children().top().iter() {
+        collect_outermost(*child, parser, select, out);
+    }
+}
+
+fn splice(html: &str, mut edits: Vec<(usize, usize, String)>) -> String {
+    if edits.is_empty() {
+        return html.to_string();
+    }
+    edits.sort_by_key(|e| e.0);
+    let mut out = String::with_capacity(html.len());
+    let mut pos = 0;
+    for (start, end, markup) in edits {
+        // Malformed markup can yield overlapping ranges; keep the first.
+        if start < pos
+            || end > html.len()
+            || !html.is_char_boundary(start)
+            || !html.is_char_boundary(end)
+        {
+            continue;
+        }
+        out.push_str(&html[pos..start]);
+        out.push_str(&markup);
+        pos = end;
+    }
+    out.push_str(&html[pos..]);
+    out
+}
+
+/// Lowercased tag name.
+pub fn tag_name(tag: &HTMLTag) -> String {
+    tag.name().as_utf8_str().to_ascii_lowercase()
+}
+
+/// Attribute value, if present.
+pub fn attr(tag: &HTMLTag, name: &str) -> Option<String> {
+    tag.attributes()
+        .get(name)
+        .flatten()
+        .map(|v| v.as_utf8_str().to_string())
+}
+
+/// Child elements (skipping text and comments).
+pub fn child_tags<'p, 'a>(tag: &HTMLTag<'a>, parser: &'p Parser<'a>) -> Vec<&'p HTMLTag<'a>> {
+    tag.children()
+        .top()
+        .iter()
+        .filter_map(|h| h.get(parser).and_then(|n| n.as_tag()))
+        .collect()
+}
@@ -0,0 +1,125 @@
+//! Rendering JavaScript-driven pages before conversion.
+//!
+//! Many doc portals are single-page apps whose HTML is an empty shell (a mount point and a
+//! bundle), so conversion yields nothing and no links are found. With a render command
+//! configured (e.g. a wrapper around `chromium --headless --dump-dom`), such pages are handed
+//! to it and its stdout is used as the page HTML instead.
+//!
+//! The command gets the page URL as a `{url}` argument (or appended as the last argument when
+//! no argument contains `{url}`), in `GG_URL`, and the fetched HTML on stdin.
+
+use std::{sync::Arc, sync::OnceLock, time::Duration};
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use tokio::sync::Semaphore;
+use url::Url;
+
+use crate::{external, util::invalid_choice};
+
+/// When the render command runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderWhen {
+    /// Only for pages that look like an empty JavaScript shell.
+    #[default]
+    Auto,
+    /// For every HTML page.
+    Always,
+}
+
+impl RenderWhen {
+    /// Accepted names, the default first.
+    pub const CHOICES: &'static [&'static str] = &["auto", "always"];
+}
+
+impl std::str::FromStr for RenderWhen {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            other => Err(invalid_choice("render mode", other, Self::CHOICES)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Render command and arguments; `None` uses the fetched HTML as is.
+    pub command: Option<Vec<String>>,
+    pub when: RenderWhen,
+    pub timeout: Duration,
+    /// Bounds concurrent renders (browsers are heavy) independently of crawl parallelism.
+    pub slots: Arc<Semaphore>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            command: None,
+            when: RenderWhen::Auto,
+            timeout: Duration::from_secs(60),
+            slots: Arc::new(Semaphore::new(4)),
+        }
+    }
+}
+
+/// Visible text below this many characters (with scripts present) marks an empty shell.
+const SHELL_MAX_TEXT: usize = 200;
+
+fn invisible_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?is)<(script|style|noscript|template|head|svg)\b[^>]*>.*?</(script|style|noscript|template|head|svg)>",
+        )
+        .unwrap()
+    })
+}
+
+fn tag_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?s)<[^>]*>").unwrap())
+}
+
+/// Whether `html` looks like a client-rendered shell: scripts but hardly any visible text.
+pub fn is_empty_shell(html: &str) -> bool {
+    if !html.to_ascii_lowercase().contains("<script") {
+        return false;
+    }
+    let visible = invisible_regex().replace_all(html, " ");
+    let text = tag_regex().replace_all(&visible, " ");
+    text.split_whitespace()
+        .map(|w| w.chars().count())
+        .sum::<usize>()
+        < SHELL_MAX_TEXT
+}
+
+/// Render the page if a command is configured and `when` calls for it. Returns the rendered
+/// HTML, or `None` when the fetched HTML should be used.
+pub async fn maybe_render(opts: &RenderOptions, url: &Url, html: &[u8]) -> Result<Option<String>> {
+    let Some(command) = &opts.command else {
+        return Ok(None);
+    };
+    if opts.when == RenderWhen::Auto && !is_empty_shell(&String::from_utf8_lossy(html)) {
+        return Ok(None);
+    }
+
+    let mut argv: Vec<String> = command
+        .iter()
+        .map(|a| a.replace("{url}", url.as_str()))
+        .collect();
+    if !command.iter().any(|a| a.contains("{url}")) {
+        argv.push(url.as_str().to_string());
+    }
+
+    let _slot = opts.slots.acquire().await?;
+    let env = [("GG_URL", url.as_str().to_string())];
+    let out = external::run_command(&argv, &env, html, opts.timeout).await?;
+    let rendered = String::from_utf8_lossy(&out).to_string();
+    if rendered.trim().is_empty() {
+        return Err(anyhow!("render command produced no output"));
+    }
+    Ok(Some(rendered))
+}
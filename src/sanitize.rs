@@ -0,0 +1,350 @@
+//! Post-conversion Markdown cleanup.
+//!
+//! Cleanup is a pipeline of named rules. Built-in rules can be disabled individually
+//! (e.g. `footer` when a docs page is genuinely titled "Footer") and user regex rules can be
+//! appended from a TOML file:
+//!
+//! ```toml
+//! disable = ["footer", "copyright"]
+//!
+//! [[rule]]
+//! name = "cookie-banner"
+//! pattern = "(?i)^we use cookies"
+//!
+//! [[rule]]
+//! name = "edit-links"
+//! pattern = "\\[Edit this page\\]\\([^)]*\\)"
+//! replace = ""
+//! ```
+//!
+//! A user rule without `replace` drops every matching line; with `replace` the matches are
+//! rewritten in place. Rules never touch fenced code blocks.
+
+use std::{collections::HashSet, path::Path, sync::OnceLock};
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::util::read_toml;
+
+/// Names of the built-in rules, in the order they are applied.
+pub const BUILTIN_RULES: &[&str] = &[
+    "frontmatter",
+    "svg",
+    "footer",
+    "horizontal-rules",
+    "images",
+    "nav-links",
+    "link-blocks",
+    "copyright",
+    "junk-lines",
+    "copy-buttons",
+    "svg-image-marker",
+];
+
+#[derive(Debug, Clone)]
+pub struct SanitizeOptions {
+    /// When false, Markdown is cached exactly as converted.
+    pub enabled: bool,
+    /// Built-in rules that are switched off.
+    pub disabled: HashSet<String>,
+    /// User rules, applied after the built-in ones.
+    pub user_rules: Vec<UserRule>,
+}
+
+impl Default for SanitizeOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled: HashSet::new(),
+            user_rules: Vec::new(),
+        }
+    }
+}
+
+impl SanitizeOptions {
+    pub fn is_enabled(&self, rule: &str) -> bool {
+        !self.disabled.contains(rule)
+    }
+
+    /// Disable a built-in rule by name.
+    pub fn disable(&mut self, rule: &str) -> Result<()> {
+        let rule = rule.trim();
+        if !BUILTIN_RULES.contains(&rule) {
+            return Err(anyhow!(
+                "unknown sanitize rule: {rule} (known: {})",
+                BUILTIN_RULES.join(", ")
+            ));
+        }
+        self.disabled.insert(rule.to_string());
+        Ok(())
+    }
+
+    /// Load `disable = [...]` and `[[rule]]` entries from a TOML rules file.
+    pub fn load_rules_file(&mut self, path: &Path) -> Result<()> {
+        let file: RulesFile = read_toml(path)?;
+        self.apply_rules_file(file)
+            .with_context(|| format!("invalid sanitize rules in {}", path.display()))
+    }
+
+    fn apply_rules_file(&mut self, file: RulesFile) -> Result<()> {
+        for name in &file.disable {
+            self.disable(name)?;
+        }
+        for r in file.rule {
+            self.user_rules
+                .push(UserRule::new(&r.name, &r.pattern, r.replace)?);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserRule {
+    pub name: String,
+    regex: Regex,
+    /// `None` drops matching lines; `Some` rewrites the matches.
+    replace: Option<String>,
+}
+
+impl UserRule {
+    pub fn new(name: &str, pattern: &str, replace: Option<String>) -> Result<Self> {
+        let regex = Regex::new(pattern)
+            .with_context(|| format!("invalid regex in sanitize rule '{name}'"))?;
+        Ok(Self {
+            name: name.to_string(),
+            regex,
+            replace,
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    disable: Vec<String>,
+    #[serde(default)]
+    rule: Vec<RuleDef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleDef {
+    name: String,
+    pattern: String,
+    replace: Option<String>,
+}
+
+fn image_md_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"!\[[^\]]*\]\([^)]+\)").unwrap())
+}
+
+fn img_tag_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)<img[^>]*>").unwrap())
+}
+
+fn footer_heading_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)^#{1,6}\s*footer\b").unwrap())
+}
+
+fn link_only_line_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^\s*(\[[^\]]+\]\([^)]+\)\s*)+$").unwrap())
+}
+
+fn junk_only_line_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^[\[\]\(\)\{\}\|\\/\-_.*•·\s]+$").unwrap())
+}
+
+/// A table delimiter row like `| --- | :---: |`, which is all punctuation but not junk.
+fn table_separator_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^\|?(\s*:?-+:?\s*\|)+\s*(:?-+:?\s*)?$").unwrap())
+}
+
+fn copyright_line_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)^(©|\(c\))\s+.*\b(19|20)\d{2}\b.*$").unwrap())
+}
+
+fn is_rule_line(s: &str) -> bool {
+    s == "---" || s == "***" || s == "___"
+}
+
+/// Run the sanitize pipeline over converted Markdown.
+pub fn sanitize_markdown(input: &str, opts: &SanitizeOptions) -> String {
+    if !opts.enabled {
+        return input.to_string();
+    }
+
+    let on = |rule: &str| opts.is_enabled(rule);
+
+    let mut out = String::with_capacity(input.len());
+    let mut in_code = false;
+    let mut in_svg = false;
+    let mut prev_blank = false;
+    let mut skipping_frontmatter = false;
+    let mut frontmatter_checked = false;
+    let mut in_footer = false;
+    let mut saw_content = false;
+    let mut saw_heading = false;
+    let mut in_trailing_links = false;
+
+    for raw_line in input.lines() {
+        let line = raw_line.trim_end();
+        let trimmed = line.trim_start();
+
+        if !frontmatter_checked {
+            frontmatter_checked = true;
+            if on("frontmatter") && trimmed == "---" {
+                skipping_frontmatter = true;
+                continue;
+            }
+        }
+
+        if skipping_frontmatter {
+            if trimmed == "---" {
+                skipping_frontmatter = false;
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            out.push_str(line);
+            out.push('\n');
+            prev_blank = false;
+            continue;
+        }
+
+        if !in_code && on("svg") {
+            if trimmed.contains("<svg") {
+                in_svg = true;
+            }
+            if in_svg {
+                if trimmed.contains("</svg>") {
+                    in_svg = false;
+                }
+                continue;
+            }
+        }
+
+        if !in_code && on("footer") && footer_heading_regex().is_match(trimmed) {
+            in_footer = true;
+            continue;
+        }
+
+        if in_footer {
+            if trimmed.starts_with('#') {
+                in_footer = false;
+            } else {
+                continue;
+            }
+        }
+
+        if !in_code && trimmed.starts_with('#') {
+            saw_heading = true;
+        }
+
+        if !in_code && !saw_content {
+            if on("horizontal-rules") && is_rule_line(trimmed) {
+                continue;
+            }
+            if trimmed.is_empty() {
+                continue;
+            }
+        }
+
+        let mut cleaned = line.to_string();
+        if !in_code && on("images") {
+            cleaned = image_md_regex().replace_all(&cleaned, "").to_string();
+            cleaned = img_tag_regex().replace_all(&cleaned, "").to_string();
+        }
+
+        if !in_code {
+            let trimmed = cleaned.trim();
+            let link_only = link_only_line_regex().is_match(trimmed);
+            if on("nav-links") && !saw_heading && link_only {
+                continue;
+            }
+            if on("link-blocks") {
+                if in_trailing_links {
+                    if trimmed.starts_with('#') {
+                        in_trailing_links = false;
+                    } else if link_only || trimmed.is_empty() {
+                        continue;
+                    } else {
+                        in_trailing_links = false;
+                    }
+                }
+                if !in_trailing_links && link_only {
+                    in_trailing_links = true;
+                    continue;
+                }
+            }
+            if on("copyright") && copyright_line_regex().is_match(trimmed) {
+                continue;
+            }
+            if on("junk-lines")
+                && junk_only_line_regex().is_match(trimmed)
+                && !table_separator_regex().is_match(trimmed)
+            {
+                continue;
+            }
+            if on("horizontal-rules") && is_rule_line(trimmed) {
+                continue;
+            }
+            if on("copy-buttons")
+                && (trimmed.eq_ignore_ascii_case("copy")
+                    || trimmed.eq_ignore_ascii_case("copy page")
+                    || trimmed.eq_ignore_ascii_case("copied"))
+            {
+                continue;
+            }
+
+            if on("svg-image-marker") && trimmed.contains("[SVG Image]") {
+                cleaned = cleaned.replace("[SVG Image]", "");
+                if cleaned.trim().is_empty() {
+                    continue;
+                }
+            }
+        }
+
+        if !in_code && apply_user_rules(&opts.user_rules, &mut cleaned) {
+            continue;
+        }
+
+        if cleaned.trim().is_empty() {
+            if !prev_blank {
+                out.push('\n');
+                prev_blank = true;
+            }
+            continue;
+        }
+
+        prev_blank = false;
+        saw_content = true;
+        out.push_str(cleaned.trim_end());
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Apply user rules to a line. Returns true when the line should be dropped.
+fn apply_user_rules(rules: &[UserRule], line: &mut String) -> bool {
+    for rule in rules {
+        if !rule.regex.is_match(line) {
+            continue;
+        }
+        match &rule.replace {
+            Some(rep) => *line = rule.regex.replace_all(line, rep.as_str()).to_string(),
+            None => return true,
+        }
+    }
+    false
+}
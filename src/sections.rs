@@ -0,0 +1,325 @@
+//! Heading anchors and per-section files.
+//!
+//! With `--heading-anchors`, source heading `id`s are carried into the Markdown
+//! (`## Usage {#usage}`) so tools can build `url#anchor` links. A page cached at `.../spec.md`
+//! can also be split at H1/H2 boundaries into `.../spec/01-introduction.md`,
+//! `.../spec/02-usage.md`, ... while `spec.md` keeps the preamble and a list of the sections.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A heading seen during conversion, with the `id` of the source element if it had one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadingRef {
+    pub level: u32,
+    pub text: String,
+    pub id: Option<String>,
+}
+
+/// How source heading ids are written into the Markdown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnchorStyle {
+    /// Drop ids.
+    #[default]
+    None,
+    /// Attribute syntax: `## Usage {#usage}`.
+    Attr,
+    /// Inline HTML anchor: `## Usage <a id="usage"></a>`.
+    Html,
+}
+
+impl AnchorStyle {
+    /// Accepted names, the default first.
+    pub const CHOICES: &'static [&'static str] = &["none", "attr", "html"];
+}
+
+impl std::str::FromStr for AnchorStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "attr" => Ok(Self::Attr),
+            "html" => Ok(Self::Html),
+            other => Err(crate::util::invalid_choice(
+                "heading anchor style",
+                other,
+                Self::CHOICES,
+            )),
+        }
+    }
+}
+
+/// Append the source `id` to a heading element's converted Markdown (its ATX line), as
+/// `{#id}` or `<a id="id"></a>`. Called by the page visitor for the element itself.
+pub fn annotate_heading(heading_markdown: &str, id: &str, style: AnchorStyle) -> Option<String> {
+    let suffix = match style {
+        AnchorStyle::None => return None,
+        // Attribute ids end at whitespace or `}`; percent-encoding keeps `url#id` links valid.
+        AnchorStyle::Attr => format!(" {{#{}}}", encode_attr_id(id)),
+        AnchorStyle::Html => format!(" <a id=\"{}\"></a>", escape_html_attr(id)),
+    };
+    let start = heading_markdown.len() - heading_markdown.trim_start().len();
+    let line_end = heading_markdown[start..]
+        .find('\n')
+        .map_or(heading_markdown.len(), |i| start + i);
+    let line = &heading_markdown[..line_end];
+    atx_heading(line.trim_start())?;
+    Some(format!(
+        "{}{suffix}{}",
+        line.trim_end(),
+        &heading_markdown[line_end..]
+    ))
+}
+
+fn encode_attr_id(id: &str) -> String {
+    let mut out = String::with_capacity(id.len());
+    for c in id.chars() {
+        if c.is_whitespace() || matches!(c, '{' | '}' | '"' | '\'' | '%' | '\\' | '<' | '>') {
+            let mut buf = [0u8; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("%{b:02X}"));
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn escape_html_attr(id: &str) -> String {
+    id.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Manifest sub-entry for one section file of a split page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionEntry {
+    pub title: String,
+    /// Source heading `id`, so hits can be linked back to `url#anchor`.
+    pub anchor: Option<String>,
+    /// Page URL with the anchor as fragment (when known).
+    pub url: String,
+    pub cache_path: String,
+}
+
+/// One section produced by `split_markdown`.
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub title: String,
+    pub anchor: Option<String>,
+    /// File name inside the section directory, e.g. `01-introduction.md`.
+    pub file_name: String,
+    pub markdown: String,
+}
+
+/// Directory holding the section files of the page cached at `page_path`.
+pub fn section_dir(page_path: &Path) -> PathBuf {
+    page_path.with_extension("")
+}
+
+/// Hidden file in the section directory listing the section files gg wrote, so they can be
+/// told apart from child pages cached under the same directory.
+const SECTION_LIST: &str = ".gg-sections";
+
+/// Section files previously written for `page_path`, in order.
+pub fn section_files(page_path: &Path) -> Vec<PathBuf> {
+    let dir = section_dir(page_path);
+    match fs::read_to_string(dir.join(SECTION_LIST)) {
+        Ok(list) => list
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| dir.join(l.trim()))
+            .filter(|p| p.is_file())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Record the section files written for `page_path` (see `section_files`).
+pub fn write_section_list(page_path: &Path, sections: &[Section]) -> std::io::Result<()> {
+    let list: String = sections
+        .iter()
+        .map(|s| format!("{}\n", s.file_name))
+        .collect();
+    fs::write(section_dir(page_path).join(SECTION_LIST), list)
+}
+
+/// Split Markdown at H1/H2 headings (outside code fences).
+///
+/// Returns the preamble (content before the first split heading) and the sections, or `None`
+/// when the page has fewer than two split headings. `headings` supplies source anchors; they
+/// are matched to Markdown headings in document order by level and text.
+pub fn split_markdown(markdown: &str, headings: &[HeadingRef]) -> Option<(String, Vec<Section>)> {
+    let mut preamble = String::new();
+    let mut parts: Vec<(String, String)> = Vec::new();
+    let mut in_code = false;
+    let mut heading_cursor = 0;
+    let mut anchors: Vec<Option<String>> = Vec::new();
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+        }
+
+        let split_heading = if in_code { None } else { split_heading(line) };
+        if let Some((level, title)) = split_heading {
+            let anchor = match_anchor(headings, &mut heading_cursor, level, &title);
+            anchors.push(anchor);
+            parts.push((title, String::new()));
+        }
+
+        let buf = match parts.last_mut() {
+            Some((_, body)) => body,
+            None => &mut preamble,
+        };
+        buf.push_str(line);
+        buf.push('\n');
+    }
+
+    if parts.len() < 2 {
+        return None;
+    }
+
+    let width = parts.len().to_string().len().max(2);
+    let sections = parts
+        .into_iter()
+        .zip(anchors)
+        .enumerate()
+        .map(|(i, ((title, body), anchor))| {
+            let slug = slugify(&title);
+            let file_name = if slug.is_empty() {
+                format!("{:0width$}.md", i + 1)
+            } else {
+                format!("{:0width$}-{slug}.md", i + 1)
+            };
+            Section {
+                title,
+                anchor,
+                file_name,
+                markdown: body.trim_end().to_string() + "\n",
+            }
+        })
+        .collect();
+
+    Some((preamble, sections))
+}
+
+/// Index written in place of the full page: preamble plus links to the section files.
+pub fn index_markdown(preamble: &str, dir_name: &str, sections: &[Section]) -> String {
+    let mut out = preamble.trim_end().to_string();
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+    for s in sections {
+        out.push_str(&format!("- [{}]({dir_name}/{})\n", s.title, s.file_name));
+    }
+    out
+}
+
+/// Remove section files from a previous split so renamed headings do not leave strays.
+pub fn clear_section_files(page_path: &Path) {
+    for f in section_files(page_path) {
+        fs::remove_file(f).ok();
+    }
+    fs::remove_file(section_dir(page_path).join(SECTION_LIST)).ok();
+}
+
+/// Title of a cached page: its first heading (outside code fences), anchor removed.
+pub fn markdown_title(markdown: &str) -> Option<String> {
+    let mut in_code = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+        } else if !in_code {
+            if let Some((_, title)) = atx_heading(line) {
+                return Some(strip_anchor(title).to_string());
+            }
+        }
+    }
+    None
+}
+
+fn split_heading(line: &str) -> Option<(u32, String)> {
+    let (level, title) = atx_heading(line)?;
+    (level <= 2).then(|| (level, strip_anchor(title).to_string()))
+}
+
+/// Level and title of an ATX heading line.
+fn atx_heading(line: &str) -> Option<(u32, &str)> {
+    let level = line.bytes().take_while(|b| *b == b'#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level as u32, rest.trim()))
+}
+
+/// Remove an anchor written by `annotate_heading` from a heading title.
+fn strip_anchor(title: &str) -> &str {
+    if title.ends_with('}') {
+        if let Some(i) = title.rfind(" {#") {
+            return title[..i].trim_end();
+        }
+    }
+    if title.ends_with("></a>") {
+        if let Some(i) = title.rfind(" <a id=\"") {
+            return title[..i].trim_end();
+        }
+    }
+    title
+}
+
+fn match_anchor(
+    headings: &[HeadingRef],
+    cursor: &mut usize,
+    level: u32,
+    title: &str,
+) -> Option<String> {
+    let want = normalize(title);
+    let found = headings[*cursor..]
+        .iter()
+        .position(|h| h.level == level && normalize(&h.text) == want)?;
+    let h = &headings[*cursor + found];
+    *cursor += found + 1;
+    h.id.clone()
+}
+
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// File-name slug for a heading: lowercase ASCII alphanumerics separated by '-'.
+pub fn slugify(title: &str) -> String {
+    let mut out = String::new();
+    let mut dash = false;
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            if dash && !out.is_empty() {
+                out.push('-');
+            }
+            dash = false;
+            out.push(c.to_ascii_lowercase());
+        } else {
+            dash = true;
+        }
+        if out.len() >= 60 {
+            break;
+        }
+    }
+    out
+}
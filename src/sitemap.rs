@@ -1,26 +1,64 @@
 use anyhow::{anyhow, Context, Result};
 use flate2::read::GzDecoder;
+use futures_util::StreamExt;
 use quick_xml::events::Event;
 use quick_xml::Reader;
 use reqwest::Client;
-use std::collections::{HashSet, VecDeque};
+use std::collections::HashSet;
 use std::io::Read;
 use url::Url;
 
 use crate::http;
+use crate::util::parse_datetime;
+
+/// A `<url>` entry of a sitemap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapEntry {
+    pub url: Url,
+    /// `<lastmod>` in Unix seconds.
+    pub lastmod: Option<i64>,
+    /// `<changefreq>` as given (`daily`, `weekly`, ...).
+    pub changefreq: Option<String>,
+    /// `<priority>`, 0.0 to 1.0.
+    pub priority: Option<f32>,
+}
+
+impl SitemapEntry {
+    /// Priority with the protocol's default of 0.5 for entries that give none.
+    pub fn effective_priority(&self) -> f32 {
+        self.priority.unwrap_or(0.5)
+    }
+}
 
 #[derive(Debug, Default)]
-struct ParsedSitemap {
-    urls: Vec<Url>,
-    child_sitemaps: Vec<Url>,
+pub struct ParsedSitemap {
+    pub entries: Vec<SitemapEntry>,
+    pub child_sitemaps: Vec<Url>,
 }
 
-/// Attempt to discover and parse a site's sitemap(s), returning all URLs found.
+/// Sitemap documents fetched at once.
+const SITEMAP_CONCURRENCY: usize = 16;
+
+/// Attempt to discover and parse a site's sitemap(s), returning all entries found.
+///
+/// Every well-known location at the origin (and next to `base`, when it is a subdirectory) is
+/// probed, along with the `Sitemap:` lines of `robots.txt`. Index files are followed level by
+/// level with their children fetched concurrently. Entries and child sitemaps that cannot lie
+/// under `base` are dropped as soon as they are seen.
 ///
 /// This is used as a *seed* for crawling so that pages not reachable via
 /// in-page links can still be included.
-pub async fn discover_sitemap_urls(client: &Client, base: &Url, max_bytes: usize) -> Result<Vec<Url>> {
+pub async fn discover_sitemap_entries(
+    client: &Client,
+    base: &Url,
+    max_bytes: usize,
+) -> Result<Vec<SitemapEntry>> {
     let origin = origin_url(base)?;
+    // The crawl covers `base` as a directory, like `crawl::path_prefix`.
+    let mut prefix = base.path().to_string();
+    if !prefix.ends_with('/') {
+        prefix.push('/');
+    }
 
     let candidates = [
         "sitemap.xml",
@@ -29,58 +67,145 @@ pub async fn discover_sitemap_urls(client: &Client, base: &Url, max_bytes: usize
         "sitemap.xml.gz",
         "sitemap_index.xml.gz",
         "sitemap-index.xml.gz",
+        "sitemap.txt",
     ];
 
-    let mut root_sitemaps = Vec::new();
-    for name in candidates {
-        let url = origin.join(name).with_context(|| format!("bad sitemap url: {name}"))?;
-        let resp = match http::fetch_limited(client, url.clone(), max_bytes).await {
-            Ok(r) => r,
-            Err(_) => continue,
-        };
-        if resp.status.as_u16() == 404 {
-            continue;
+    let mut frontier: Vec<Url> = Vec::new();
+    let mut dirs = vec![origin.clone()];
+    if prefix != "/" {
+        dirs.push(
+            base.join(&prefix)
+                .with_context(|| format!("bad sitemap directory: {prefix}"))?,
+        );
+    }
+    for dir in &dirs {
+        for name in candidates {
+            frontier.push(
+                dir.join(name)
+                    .with_context(|| format!("bad sitemap url: {name}"))?,
+            );
         }
-        if !resp.status.is_success() {
-            continue;
+    }
+    if let Ok(robots) = origin.join("robots.txt") {
+        if let Ok(resp) = http::fetch_limited(client, robots, max_bytes).await {
+            if resp.status.is_success() {
+                frontier.extend(robots_sitemaps(&String::from_utf8_lossy(&resp.body)));
+            }
         }
-        root_sitemaps.push((url, resp.body));
-        // Use the first sitemap that exists; many sites have multiple, but fetching all can be expensive.
-        break;
     }
 
     let mut out = Vec::new();
     let mut seen_sitemaps: HashSet<String> = HashSet::new();
-    let mut queue: VecDeque<Url> = VecDeque::new();
+    let mut seen_urls: HashSet<String> = HashSet::new();
 
-    for (url, _) in &root_sitemaps {
-        queue.push_back(url.clone());
-    }
+    while !frontier.is_empty() {
+        let batch: Vec<Url> = frontier
+            .drain(..)
+            .filter(|u| seen_sitemaps.insert(u.as_str().to_string()))
+            .collect();
+        let mut fetches = futures_util::stream::iter(batch)
+            .map(|url| async move {
+                let resp = http::fetch_limited(client, url, max_bytes).await.ok()?;
+                if !resp.status.is_success() {
+                    return None;
+                }
+                let bytes = maybe_gunzip(&resp.body).ok()?;
+                parse_sitemap(&bytes).ok()
+            })
+            .buffer_unordered(SITEMAP_CONCURRENCY);
 
-    // We'll refetch the root sitemap URLs too, to keep logic uniform.
-    while let Some(sm_url) = queue.pop_front() {
-        let key = sm_url.as_str().to_string();
-        if !seen_sitemaps.insert(key) {
-            continue;
+        while let Some(parsed) = fetches.next().await {
+            let Some(parsed) = parsed else { continue };
+            for entry in parsed.entries {
+                if under_prefix(entry.url.path(), &prefix)
+                    && seen_urls.insert(entry.url.as_str().to_string())
+                {
+                    out.push(entry);
+                }
+            }
+            frontier.extend(
+                parsed
+                    .child_sitemaps
+                    .into_iter()
+                    .filter(|child| may_cover_prefix(child.path(), &prefix)),
+            );
         }
+    }
 
-        let resp = match http::fetch_limited(client, sm_url.clone(), max_bytes).await {
-            Ok(r) => r,
-            Err(_) => continue,
-        };
-        if !resp.status.is_success() {
+    Ok(out)
+}
+
+/// `Sitemap:` URLs listed in a robots.txt.
+pub fn robots_sitemaps(robots: &str) -> Vec<Url> {
+    robots
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if !key.trim().eq_ignore_ascii_case("sitemap") {
+                return None;
+            }
+            Url::parse(value.trim()).ok()
+        })
+        .collect()
+}
+
+/// Parse a sitemap document: XML (`<urlset>` or `<sitemapindex>`) or plain text with one URL
+/// per line.
+pub fn parse_sitemap(bytes: &[u8]) -> Result<ParsedSitemap> {
+    let text = String::from_utf8_lossy(bytes);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with('<') {
+        return parse_sitemap_xml(bytes);
+    }
+
+    let mut parsed = ParsedSitemap::default();
+    for line in text.lines() {
+        let Ok(url) = Url::parse(line.trim()) else {
             continue;
+        };
+        if matches!(url.scheme(), "http" | "https") {
+            parsed.entries.push(SitemapEntry {
+                url,
+                lastmod: None,
+                changefreq: None,
+                priority: None,
+            });
         }
+    }
+    Ok(parsed)
+}
 
-        let bytes = maybe_gunzip(&resp.body)?;
-        let parsed = parse_sitemap_xml(&bytes)?;
-        out.extend(parsed.urls);
-        for child in parsed.child_sitemaps {
-            queue.push_back(child);
-        }
+/// `path` up to and including its last `/`.
+fn dir_prefix(path: &str) -> String {
+    match path.rfind('/') {
+        Some(i) => path[..=i].to_string(),
+        None => "/".to_string(),
     }
+}
 
-    Ok(out)
+/// Whether a page path lies under the crawl prefix (a directory ending in `/`).
+fn under_prefix(path: &str, prefix: &str) -> bool {
+    path.starts_with(prefix) || path == prefix.trim_end_matches('/')
+}
+
+/// Whether a child sitemap could list pages under the prefix. Only children that sit inside
+/// another content section (`/blog/sitemap.xml` for `/docs/`) are skipped; ancestors
+/// (`/sitemap-1.xml`), descendants (`/docs/api/sitemap.xml`) and sitemap folders
+/// (`/sitemaps/sitemap-docs.xml`) are fetched and filtered by the URLs they list.
+fn may_cover_prefix(sitemap_path: &str, prefix: &str) -> bool {
+    let dir = dir_prefix(sitemap_path);
+    if prefix.starts_with(&dir) || dir.starts_with(prefix) {
+        return true;
+    }
+    let common = dir
+        .bytes()
+        .zip(prefix.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let common = dir[..common].rfind('/').map_or(0, |i| i + 1);
+    dir[common..]
+        .split('/')
+        .any(|segment| segment.to_ascii_lowercase().contains("sitemap"))
 }
 
 fn origin_url(base: &Url) -> Result<Url> {
@@ -88,7 +213,9 @@ fn origin_url(base: &Url) -> Result<Url> {
         .host_str()
         .ok_or_else(|| anyhow!("base URL has no host: {base}"))?;
     let scheme = base.scheme();
-    Url::parse(&format!("{scheme}://{host}/")).with_context(|| format!("failed to build origin for {base}"))
+    let port = base.port().map(|p| format!(":{p}")).unwrap_or_default();
+    Url::parse(&format!("{scheme}://{host}{port}/"))
+        .with_context(|| format!("failed to build origin for {base}"))
 }
 
 fn maybe_gunzip(bytes: &[u8]) -> Result<Vec<u8>> {
@@ -102,7 +229,8 @@ fn maybe_gunzip(bytes: &[u8]) -> Result<Vec<u8>> {
     Ok(bytes.to_vec())
 }
 
-fn parse_sitemap_xml(bytes: &[u8]) -> Result<ParsedSitemap> {
+/// Parse a sitemap or sitemap index.
+pub fn parse_sitemap_xml(bytes: &[u8]) -> Result<ParsedSitemap> {
     let mut reader = Reader::from_reader(bytes);
     reader.config_mut().trim_text(true);
 
@@ -116,47 +244,74 @@ fn parse_sitemap_xml(bytes: &[u8]) -> Result<ParsedSitemap> {
     }
 
     let mut ctx = Ctx::None;
-    let mut in_loc = false;
-    let mut loc = String::new();
+    // Child element of `<url>` / `<sitemap>` being read, and its text.
+    let mut field: Option<Vec<u8>> = None;
+    let mut text = String::new();
+    let mut loc: Option<Url> = None;
+    let mut lastmod: Option<i64> = None;
+    let mut changefreq: Option<String> = None;
+    let mut priority: Option<f32> = None;
 
     loop {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(e)) => {
                 let name = e.local_name();
                 match name.as_ref() {
-                    b"url" => ctx = Ctx::Url,
+                    b"url" => {
+                        ctx = Ctx::Url;
+                        loc = None;
+                        lastmod = None;
+                        changefreq = None;
+                        priority = None;
+                    }
                     b"sitemap" => ctx = Ctx::Sitemap,
-                    b"loc" => {
-                        in_loc = true;
-                        loc.clear();
+                    b"loc" | b"lastmod" | b"changefreq" | b"priority" => {
+                        field = Some(name.as_ref().to_vec());
+                        text.clear();
                     }
                     _ => {}
                 }
             }
-            Ok(Event::Text(e)) => {
-                if in_loc {
-                    loc.push_str(&e.unescape().unwrap_or_default());
-                }
+            Ok(Event::Text(e)) if field.is_some() => {
+                text.push_str(&e.unescape().unwrap_or_default());
+            }
+            Ok(Event::CData(e)) if field.is_some() => {
+                text.push_str(&String::from_utf8_lossy(&e));
             }
             Ok(Event::End(e)) => {
                 let name = e.local_name();
+                let value = text.trim();
                 match name.as_ref() {
                     b"loc" => {
-                        in_loc = false;
-                        let u = loc.trim();
-                        if !u.is_empty() {
-                            if let Ok(url) = Url::parse(u) {
-                                match ctx {
-                                    Ctx::Url => parsed.urls.push(url),
-                                    Ctx::Sitemap => parsed.child_sitemaps.push(url),
-                                    Ctx::None => {}
-                                }
+                        if let Ok(url) = Url::parse(value) {
+                            match ctx {
+                                Ctx::Url => loc = Some(url),
+                                Ctx::Sitemap => parsed.child_sitemaps.push(url),
+                                Ctx::None => {}
                             }
                         }
                     }
-                    b"url" | b"sitemap" => ctx = Ctx::None,
+                    b"lastmod" => lastmod = parse_datetime(value),
+                    b"changefreq" if !value.is_empty() => {
+                        changefreq = Some(value.to_ascii_lowercase())
+                    }
+                    b"priority" => priority = value.parse::<f32>().ok().map(|p| p.clamp(0.0, 1.0)),
+                    b"url" => {
+                        // `<loc>` may come after the other fields.
+                        if let Some(url) = loc.take() {
+                            parsed.entries.push(SitemapEntry {
+                                url,
+                                lastmod,
+                                changefreq: changefreq.take(),
+                                priority,
+                            });
+                        }
+                        ctx = Ctx::None;
+                    }
+                    b"sitemap" => ctx = Ctx::None,
                     _ => {}
                 }
+                field = None;
             }
             Ok(Event::Eof) => break,
             Err(e) => return Err(anyhow!("sitemap XML parse error: {e}")),
@@ -0,0 +1,325 @@
+//! Table rendering around the `html_to_markdown_rs` conversion.
+//!
+//! In the non-native modes, tables are cut out of the HTML before conversion and rendered
+//! from the DOM: `colspan`/`rowspan` are expanded into a regular grid and cell content
+//! (lists, code, nested tables) is flattened onto one line. The rendered tables are
+//! substituted back into the converted Markdown afterwards.
+
+use anyhow::Result;
+use tl::{HTMLTag, Parser};
+
+use crate::{
+    preprocess::{attr, child_tags, replace_elements, tag_name},
+    util::invalid_choice,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TableMode {
+    /// Leave tables to `html_to_markdown_rs`.
+    #[default]
+    Native,
+    /// Regular GFM pipe tables with spans expanded and single-line cells.
+    Gfm,
+    /// One line per row: `col: value; col: value`, designed for grep.
+    Rows,
+}
+
+impl TableMode {
+    /// Accepted names, the default first.
+    pub const CHOICES: &'static [&'static str] = &["native", "gfm", "rows"];
+}
+
+impl std::str::FromStr for TableMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "native" => Ok(Self::Native),
+            "gfm" => Ok(Self::Gfm),
+            "rows" => Ok(Self::Rows),
+            other => Err(invalid_choice("table mode", other, Self::CHOICES)),
+        }
+    }
+}
+
+/// Upper bound on expanded spans, mirroring `html_to_markdown_rs`.
+const MAX_SPAN: usize = 1000;
+
+#[derive(Debug, Clone, Default)]
+struct Cell {
+    text: String,
+    header: bool,
+}
+
+fn placeholder(n: usize) -> String {
+    format!("GGTABLEPLACEHOLDER{n}END")
+}
+
+/// Replace each outermost `<table>` with a placeholder paragraph and render it in `mode`.
+///
+/// `convert_cell` turns a cell's inner HTML into Markdown. Returns the rewritten HTML and
+/// the rendered tables, to be passed to `restore_tables` after conversion.
+pub fn extract_tables<F>(html: &str, mode: TableMode, mut convert_cell: F) -> (String, Vec<String>)
+where
+    F: FnMut(&str) -> String,
+{
+    let mut rendered: Vec<String> = Vec::new();
+    if mode == TableMode::Native {
+        return (html.to_string(), rendered);
+    }
+
+    let out = replace_elements(
+        html,
+        |tag, _| tag_name(tag) == "table",
+        |tag, parser| {
+            let table = render_table(tag, parser, mode, &mut convert_cell)?;
+            let p = placeholder(rendered.len());
+            rendered.push(table);
+            Some(format!("<p>{p}</p>"))
+        },
+    );
+    (out, rendered)
+}
+
+/// Substitute rendered tables for the placeholder lines left by `extract_tables`.
+pub fn restore_tables(markdown: &str, tables: &[String]) -> String {
+    if tables.is_empty() {
+        return markdown.to_string();
+    }
+    let mut out = String::with_capacity(markdown.len());
+    for line in markdown.lines() {
+        let hit = tables
+            .iter()
+            .enumerate()
+            .find(|(i, _)| line.contains(&placeholder(*i)));
+        match hit {
+            Some((_, table)) => {
+                out.push('\n');
+                out.push_str(table);
+                out.push_str("\n\n");
+            }
+            None => {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+fn render_table<F>(
+    table: &HTMLTag,
+    parser: &Parser,
+    mode: TableMode,
+    convert_cell: &mut F,
+) -> Option<String>
+where
+    F: FnMut(&str) -> String,
+{
+    let mut caption: Option<String> = None;
+    let mut rows: Vec<(&HTMLTag, bool)> = Vec::new();
+    collect_rows(table, parser, false, &mut rows, &mut caption, convert_cell);
+    if rows.is_empty() {
+        return None;
+    }
+
+    let sep = if mode == TableMode::Gfm {
+        "<br>"
+    } else {
+        " / "
+    };
+    let grid = build_grid(&rows, parser, sep, convert_cell);
+    let width = grid.iter().map(|r| r.len()).max().unwrap_or(0);
+    if width == 0 {
+        return None;
+    }
+
+    let header_rows = grid
+        .iter()
+        .take_while(|r| !r.is_empty() && r.iter().all(|c| c.header))
+        .count();
+
+    let mut out = String::new();
+    if let Some(c) = caption.filter(|c| !c.is_empty()) {
+        out.push_str("Table: ");
+        out.push_str(&c);
+        out.push_str("\n\n");
+    }
+
+    match mode {
+        TableMode::Gfm => render_gfm(&grid, header_rows, width, &mut out),
+        _ => render_rows(&grid, header_rows, width, &mut out),
+    }
+    Some(out.trim_end().to_string())
+}
+
+fn collect_rows<'p, 'a, F>(
+    tag: &'p HTMLTag<'a>,
+    parser: &'p Parser<'a>,
+    in_head: bool,
+    rows: &mut Vec<(&'p HTMLTag<'a>, bool)>,
+    caption: &mut Option<String>,
+    convert_cell: &mut F,
+) where
+    F: FnMut(&str) -> String,
+{
+    for child in child_tags(tag, parser) {
+        match tag_name(child).as_str() {
+            "tr" => rows.push((child, in_head)),
+            "thead" => collect_rows(child, parser, true, rows, caption, convert_cell),
+            "tbody" | "tfoot" => collect_rows(child, parser, false, rows, caption, convert_cell),
+            "caption" if caption.is_none() => {
+                *caption = Some(flatten_cell(&convert_cell(&child.inner_html(parser)), " "));
+            }
+            // Nested tables belong to a cell; never descend into them here.
+            _ => {}
+        }
+    }
+}
+
+fn build_grid<F>(
+    rows: &[(&HTMLTag, bool)],
+    parser: &Parser,
+    sep: &str,
+    convert_cell: &mut F,
+) -> Vec<Vec<Cell>>
+where
+    F: FnMut(&str) -> String,
+{
+    let mut grid: Vec<Vec<Option<Cell>>> = vec![Vec::new(); rows.len()];
+
+    for (r, (tr, in_head)) in rows.iter().enumerate() {
+        let mut col = 0;
+        for td in child_tags(tr, parser) {
+            let name = tag_name(td);
+            if name != "td" && name != "th" {
+                continue;
+            }
+            let span = |a: &str| {
+                attr(td, a)
+                    .and_then(|v| v.trim().parse::<usize>().ok())
+                    .unwrap_or(1)
+                    .clamp(1, MAX_SPAN)
+            };
+            let colspan = span("colspan");
+            let rowspan = span("rowspan").min(rows.len() - r);
+
+            while grid[r].get(col).is_some_and(|c| c.is_some()) {
+                col += 1;
+            }
+
+            let mut text = flatten_cell(&convert_cell(&td.inner_html(parser)), sep);
+            if sep == "<br>" {
+                text = escape_pipes(&text);
+            }
+            let cell = Cell {
+                text,
+                header: *in_head || name == "th",
+            };
+
+            // Spanned positions repeat the content so every row stays self-contained.
+            for row in grid.iter_mut().skip(r).take(rowspan) {
+                if row.len() < col + colspan {
+                    row.resize(col + colspan, None);
+                }
+                for slot in row.iter_mut().skip(col).take(colspan) {
+                    *slot = Some(cell.clone());
+                }
+            }
+            col += colspan;
+        }
+    }
+
+    grid.into_iter()
+        .map(|row| row.into_iter().map(|c| c.unwrap_or_default()).collect())
+        .collect()
+}
+
+/// Collapse converted cell Markdown onto one line, dropping code fences.
+fn flatten_cell(md: &str, sep: &str) -> String {
+    md.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with("```") && !l.starts_with("~~~"))
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+fn escape_pipes(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut prev = '\0';
+    for c in s.chars() {
+        if c == '|' && prev != '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+        prev = c;
+    }
+    out
+}
+
+/// Column names from the header rows; stacked header rows are joined with " / ".
+fn column_names(grid: &[Vec<Cell>], header_rows: usize, width: usize) -> Vec<String> {
+    (0..width)
+        .map(|c| {
+            let mut parts: Vec<&str> = Vec::new();
+            for row in &grid[..header_rows] {
+                let t = row.get(c).map(|cell| cell.text.as_str()).unwrap_or("");
+                if !t.is_empty() && !parts.contains(&t) {
+                    parts.push(t);
+                }
+            }
+            parts.join(" / ")
+        })
+        .collect()
+}
+
+fn render_gfm(grid: &[Vec<Cell>], header_rows: usize, width: usize, out: &mut String) {
+    // GFM always needs a header row; promote the first row if the table has none.
+    let (header, body) = if header_rows == 0 {
+        (column_names(grid, 1, width), &grid[1..])
+    } else {
+        (column_names(grid, header_rows, width), &grid[header_rows..])
+    };
+
+    push_gfm_row(out, header.iter().map(String::as_str), width);
+    out.push('|');
+    for _ in 0..width {
+        out.push_str(" --- |");
+    }
+    out.push('\n');
+    for row in body {
+        push_gfm_row(out, row.iter().map(|c| c.text.as_str()), width);
+    }
+}
+
+fn push_gfm_row<'s>(out: &mut String, cells: impl Iterator<Item = &'s str>, width: usize) {
+    let mut cells: Vec<&str> = cells.collect();
+    cells.resize(width, "");
+    out.push('|');
+    for c in cells {
+        out.push(' ');
+        out.push_str(c);
+        out.push_str(" |");
+    }
+    out.push('\n');
+}
+
+fn render_rows(grid: &[Vec<Cell>], header_rows: usize, width: usize, out: &mut String) {
+    let names = column_names(grid, header_rows, width);
+    for row in &grid[header_rows..] {
+        let parts: Vec<String> = row
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.text.is_empty())
+            .map(|(i, c)| match names.get(i).filter(|n| !n.is_empty()) {
+                Some(name) => format!("{name}: {}", c.text),
+                None => c.text.clone(),
+            })
+            .collect();
+        if parts.is_empty() {
+            continue;
+        }
+        out.push_str(&parts.join("; "));
+        out.push('\n');
+    }
+}
@@ -12,13 +12,84 @@ pub enum SourceSpec {
     CrawlRoot(Url),
     /// Crawl `root` (if needed) and then select only URLs matching the pattern.
     Pattern(UrlPattern),
+    /// `!`-prefixed pattern: drop matching pages from everything the other sources select.
+    Exclude(UrlPattern),
+    /// `re:` regex over page URLs; crawled and selected like `Pattern`.
+    Regex(UrlRegex),
 }
 
+impl SourceSpec {
+    /// The page, or the root a crawl for this source starts from.
+    pub fn root(&self) -> &Url {
+        match self {
+            Self::Page(url) | Self::CrawlRoot(url) => url,
+            Self::Pattern(pat) | Self::Exclude(pat) => &pat.root,
+            Self::Regex(re) => &re.root,
+        }
+    }
+}
+
+/// A glob over page URLs: `https://site/docs/**`.
+///
+/// A pattern may end in a query part, `https://site/docs/**?lang=en&v=2*`: a `?` starts it when
+/// the rest holds a `=` and no `/`, or when it follows `*` or `/` and the rest is only bare keys
+/// (`**?draft`); otherwise `?` is the one-character wildcard. Each `&`-joined
+/// condition requires that key with a value matching its glob (a bare key accepts any value);
+/// other parameters are ignored, and a pattern without a query part matches any query. Write
+/// `\?` to match the URL's `?` literally instead, which compares the query as plain text.
+///
+/// Each distinct query string of a page is cached as its own file, named `<page>__q<hash>.md` by
+/// `Cache::page_path`, so query patterns select among those files through the crawl manifest
+/// (never through the whole-subtree fast path).
 #[derive(Debug, Clone)]
 pub struct UrlPattern {
     pub original: String,
     pub root: Url,
+    /// Host wildcard such as `*.example.com`; `root` is then on the bare domain and the crawl
+    /// must also allow the matching hosts.
+    pub host_pattern: Option<String>,
+    alternatives: Vec<Alternative>,
+}
+
+/// One `{a,b}` alternative of a pattern, compiled.
+#[derive(Debug, Clone)]
+struct Alternative {
+    /// Matches the URL without fragment, and without query unless `query_text`.
     regex: Regex,
+    /// Query keys and value globs that must all be present.
+    query: Vec<(String, Regex)>,
+    /// The pattern spells out the query with `\?`, so `regex` sees the full URL.
+    query_text: bool,
+}
+
+impl Alternative {
+    fn compile(path: &str, query: Option<&str>) -> Result<Self> {
+        let regex = Regex::new(&format!("^{}$", glob_regex_body(path)?))
+            .with_context(|| format!("failed to compile regex from pattern: {path}"))?;
+        let mut conditions = Vec::new();
+        for cond in query.unwrap_or("").split('&').filter(|c| !c.is_empty()) {
+            let (key, value) = cond.split_once('=').unwrap_or((cond, "**"));
+            let value = Regex::new(&format!("^{}$", glob_regex_body(value)?))
+                .with_context(|| format!("failed to compile query condition: {cond}"))?;
+            conditions.push((unescape(key), value));
+        }
+        Ok(Self {
+            regex,
+            query: conditions,
+            query_text: path.contains("\\?"),
+        })
+    }
+
+    fn matches(&self, full: &str, without_query: &str, pairs: &[(String, String)]) -> bool {
+        if self.query_text {
+            return self.regex.is_match(full);
+        }
+        self.regex.is_match(without_query)
+            && self
+                .query
+                .iter()
+                .all(|(key, value)| pairs.iter().any(|(k, v)| k == key && value.is_match(v)))
+    }
 }
 
 impl UrlPattern {
@@ -26,12 +97,39 @@ impl UrlPattern {
         if !contains_glob(pattern) {
             return Err(anyhow!("pattern has no glob characters"));
         }
-        let root = pattern_root(pattern)?;
-        let regex = compile_glob_url_regex(pattern)?;
+        Self::compile(pattern)
+    }
+
+    /// Pattern for a `!` exclusion, where a plain URL (no globs) is also allowed.
+    pub fn exclusion(pattern: &str) -> Result<Self> {
+        Self::compile(pattern)
+    }
+
+    fn compile(pattern: &str) -> Result<Self> {
+        let expanded = expand_braces(pattern)?;
+        let mut host_pattern: Option<String> = None;
+        let mut concrete: Vec<String> = Vec::new();
+        let mut alternatives: Vec<Alternative> = Vec::new();
+        for alt in &expanded {
+            let (alt_concrete, host) = split_host_wildcard(alt)?;
+            if let Some(host) = host {
+                if host_pattern.as_ref().is_some_and(|h| *h != host) {
+                    return Err(anyhow!(
+                        "pattern alternatives use different host wildcards: {pattern}"
+                    ));
+                }
+                host_pattern = Some(host);
+            }
+            concrete.push(split_query(&alt_concrete).0.to_string());
+            let (path, query) = split_query(alt);
+            alternatives.push(Alternative::compile(path, query)?);
+        }
+        let root = common_root(&concrete)?;
         Ok(Self {
             original: pattern.to_string(),
             root,
-            regex,
+            host_pattern,
+            alternatives,
         })
     }
 
@@ -39,9 +137,12 @@ impl UrlPattern {
         contains_glob(s)
     }
 
+    /// Whether the pattern selects one whole subtree (`https://site/docs/**`); brace
+    /// alternatives such as `docs/{guide,reference}/**` select several and do not count.
     pub fn is_subtree_pattern(&self) -> bool {
         let p = self.original.as_str();
-        p.ends_with("/**/*") || p.ends_with("/**/*.*") || p.ends_with("/**")
+        self.alternatives.len() == 1
+            && (p.ends_with("/**/*") || p.ends_with("/**/*.*") || p.ends_with("/**"))
     }
 
     pub fn matches_url_string(&self, url: &str) -> bool {
@@ -53,12 +154,109 @@ impl UrlPattern {
 
     pub fn matches(&self, url: &Url) -> bool {
         // Match against the URL string without fragment.
+        let mut u = url.clone();
+        u.set_fragment(None);
+        let full = u.as_str().to_string();
+        let pairs: Vec<(String, String)> = u
+            .query_pairs()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        u.set_query(None);
+        self.alternatives
+            .iter()
+            .any(|alt| alt.matches(&full, u.as_str(), &pairs))
+    }
+}
+
+/// A regex over page URLs (fragment removed), anchored at both ends:
+/// `re:https://site/docs/v[2-9]/.*`.
+///
+/// The crawl root is the literal text before the first regex operator, cut back to its last `/`
+/// (`https://site/docs/` above). Give it explicitly as `re(ROOT):REGEX` when the regex starts
+/// with an operator or should crawl from a narrower directory.
+#[derive(Debug, Clone)]
+pub struct UrlRegex {
+    pub original: String,
+    pub root: Url,
+    regex: Regex,
+}
+
+impl UrlRegex {
+    /// Parse a `re:REGEX` or `re(ROOT):REGEX` source.
+    pub fn new(source: &str) -> Result<Self> {
+        let (root, pattern) = if let Some(rest) = source.strip_prefix("re(") {
+            let (root, pattern) = rest
+                .split_once("):")
+                .ok_or_else(|| anyhow!("expected re(ROOT):REGEX: {source}"))?;
+            let root = Url::parse(root).with_context(|| format!("invalid crawl root: {root}"))?;
+            (root, pattern)
+        } else {
+            let pattern = source
+                .strip_prefix("re:")
+                .ok_or_else(|| anyhow!("expected re:REGEX: {source}"))?;
+            (regex_root(pattern)?, pattern)
+        };
+        let regex = Regex::new(&format!("^(?:{pattern})$"))
+            .with_context(|| format!("invalid URL regex: {pattern}"))?;
+        Ok(Self {
+            original: source.to_string(),
+            root,
+            regex,
+        })
+    }
+
+    /// Whether a token is a regex source.
+    pub fn is_regex_source(s: &str) -> bool {
+        s.starts_with("re:") || s.starts_with("re(")
+    }
+
+    pub fn matches_url_string(&self, url: &str) -> bool {
+        match Url::parse(url) {
+            Ok(u) => self.matches(&u),
+            Err(_) => false,
+        }
+    }
+
+    pub fn matches(&self, url: &Url) -> bool {
         let mut u = url.clone();
         u.set_fragment(None);
         self.regex.is_match(u.as_str())
     }
 }
 
+/// Crawl root of a URL regex: its literal prefix up to the last `/`. An unescaped `.` counts as
+/// literal (hosts are rarely written `example\.com`) unless a quantifier follows it.
+fn regex_root(pattern: &str) -> Result<Url> {
+    let mut literal = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if !next.is_ascii_alphanumeric() => literal.push(next),
+                _ => break,
+            },
+            '.' if !matches!(chars.peek(), Some('*' | '+' | '?' | '{')) => literal.push('.'),
+            '^' | '$' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '|' | '.' => break,
+            _ => {
+                // A quantifier applies to the previous character, so that one is not literal.
+                if matches!(chars.peek(), Some('*' | '+' | '?' | '{')) {
+                    break;
+                }
+                literal.push(c);
+            }
+        }
+    }
+
+    let cut = literal.rfind('/').map_or(0, |i| i + 1);
+    let root = &literal[..cut];
+    Url::parse(root)
+        .ok()
+        .filter(|u| root.len() >= format!("{}://{}/", u.scheme(), u.authority()).len())
+        .ok_or_else(|| {
+            anyhow!("cannot infer a crawl root from regex (use re(ROOT):REGEX): {pattern}")
+        })
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct SourceParseOpts {
     /// If true, treat non-glob URLs as crawl roots.
@@ -70,6 +268,12 @@ pub struct SourceParseOpts {
 /// Parse a single URL/token into a `SourceSpec`.
 pub fn parse_source_token(token: &str, opts: SourceParseOpts) -> Result<SourceSpec> {
     let t = token.trim();
+    if UrlRegex::is_regex_source(t) {
+        return Ok(SourceSpec::Regex(UrlRegex::new(t)?));
+    }
+    if let Some(excluded) = t.strip_prefix('!').filter(|e| is_url_like(e)) {
+        return Ok(SourceSpec::Exclude(UrlPattern::exclusion(excluded)?));
+    }
     if !is_url_like(t) {
         return Err(anyhow!("not a URL: {t}"));
     }
@@ -93,14 +297,192 @@ pub fn parse_source_token(token: &str, opts: SourceParseOpts) -> Result<SourceSp
 }
 
 fn contains_glob(s: &str) -> bool {
-    s.contains('*') || s.contains('?') || s.contains('[')
+    s.contains('*') || s.contains('?') || s.contains('[') || s.contains('{')
+}
+
+/// Split a leading `*.` host wildcard off a pattern: returns the pattern on the bare domain
+/// (for finding the crawl root) and the host wildcard.
+fn split_host_wildcard(pattern: &str) -> Result<(String, Option<String>)> {
+    let Some((scheme, rest)) = pattern.split_once("://") else {
+        return Ok((pattern.to_string(), None));
+    };
+    let end = rest.find('/').unwrap_or(rest.len());
+    let authority = &rest[..end];
+    if !contains_glob(authority) {
+        return Ok((pattern.to_string(), None));
+    }
+
+    let domain = authority
+        .strip_prefix("*.")
+        .filter(|d| !d.is_empty() && !contains_glob(d))
+        .ok_or_else(|| anyhow!("only a leading `*.` wildcard is supported in hosts: {pattern}"))?;
+    let host = domain.split(':').next().unwrap_or(domain);
+    Ok((
+        format!("{scheme}://{domain}{}", &rest[end..]),
+        Some(format!("*.{}", host.to_ascii_lowercase())),
+    ))
+}
+
+/// Most alternatives a single pattern may expand to.
+const MAX_ALTERNATIVES: usize = 1024;
+
+/// Expand `{a,b}` alternatives (nesting allowed) into one pattern per combination.
+fn expand_braces(pattern: &str) -> Result<Vec<String>> {
+    let Some((open, _)) = unescaped_chars(pattern).find(|&(_, c)| c == '{') else {
+        return Ok(vec![pattern.to_string()]);
+    };
+
+    let mut depth = 0;
+    let mut close = None;
+    let mut bounds = vec![open];
+    for (i, c) in unescaped_chars(pattern).skip_while(|&(i, _)| i < open) {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+            ',' if depth == 1 => bounds.push(i),
+            _ => {}
+        }
+    }
+    let close = close.ok_or_else(|| anyhow!("unclosed '{{' in pattern: {pattern}"))?;
+    bounds.push(close);
+
+    let (head, tail) = (&pattern[..open], &pattern[close + 1..]);
+    let mut out = Vec::new();
+    for w in bounds.windows(2) {
+        let alternative = &pattern[w[0] + 1..w[1]];
+        out.extend(expand_braces(&format!("{head}{alternative}{tail}"))?);
+        if out.len() > MAX_ALTERNATIVES {
+            return Err(anyhow!(
+                "pattern expands to more than {MAX_ALTERNATIVES} alternatives: {pattern}"
+            ));
+        }
+    }
+    Ok(out)
+}
+
+/// Characters of a pattern with their byte offsets, leaving out `\`-escaped ones.
+fn unescaped_chars(pattern: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut escaped = false;
+    pattern.char_indices().filter(move |&(_, c)| {
+        if escaped {
+            escaped = false;
+            return false;
+        }
+        if c == '\\' {
+            escaped = true;
+            return false;
+        }
+        true
+    })
+}
+
+/// Drop the `\` of escaped characters.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                out.push(next);
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Whether a pattern has an unescaped `*`, `?` or `[`.
+fn has_wildcard(s: &str) -> bool {
+    unescaped_chars(s).any(|(_, c)| matches!(c, '*' | '?' | '['))
+}
+
+/// Split a pattern at the `?` that starts its query part, if any (see `UrlPattern`).
+fn split_query(pattern: &str) -> (&str, Option<&str>) {
+    let mut in_class = false;
+    for (i, c) in unescaped_chars(pattern) {
+        match c {
+            '[' if !in_class => in_class = true,
+            ']' if in_class => in_class = false,
+            '?' if !in_class => {
+                let rest = &pattern[i + 1..];
+                if !rest.contains('/')
+                    && (rest.contains('=')
+                        || pattern[..i].ends_with(['*', '/']) && is_bare_keys(rest))
+                {
+                    return (&pattern[..i], Some(rest));
+                }
+            }
+            _ => {}
+        }
+    }
+    (pattern, None)
+}
+
+/// Whether a query part is only bare keys, `draft&beta`.
+fn is_bare_keys(query: &str) -> bool {
+    query.split('&').all(|key| {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    })
+}
+
+/// The deepest directory containing the crawl roots of every alternative, so one crawl covers
+/// them all. A plain URL alternative contributes its directory.
+fn common_root(alternatives: &[String]) -> Result<Url> {
+    let mut roots: Vec<String> = Vec::new();
+    for alt in alternatives {
+        let root = if has_wildcard(alt) {
+            pattern_root(alt)?
+        } else {
+            Url::parse(&unescape(alt))
+                .with_context(|| format!("invalid URL: {alt}"))?
+                .join("./")
+                .with_context(|| format!("failed to find directory of {alt}"))?
+        };
+        roots.push(root.as_str().to_string());
+    }
+
+    let first = roots.first().ok_or_else(|| anyhow!("empty pattern"))?;
+    let mut common = first.as_str();
+    for r in &roots[1..] {
+        let len = common
+            .char_indices()
+            .zip(r.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8());
+        common = &common[..len];
+    }
+    let common = match common.rfind('/') {
+        Some(i) => &common[..=i],
+        None => "",
+    };
+
+    let root = Url::parse(common).ok().filter(|u| {
+        let origin = format!("{}://{}/", u.scheme(), u.authority());
+        common.len() >= origin.len()
+    });
+    root.ok_or_else(|| {
+        anyhow!(
+            "pattern alternatives must share a scheme and host: {}",
+            alternatives.join(" ")
+        )
+    })
 }
 
 /// Determine the crawl root of a glob URL by taking everything up to the last '/' before the
 /// first glob character.
 fn pattern_root(pattern: &str) -> Result<Url> {
-    let first_glob = pattern
-        .char_indices()
+    let first_glob = unescaped_chars(pattern)
         .find(|(_, c)| matches!(c, '*' | '?' | '['))
         .map(|(i, _)| i)
         .context("glob pattern is missing wildcard")?;
@@ -116,20 +498,22 @@ fn pattern_root(pattern: &str) -> Result<Url> {
     if root_str.ends_with("://") {
         root_str = pattern;
     }
+    let root_str = unescape(root_str);
 
-    Url::parse(root_str).with_context(|| format!("failed to parse crawl root from pattern: {root_str}"))
+    Url::parse(&root_str)
+        .with_context(|| format!("failed to parse crawl root from pattern: {root_str}"))
 }
 
-/// Convert a glob URL pattern into a regex that matches full URLs.
+/// Convert a glob URL pattern into the body of a regex that matches URLs.
 ///
 /// Supported glob syntax:
 /// - `*` matches any characters except '/'
 /// - `**` matches any characters (including '/')
 /// - `?` matches a single character except '/'
-/// - Character classes like `[abc]` are passed through (best-effort)
-fn compile_glob_url_regex(pattern: &str) -> Result<Regex> {
+/// - `[abc]`, `[a-z]` and negated `[!abc]` / `[^abc]` match one character (never '/')
+/// - `\` makes the next character literal (`\*`, `\?`, `\[`, `\{`)
+fn glob_regex_body(pattern: &str) -> Result<String> {
     let mut out = String::with_capacity(pattern.len() * 2);
-    out.push('^');
 
     let mut chars = pattern.chars().peekable();
     while let Some(c) = chars.next() {
@@ -143,18 +527,35 @@ fn compile_glob_url_regex(pattern: &str) -> Result<Regex> {
                 }
             }
             '?' => out.push_str("[^/]{1}"),
+            '\\' => match chars.next() {
+                Some(lit) => out.push_str(&regex::escape(&lit.to_string())),
+                None => out.push_str("\\\\"),
+            },
             '[' => {
-                // Best effort: copy until closing ']' without interpreting.
-                out.push('[');
+                let negated = matches!(chars.peek(), Some('!' | '^'));
+                if negated {
+                    chars.next();
+                }
+                // A `]` right after the opening bracket is a literal member.
+                let mut members: Vec<char> = Vec::new();
+                let mut closed = false;
                 for nc in chars.by_ref() {
-                    out.push(nc);
-                    if nc == ']' {
+                    if nc == ']' && !members.is_empty() {
+                        closed = true;
                         break;
                     }
+                    members.push(nc);
+                }
+                if !closed {
+                    return Err(anyhow!("unclosed '[' in pattern: {pattern}"));
                 }
+                out.push_str(
+                    &char_class(&members, negated)
+                        .with_context(|| format!("in pattern: {pattern}"))?,
+                );
             }
             // Escape regex metacharacters.
-            '.' | '+' | '(' | ')' | '|' | '^' | '$' | '{' | '}' | '\\' => {
+            '.' | '+' | '(' | ')' | '|' | '^' | '$' | '{' | '}' | ']' => {
                 out.push('\\');
                 out.push(c);
             }
@@ -162,6 +563,40 @@ fn compile_glob_url_regex(pattern: &str) -> Result<Regex> {
         }
     }
 
-    out.push('$');
-    Regex::new(&out).with_context(|| format!("failed to compile regex from pattern: {pattern}"))
+    Ok(out)
+}
+
+/// Regex class for the members of a glob `[...]`, validating ranges.
+fn char_class(members: &[char], negated: bool) -> Result<String> {
+    fn push_escaped(out: &mut String, c: char) {
+        if matches!(c, '\\' | '[' | ']' | '^' | '-' | '&' | '~') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+
+    // Like `?`, a class never matches the path separator.
+    if !negated && members.contains(&'/') {
+        return Err(anyhow!("character class cannot match '/'"));
+    }
+    let mut out = String::from(if negated { "[^/" } else { "[" });
+    let mut i = 0;
+    while i < members.len() {
+        let c = members[i];
+        if i + 2 < members.len() && members[i + 1] == '-' {
+            let end = members[i + 2];
+            if c > end {
+                return Err(anyhow!("invalid character range {c}-{end}"));
+            }
+            push_escaped(&mut out, c);
+            out.push('-');
+            push_escaped(&mut out, end);
+            i += 3;
+        } else {
+            push_escaped(&mut out, c);
+            i += 1;
+        }
+    }
+    out.push(']');
+    Ok(out)
 }
@@ -1,7 +1,21 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Component, Path},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
 use url::Url;
 
+/// Read and deserialize a TOML file.
+pub fn read_toml<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))
+}
+
 pub fn now_unix_secs() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -14,19 +28,94 @@ pub fn is_url_like(s: &str) -> bool {
     s.starts_with("https://") || s.starts_with("http://")
 }
 
+/// Split a comma-separated list, leaving commas inside `{a,b}` glob alternatives alone.
 pub fn split_comma_separated(s: &str) -> Vec<String> {
-    s.split(',')
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+        .into_iter()
         .map(|p| p.trim())
         .filter(|p| !p.is_empty())
         .map(|p| p.to_string())
         .collect()
 }
 
+/// Entries of a URL list: one URL or glob per line, with blank lines and `#` comments ignored.
+/// A `#` starts a comment only at the start of a line or after whitespace, so fragments survive.
+pub fn parse_url_list(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| {
+            let line = line.trim();
+            if line.starts_with('#') {
+                return "";
+            }
+            match line.find(" #").or_else(|| line.find("\t#")) {
+                Some(i) => line[..i].trim_end(),
+                None => line,
+            }
+        })
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 pub fn strip_fragment(mut url: Url) -> Url {
     url.set_fragment(None);
     url
 }
 
+/// Hosts a crawl may visit: exact names plus `*.suffix` wildcards (subdomains only).
+#[derive(Debug, Clone, Default)]
+pub struct HostSet {
+    exact: HashSet<String>,
+    suffixes: Vec<String>,
+}
+
+impl HostSet {
+    /// Add an exact host or a `*.example.com` wildcard.
+    pub fn insert(&mut self, pattern: &str) {
+        let p = pattern.trim().to_ascii_lowercase();
+        match p.strip_prefix("*.") {
+            Some(suffix) => self.suffixes.push(format!(".{suffix}")),
+            None => {
+                self.exact.insert(p);
+            }
+        }
+    }
+
+    pub fn contains(&self, host: &str) -> bool {
+        let h = host.to_ascii_lowercase();
+        self.exact.contains(&h) || self.suffixes.iter().any(|s| h.ends_with(s.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.suffixes.is_empty()
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for HostSet {
+    fn from_iter<I: IntoIterator<Item = S>>(patterns: I) -> Self {
+        let mut set = Self::default();
+        for pattern in patterns {
+            set.insert(pattern.as_ref());
+        }
+        set
+    }
+}
+
 pub fn host_variants(host: &str) -> Vec<String> {
     let h = host.to_ascii_lowercase();
     if let Some(rest) = h.strip_prefix("www.") {
@@ -35,3 +124,239 @@ pub fn host_variants(host: &str) -> Vec<String> {
         vec![h.clone(), format!("www.{h}")]
     }
 }
+
+/// Relative, `/`-separated link from directory `from_dir` to file `to` (for Markdown links).
+pub fn relative_link(from_dir: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from_dir.components().collect();
+    let target: Vec<Component> = to.components().collect();
+    let common = from
+        .iter()
+        .zip(target.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<String> = Vec::new();
+    for _ in common..from.len() {
+        parts.push("..".to_string());
+    }
+    for c in &target[common..] {
+        parts.push(c.as_os_str().to_string_lossy().to_string());
+    }
+    parts.join("/")
+}
+
+/// Split a command line into arguments: whitespace-separated, with single and double quotes
+/// grouping words (no escapes or expansion).
+pub fn split_command_line(s: &str) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    let mut cur = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    for c in s.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => cur.push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                in_word = true;
+            }
+            None if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut cur));
+                    in_word = false;
+                }
+            }
+            None => {
+                cur.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        args.push(cur);
+    }
+    args
+}
+
+/// `s` usable as a file name: `/`, `\` and control characters become `-`, and leading `-` and
+/// surrounding whitespace are dropped so the name cannot be taken for a flag.
+pub fn filename_safe(s: &str) -> String {
+    let replaced: String = s
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect();
+    replaced
+        .trim()
+        .trim_start_matches(|c: char| c == '-' || c.is_whitespace())
+        .to_string()
+}
+
+/// Error for a value that is none of `choices`: `invalid image mode: x (expected drop, alt or
+/// download)`.
+pub fn invalid_choice(what: &str, got: &str, choices: &[&str]) -> anyhow::Error {
+    let expected = match choices {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [init @ .., last] => format!("{} or {last}", init.join(", ")),
+    };
+    anyhow!("invalid {what}: {got} (expected {expected})")
+}
+
+/// Parse a duration such as `90`, `45s`, `30m`, `12h`, `7d` or `2w` into seconds.
+pub fn parse_duration_secs(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, unit) = s.split_at(split);
+    let n: u64 = digits
+        .parse()
+        .map_err(|_| anyhow!("invalid duration: {s}"))?;
+    let scale = match unit.trim() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(anyhow!(
+                "invalid duration: {s} (expected e.g. 90s, 30m, 12h, 7d)"
+            ))
+        }
+    };
+    n.checked_mul(scale)
+        .ok_or_else(|| anyhow!("duration too long: {s}"))
+}
+
+/// Parse a feed/sitemap timestamp into Unix seconds: RFC 3339 / W3C datetime
+/// (`2024-05-01`, `2024-05-01T10:00:00+02:00`, also `2024` and `2024-05`) or RFC 2822
+/// (`Wed, 01 May 2024 10:00:00 GMT`).
+pub fn parse_datetime(s: &str) -> Option<i64> {
+    let s = s.trim();
+    if s.as_bytes().first()?.is_ascii_digit()
+        && s.len() >= 4
+        && s.as_bytes()[..4].iter().all(u8::is_ascii_digit)
+    {
+        parse_rfc3339(s)
+    } else {
+        parse_rfc2822(s)
+    }
+}
+
+fn parse_rfc3339(s: &str) -> Option<i64> {
+    let (date, time) = match s.find(['T', 't', ' ']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let mut parts = date.split('-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next().map_or(Some(1), |m| m.parse().ok())?;
+    let day: u32 = parts.next().map_or(Some(1), |d| d.parse().ok())?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let (secs, offset) = match time {
+        Some(t) => {
+            let (clock, offset) = match t.find(['Z', 'z', '+', '-']) {
+                Some(i) => (&t[..i], parse_offset(&t[i..])?),
+                None => (t, 0),
+            };
+            (parse_clock(clock)?, offset)
+        }
+        None => (0, 0),
+    };
+    Some(unix_from_parts(year, month, day)? + secs - offset)
+}
+
+fn parse_rfc2822(s: &str) -> Option<i64> {
+    // Drop the optional weekday.
+    let s = match s.find(',') {
+        Some(i) => &s[i + 1..],
+        None => s,
+    };
+    let mut it = s.split_whitespace();
+    let day: u32 = it.next()?.parse().ok()?;
+    let month = month_number(it.next()?)?;
+    let mut year: i64 = it.next()?.parse().ok()?;
+    if year < 100 {
+        year += if year < 50 { 2000 } else { 1900 };
+    }
+    let secs = match it.next() {
+        Some(clock) => parse_clock(clock)?,
+        None => 0,
+    };
+    let offset = match it.next() {
+        Some(zone) => parse_offset(zone)?,
+        None => 0,
+    };
+    Some(unix_from_parts(year, month, day)? + secs - offset)
+}
+
+/// `HH:MM[:SS[.frac]]` as seconds since midnight.
+fn parse_clock(s: &str) -> Option<i64> {
+    let mut parts = s.split(':');
+    let h: i64 = parts.next()?.parse().ok()?;
+    let m: i64 = parts.next()?.parse().ok()?;
+    let sec: i64 = match parts.next() {
+        Some(p) => p.split('.').next()?.parse().ok()?,
+        None => 0,
+    };
+    (h < 24 && m < 60 && sec <= 60).then_some(h * 3600 + m * 60 + sec)
+}
+
+/// Zone offset in seconds east of UTC: `Z`, `+02:00`, `-0500`, `GMT`, `EST`, ...
+fn parse_offset(s: &str) -> Option<i64> {
+    let named = match s.to_ascii_uppercase().as_str() {
+        "Z" | "UT" | "UTC" | "GMT" => Some(0),
+        "EST" => Some(-5),
+        "EDT" => Some(-4),
+        "CST" => Some(-6),
+        "CDT" => Some(-5),
+        "MST" => Some(-7),
+        "MDT" => Some(-6),
+        "PST" => Some(-8),
+        "PDT" => Some(-7),
+        _ => None,
+    };
+    if let Some(hours) = named {
+        return Some(hours * 3600);
+    }
+    let sign = match s.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let digits: String = s[1..].chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.len() != 4 {
+        return None;
+    }
+    let h: i64 = digits[..2].parse().ok()?;
+    let m: i64 = digits[2..].parse().ok()?;
+    Some(sign * (h * 3600 + m * 60))
+}
+
+fn month_number(s: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let s = s.get(..3)?.to_ascii_lowercase();
+    MONTHS.iter().position(|m| *m == s).map(|i| i as u32 + 1)
+}
+
+/// Unix seconds at midnight UTC of a civil date (days-from-civil algorithm). Years outside
+/// 1..=9999 are rejected, which also keeps the arithmetic from overflowing.
+fn unix_from_parts(year: i64, month: u32, day: u32) -> Option<i64> {
+    if !(1..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some((era * 146_097 + doe - 719_468) * 86_400)
+}
@@ -0,0 +1,373 @@
+//! Documentation-site widgets: math, admonitions and tab groups.
+//!
+//! Math and tab groups are rewritten in the HTML before conversion: rendered math is only
+//! recoverable from its TeX annotation (or a `<script type="math/tex">`, which the converter
+//! strips), and tab labels are usually buttons inside a `role="tablist"`, which the converter
+//! drops as navigation. Admonitions survive conversion and are reshaped by the page visitor
+//! using `admonition_kind` / `is_admonition_title` / `format_admonition`.
+
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use regex::Regex;
+use tl::{HTMLTag, Node, Parser};
+
+use crate::preprocess::{attr, replace_elements, tag_name};
+
+fn placeholder(n: usize) -> String {
+    format!("GGMATHPLACEHOLDER{n}END")
+}
+
+/// Replace rendered math (KaTeX, MathJax, MathML) with placeholders carrying its TeX source.
+///
+/// Returns the rewritten HTML and the `$...$` / `$$...$$` replacements, to be passed to
+/// `restore_math` after conversion. Math without a TeX source is left to the converter.
+pub fn extract_math(html: &str) -> (String, Vec<String>) {
+    let mut tex: Vec<String> = Vec::new();
+
+    // MathJax 2 sources are raw TeX (`a < b`), which an HTML parser would misread as markup.
+    let html = math_script_regex().replace_all(html, |caps: &regex::Captures| {
+        let source = collapse(&caps[2]);
+        if source.is_empty() {
+            return String::new();
+        }
+        let p = placeholder(tex.len());
+        if caps[1].contains("mode=display") {
+            tex.push(format!("$${source}$$"));
+            format!("<p>{p}</p>")
+        } else {
+            tex.push(format!("${source}$"));
+            format!("<span>{p}</span>")
+        }
+    });
+
+    let out = replace_elements(
+        &html,
+        |tag, _| is_math(tag),
+        |tag, parser| {
+            // MathJax 2 keeps the TeX in a sibling script; its rendered output is noise.
+            if is_mathjax2_output(tag) {
+                return Some(String::new());
+            }
+            let (source, display) = math_source(tag, parser)?;
+            let p = placeholder(tex.len());
+            if display {
+                tex.push(format!("$${source}$$"));
+                Some(format!("<p>{p}</p>"))
+            } else {
+                tex.push(format!("${source}$"));
+                Some(format!("<span>{p}</span>"))
+            }
+        },
+    );
+    (out, tex)
+}
+
+/// Substitute TeX for the placeholders left by `extract_math`.
+pub fn restore_math(markdown: &str, tex: &[String]) -> String {
+    let mut out = markdown.to_string();
+    // Highest index first so `...1END` never matches inside `...10END`.
+    for (i, t) in tex.iter().enumerate().rev() {
+        out = out.replace(&placeholder(i), t);
+    }
+    out
+}
+
+fn math_script_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"(?is)<script[^>]*\btype\s*=\s*["']?(math/tex[^"'>]*)["']?[^>]*>(.*?)</script>"#,
+        )
+        .unwrap()
+    })
+}
+
+fn is_math(tag: &HTMLTag) -> bool {
+    let name = tag_name(tag);
+    if name == "math" || name == "mjx-container" {
+        return true;
+    }
+    let classes = class_tokens(tag);
+    classes.iter().any(|c| c == "katex" || c == "katex-display") || is_mathjax2_output(tag)
+}
+
+fn is_mathjax2_output(tag: &HTMLTag) -> bool {
+    class_tokens(tag).iter().any(|c| {
+        matches!(
+            c.as_str(),
+            "MathJax"
+                | "MathJax_Display"
+                | "MathJax_Preview"
+                | "MathJax_SVG"
+                | "MathJax_CHTML"
+                | "MathJax_SVG_Display"
+        )
+    })
+}
+
+/// TeX source of a math element and whether it is display math.
+fn math_source(tag: &HTMLTag, parser: &Parser) -> Option<(String, bool)> {
+    let name = tag_name(tag);
+    let display = match name.as_str() {
+        "math" => attr(tag, "display").is_some_and(|d| d == "block"),
+        "mjx-container" => attr(tag, "display").is_some_and(|d| d == "true" || d == "block"),
+        _ => class_tokens(tag).iter().any(|c| c == "katex-display"),
+    };
+
+    let mut alttext: Option<String> = if name == "math" {
+        attr(tag, "alttext")
+    } else {
+        None
+    };
+    for node in tag.children().all(parser) {
+        let Node::Tag(t) = node else { continue };
+        match tag_name(t).as_str() {
+            "annotation" if attr(t, "encoding").is_some_and(|e| e.contains("tex")) => {
+                let source = collapse(&decode_entities(&t.inner_text(parser)));
+                if !source.is_empty() {
+                    return Some((source, display));
+                }
+            }
+            "math" if alttext.is_none() => alttext = attr(t, "alttext"),
+            _ => {}
+        }
+    }
+    let source = collapse(&decode_entities(&alttext?));
+    (!source.is_empty()).then_some((source, display))
+}
+
+/// Rewrite tab groups into consecutive sections, each panel preceded by its bold label.
+pub fn expand_tabs(html: &str) -> String {
+    replace_elements(html, is_tab_group, |tag, parser| {
+        let mut labels: Vec<String> = Vec::new();
+        let mut panels: Vec<String> = Vec::new();
+        collect_tabs(tag, parser, &mut labels, &mut panels);
+        if panels.is_empty() {
+            return None;
+        }
+
+        let mut out = String::from("<div>");
+        for (i, panel) in panels.iter().enumerate() {
+            if let Some(label) = labels.get(i).filter(|l| !l.trim().is_empty()) {
+                out.push_str(&format!("<p><strong>{}</strong></p>", label.trim()));
+            }
+            // Panels may hold their own tab groups.
+            out.push_str(&format!("<div>{}</div>", expand_tabs(panel)));
+        }
+        out.push_str("</div>");
+        Some(out)
+    })
+}
+
+/// The innermost element holding both tab labels and tab panels.
+fn is_tab_group(tag: &HTMLTag, parser: &Parser) -> bool {
+    if !has_tabs(tag, parser) {
+        return false;
+    }
+    !tag.children()
+        .top()
+        .iter()
+        .filter_map(|h| h.get(parser).and_then(|n| n.as_tag()))
+        .any(|child| has_tabs(child, parser))
+}
+
+fn has_tabs(tag: &HTMLTag, parser: &Parser) -> bool {
+    let (mut label, mut panel) = (false, false);
+    for node in tag.children().all(parser) {
+        if let Node::Tag(t) = node {
+            label |= is_tab_label(t) || is_label_group(t);
+            panel |= is_tab_panel(t);
+        }
+    }
+    label && panel
+}
+
+fn is_tab_label(tag: &HTMLTag) -> bool {
+    if attr(tag, "role").is_some_and(|r| r == "tab") {
+        return true;
+    }
+    // sphinx-design; see `is_label_group` for MkDocs Material.
+    tag_name(tag) == "label" && class_tokens(tag).iter().any(|c| c == "sd-tab-label")
+}
+
+/// MkDocs Material puts plain `<label>`s in a `.tabbed-labels` strip.
+fn is_label_group(tag: &HTMLTag) -> bool {
+    class_tokens(tag).iter().any(|c| c == "tabbed-labels")
+}
+
+fn is_tab_panel(tag: &HTMLTag) -> bool {
+    attr(tag, "role").is_some_and(|r| r == "tabpanel")
+        || class_tokens(tag)
+            .iter()
+            .any(|c| c == "tabbed-block" || c == "sd-tab-content")
+}
+
+/// Labels and panels in document order; panels are not searched for further labels.
+fn collect_tabs(
+    tag: &HTMLTag,
+    parser: &Parser,
+    labels: &mut Vec<String>,
+    panels: &mut Vec<String>,
+) {
+    let in_tabbed_labels = is_label_group(tag);
+    for h in tag.children().top().iter() {
+        let Some(Node::Tag(child)) = h.get(parser) else {
+            continue;
+        };
+        if is_tab_panel(child) {
+            panels.push(child.inner_html(parser));
+        } else if is_tab_label(child) || (in_tabbed_labels && tag_name(child) == "label") {
+            labels.push(child.inner_html(parser));
+        } else {
+            collect_tabs(child, parser, labels, panels);
+        }
+    }
+}
+
+/// Container class names that mark an admonition, across common doc generators.
+const ADMONITION_CLASSES: &[&str] = &[
+    "admonition",
+    "markdown-alert",
+    "theme-admonition",
+    "callout",
+    "starlight-aside",
+];
+
+const ADMONITION_KINDS: &[&str] = &[
+    "note",
+    "tip",
+    "hint",
+    "info",
+    "important",
+    "warning",
+    "caution",
+    "danger",
+    "error",
+    "attention",
+    "example",
+    "question",
+    "success",
+    "failure",
+    "bug",
+    "abstract",
+    "seealso",
+    "todo",
+];
+
+/// Admonition kind (`note`, `warning`, ...) if the element is an admonition container.
+pub fn admonition_kind(tag: &str, attributes: &BTreeMap<String, String>) -> Option<String> {
+    let class = attributes.get("class")?;
+    let tokens: Vec<&str> = class.split_whitespace().collect();
+    let container = tokens.iter().any(|t| ADMONITION_CLASSES.contains(t));
+    // MkDocs `??? note` renders as a bare `<details class="note">`.
+    let collapsible = tag == "details";
+    if !container && !collapsible {
+        return None;
+    }
+
+    let kind = tokens.iter().find_map(|t| {
+        let t = t.to_ascii_lowercase();
+        let kind = ADMONITION_CLASSES
+            .iter()
+            .find_map(|prefix| {
+                t.strip_prefix(prefix)
+                    .map(|rest| rest.trim_start_matches('-').to_string())
+            })
+            .unwrap_or(t);
+        ADMONITION_KINDS.contains(&kind.as_str()).then_some(kind)
+    });
+    match kind {
+        Some(k) => Some(k),
+        None if container => Some("note".to_string()),
+        None => None,
+    }
+}
+
+/// Whether the element is the title of an admonition (`<p class="admonition-title">` etc.).
+pub fn is_admonition_title(tag: &str, attributes: &BTreeMap<String, String>) -> bool {
+    if tag == "summary" {
+        return true;
+    }
+    attributes.get("class").is_some_and(|class| {
+        class.split_whitespace().any(|c| {
+            matches!(
+                c,
+                "admonition-title"
+                    | "markdown-alert-title"
+                    | "callout-title"
+                    | "starlight-aside__title"
+            ) || c.starts_with("admonitionHeading")
+        })
+    })
+}
+
+/// Render an admonition as a blockquote led by `**Kind:**`, followed by the title if the source
+/// gave it a custom one.
+pub fn format_admonition(kind: &str, title: Option<&str>, body: &str) -> String {
+    // Titles often carry an icon, which converts to an image.
+    let title = title
+        .map(|t| image_md_regex().replace_all(t, ""))
+        .map(|t| {
+            t.trim()
+                .trim_matches('*')
+                .trim()
+                .trim_end_matches(':')
+                .trim()
+                .to_string()
+        })
+        .filter(|t| !t.is_empty() && !t.eq_ignore_ascii_case(kind));
+
+    let mut out = format!("\n\n> **{}:**", capitalize(kind));
+    if let Some(t) = title {
+        out.push(' ');
+        out.push_str(&t);
+    }
+    out.push('\n');
+    let body = body.trim();
+    if !body.is_empty() {
+        out.push_str(">\n");
+        for line in body.lines() {
+            if line.trim().is_empty() {
+                out.push_str(">\n");
+            } else {
+                out.push_str("> ");
+                out.push_str(line.trim_end());
+                out.push('\n');
+            }
+        }
+    }
+    out.push('\n');
+    out
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn image_md_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"!\[[^\]]*\]\([^)]*\)").unwrap())
+}
+
+fn class_tokens(tag: &HTMLTag) -> Vec<String> {
+    attr(tag, "class")
+        .map(|c| c.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
@@ -20,3 +20,148 @@ fn cache_path_mapping() {
     let p = cache.page_path(&u).unwrap();
     assert!(p.to_string_lossy().ends_with("sites/https/example.com/docs/getting-started.md"));
 }
+
+#[test]
+fn url_pattern_brace_alternatives() {
+    let pat =
+        gg::urlspec::UrlPattern::new("https://example.com/docs/{guide,reference}/**").unwrap();
+    assert_eq!(pat.root.as_str(), "https://example.com/docs/");
+    assert!(pat.matches_url_string("https://example.com/docs/guide/intro"));
+    assert!(pat.matches_url_string("https://example.com/docs/reference/api/x"));
+    assert!(!pat.matches_url_string("https://example.com/docs/blog/post"));
+
+    let nested = gg::urlspec::UrlPattern::new("https://example.com/{docs/v{1,2},api}/*").unwrap();
+    assert_eq!(nested.root.as_str(), "https://example.com/");
+    assert!(nested.matches_url_string("https://example.com/docs/v2/a"));
+    assert!(nested.matches_url_string("https://example.com/api/a"));
+    assert!(!nested.matches_url_string("https://example.com/docs/v3/a"));
+
+    assert!(gg::urlspec::UrlPattern::new("https://example.com/{a,b/*").is_err());
+    assert!(gg::urlspec::UrlPattern::new("https://{a,b}.example.com/*").is_err());
+    assert_eq!(
+        gg::util::split_comma_separated("https://a.dev/{x,y}/*, https://b.dev/"),
+        ["https://a.dev/{x,y}/*", "https://b.dev/"]
+    );
+}
+
+#[test]
+fn url_pattern_character_classes() {
+    let pat = gg::urlspec::UrlPattern::new("https://example.com/v[2-9]/[!_]*").unwrap();
+    assert!(pat.matches_url_string("https://example.com/v3/intro"));
+    assert!(!pat.matches_url_string("https://example.com/v1/intro"));
+    assert!(!pat.matches_url_string("https://example.com/v3/_private"));
+
+    let literal = gg::urlspec::UrlPattern::new("https://example.com/[]-]x*").unwrap();
+    assert!(literal.matches_url_string("https://example.com/]x"));
+    assert!(literal.matches_url_string("https://example.com/-x1"));
+
+    assert!(gg::urlspec::UrlPattern::new("https://example.com/[a-").is_err());
+    assert!(gg::urlspec::UrlPattern::new("https://example.com/[z-a]*").is_err());
+}
+
+#[test]
+fn exclusion_sources() {
+    let opts = gg::urlspec::SourceParseOpts::default();
+    let spec =
+        gg::urlspec::parse_source_token("!https://example.com/docs/legacy/**", opts).unwrap();
+    let gg::urlspec::SourceSpec::Exclude(pat) = spec else {
+        panic!("expected an exclusion");
+    };
+    assert!(pat.matches_url_string("https://example.com/docs/legacy/a"));
+    assert!(!pat.matches_url_string("https://example.com/docs/a"));
+
+    let exact = gg::urlspec::UrlPattern::exclusion("https://example.com/docs/old").unwrap();
+    assert!(exact.matches_url_string("https://example.com/docs/old#top"));
+    assert!(!exact.matches_url_string("https://example.com/docs/older"));
+}
+
+#[test]
+fn url_pattern_host_wildcards() {
+    let pat = gg::urlspec::UrlPattern::new("https://*.example.com/docs/**").unwrap();
+    assert_eq!(pat.root.as_str(), "https://example.com/docs/");
+    assert_eq!(pat.host_pattern.as_deref(), Some("*.example.com"));
+    assert!(pat.matches_url_string("https://api.example.com/docs/v1/x"));
+    assert!(!pat.matches_url_string("https://example.com/docs/x"));
+    assert!(!pat.matches_url_string("https://api.example.org/docs/x"));
+
+    assert!(gg::urlspec::UrlPattern::new("https://docs.*.com/**").is_err());
+
+    let mut hosts = gg::util::HostSet::default();
+    hosts.insert("*.example.com");
+    hosts.insert("Developer.Example.org");
+    assert!(hosts.contains("api.example.com"));
+    assert!(hosts.contains("developer.example.org"));
+    assert!(!hosts.contains("example.com"));
+    assert!(!hosts.contains("badexample.com"));
+}
+
+#[test]
+fn brace_alternatives_are_not_one_subtree() {
+    let pat =
+        gg::urlspec::UrlPattern::new("https://example.com/docs/{guide,reference}/**").unwrap();
+    assert!(!pat.is_subtree_pattern());
+    let pat = gg::urlspec::UrlPattern::new("https://example.com/{docs}/**").unwrap();
+    assert!(pat.is_subtree_pattern());
+}
+
+#[test]
+fn url_pattern_query_conditions() {
+    let pat = gg::urlspec::UrlPattern::new("https://example.com/docs/**?lang=en&v=2*").unwrap();
+    assert_eq!(pat.root.as_str(), "https://example.com/docs/");
+    assert!(!pat.is_subtree_pattern());
+    assert!(pat.matches_url_string("https://example.com/docs/a/b?v=2.1&lang=en&x=1"));
+    assert!(!pat.matches_url_string("https://example.com/docs/a?lang=de&v=2"));
+    assert!(!pat.matches_url_string("https://example.com/docs/a?lang=en"));
+    assert!(!pat.matches_url_string("https://example.com/docs/a"));
+
+    // Without '=' after it, `?` stays a one-character wildcard; no query part means any query.
+    let wildcard = gg::urlspec::UrlPattern::new("https://example.com/v?/*").unwrap();
+    assert!(wildcard.matches_url_string("https://example.com/v2/intro?lang=de"));
+    let wildcard = gg::urlspec::UrlPattern::new("https://example.com/ch?").unwrap();
+    assert!(wildcard.matches_url_string("https://example.com/ch1"));
+
+    // After `*` or `/`, a bare key is a query condition.
+    let key_only = gg::urlspec::UrlPattern::new("https://example.com/docs/**?lang").unwrap();
+    assert!(key_only.matches_url_string("https://example.com/docs/a?lang=de"));
+    assert!(!key_only.matches_url_string("https://example.com/docs/a"));
+    assert!(!key_only.matches_url_string("https://example.com/docs/alang"));
+
+    let bare_key = gg::urlspec::UrlPattern::new("https://example.com/*?lang=*&draft").unwrap();
+    assert!(bare_key.matches_url_string("https://example.com/a?draft&lang=fr"));
+    assert!(!bare_key.matches_url_string("https://example.com/a?lang=fr"));
+
+    // `\?` matches the query separator literally, comparing the query as text.
+    let literal = gg::urlspec::UrlPattern::new(r"https://example.com/search\?q=*").unwrap();
+    assert_eq!(literal.root.as_str(), "https://example.com/");
+    assert!(literal.matches_url_string("https://example.com/search?q=rust"));
+    assert!(!literal.matches_url_string("https://example.com/search?page=2&q=rust"));
+}
+
+#[test]
+fn url_regex_sources() {
+    let opts = gg::urlspec::SourceParseOpts::default();
+    let spec =
+        gg::urlspec::parse_source_token("re:https://example.com/docs/v[2-9]/.*", opts).unwrap();
+    let gg::urlspec::SourceSpec::Regex(re) = spec else {
+        panic!("expected a regex source: {spec:?}");
+    };
+    assert_eq!(re.root.as_str(), "https://example.com/docs/");
+    assert!(re.matches_url_string("https://example.com/docs/v3/guide/intro#setup"));
+    assert!(!re.matches_url_string("https://example.com/docs/v1/guide"));
+    // Anchored: a match must cover the whole URL.
+    assert!(!re.matches_url_string("https://mirror.dev/https://example.com/docs/v3/a"));
+
+    // A quantified character is not part of the literal root.
+    let re = gg::urlspec::UrlRegex::new(r"re:https://example\.com/api/ref/s?[a-z]+\.html").unwrap();
+    assert_eq!(re.root.as_str(), "https://example.com/api/ref/");
+    let re = gg::urlspec::UrlRegex::new("re:https://example.com/blogs?/.*").unwrap();
+    assert_eq!(re.root.as_str(), "https://example.com/");
+
+    let explicit =
+        gg::urlspec::UrlRegex::new("re(https://example.com/docs/):.*/(intro|setup)").unwrap();
+    assert_eq!(explicit.root.as_str(), "https://example.com/docs/");
+    assert!(explicit.matches_url_string("https://example.com/docs/guide/setup"));
+
+    assert!(gg::urlspec::UrlRegex::new("re:.*/docs/.*").is_err());
+    assert!(gg::urlspec::UrlRegex::new("re:https://example.com/(unclosed").is_err());
+}
@@ -0,0 +1,280 @@
+mod common;
+
+use std::path::Path;
+
+use tokio::process::Command;
+
+async fn gg(cache: &Path, args: &[&str]) -> (bool, String, String) {
+    let out = Command::new(env!("CARGO_BIN_EXE_gg"))
+        .arg("--cache-dir")
+        .arg(cache)
+        .args(args)
+        .output()
+        .await
+        .unwrap();
+    (
+        out.status.success(),
+        String::from_utf8(out.stdout).unwrap(),
+        String::from_utf8(out.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn help_lists_every_flag_and_subcommand() {
+    let help = gg::cli::help();
+    for f in gg::cli::FLAGS {
+        assert!(help.contains(&f.usage()), "{}", f.long);
+    }
+    for s in gg::cli::SUBCOMMANDS {
+        assert!(help.contains(&format!("  {}", s.usage())), "{}", s.name);
+    }
+    assert!(gg::cli::subcommand_help("crawl")
+        .unwrap()
+        .contains("gg [GG_FLAGS] crawl ROOT..."));
+    assert!(gg::cli::subcommand_help("nope").is_none());
+}
+
+#[test]
+fn completions_and_man_page_cover_every_flag_and_subcommand() {
+    use gg::completions::{script, Shell};
+
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+        let text = script(shell);
+        for f in gg::cli::FLAGS {
+            let long = match shell {
+                Shell::Fish => format!("-l {}", &f.long[2..]),
+                _ => f.long.to_string(),
+            };
+            assert!(text.contains(&long), "{shell:?} {}", f.long);
+        }
+        for s in gg::cli::SUBCOMMANDS {
+            assert!(text.contains(s.name), "{shell:?} {}", s.name);
+        }
+        assert!(text.contains("gg __complete"), "{shell:?}");
+    }
+    let bash = script(Shell::Bash);
+    assert!(bash
+        .contains("--discover)\n            COMPREPLY=($(compgen -W \"both sitemap links none\""));
+    assert!("fish".parse::<Shell>().is_ok() && "csh".parse::<Shell>().is_err());
+
+    let man = gg::cli::man_page();
+    assert!(man.starts_with(".TH GG 1"), "{man}");
+    assert!(man.contains("\\fB\\-\\-max\\-depth\\fR \\fIN\\fR"), "{man}");
+    assert!(man.contains(".SH DATA SOURCES\n.IP \\(bu 2\n"), "{man}");
+    // No text line may start a roff request by accident.
+    for line in man.lines() {
+        assert!(
+            !line.starts_with('\'') && !line.starts_with(".  "),
+            "{line}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn subcommands_fetch_crawl_run_and_manage_the_cache() {
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/docs/".into(),
+        (
+            200,
+            "text/html",
+            r#"<html><body><p>Docs: <a href="/docs/test">Test</a></p></body></html>"#.into(),
+        ),
+    );
+    routes.insert(
+        "/docs/test".into(),
+        (
+            200,
+            "text/html",
+            "<html><body><p>Testing page</p></body></html>".into(),
+        ),
+    );
+    let base = common::serve(routes, None).await;
+    let dir = std::env::temp_dir().join(format!("gg-cli-{}", std::process::id()));
+    let cache = dir.join("cache");
+    let docs = format!("{base}docs/");
+    let page = format!("{base}docs/test");
+
+    // `fetch` takes the URL as a single page even with a trailing slash.
+    let (ok, out, err) = gg(&cache, &["fetch", &docs]).await;
+    assert!(ok, "{err}");
+    assert!(out.trim_end().ends_with("/docs/index.md"), "{out}");
+
+    let (ok, out, err) = gg(&cache, &["--discover=links", "crawl", &docs]).await;
+    assert!(ok, "{err}");
+    assert!(out.trim_end().ends_with("/docs"), "{out}");
+
+    // Everything after `--` belongs to the command, even words that look like gg flags.
+    let (ok, out, err) = gg(&cache, &["run", &page, "--", "grep", "--count", "Testing"]).await;
+    assert!(ok, "{err}");
+    assert_eq!(out.trim(), "1");
+
+    let (ok, out, _) = gg(&cache, &["paths", &page]).await;
+    assert!(ok && out.trim_end().ends_with("/docs/test.md"), "{out}");
+
+    // `ls` is no subcommand: the host's ls lists the cached file.
+    let (ok, out, err) = gg(&cache, &["ls", "-l", &page]).await;
+    assert!(ok, "{err}");
+    assert!(
+        out.starts_with('-') && out.trim_end().ends_with("/docs/test.md"),
+        "{out}"
+    );
+
+    let (ok, out, _) = gg(&cache, &["cache", "list"]).await;
+    assert!(ok);
+    assert_eq!(out, format!("{docs}\t2 pages\n"));
+
+    // Completion candidates come from the cache manifests: the site, its crawl roots and,
+    // once the prefix reaches into a root, its pages.
+    let (ok, out, _) = gg(&cache, &["__complete", &base[..base.len() - 3]]).await;
+    assert!(ok);
+    assert_eq!(out, format!("{base}\n{docs}\n"));
+    let (_, out, _) = gg(&cache, &["__complete", &format!("{docs}t")]).await;
+    assert_eq!(out, format!("{page}\n"));
+    let (_, out, _) = gg(&cache, &["__complete", "https://elsewhere"]).await;
+    assert!(out.is_empty(), "{out}");
+
+    let (ok, out, _) = gg(&cache, &["cache", "dir"]).await;
+    assert!(ok && out.trim() == cache.display().to_string(), "{out}");
+    let (ok, _, err) = gg(&cache, &["cache", "clear", &page]).await;
+    assert!(ok && err.contains("removed"), "{err}");
+    let site_dir = cache
+        .join("sites")
+        .join("http")
+        .read_dir()
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    assert!(!site_dir.join("docs").join("test.md").exists());
+    assert!(site_dir.join("docs").join("index.md").exists());
+    let (ok, _, _) = gg(&cache, &["cache", "clear", "--all"]).await;
+    assert!(ok && !cache.join("sites").exists());
+
+    let (ok, _, err) = gg(&cache, &["paths", "--bogus", &page]).await;
+    assert!(
+        !ok && err.contains("unknown flag for gg paths: --bogus"),
+        "{err}"
+    );
+    let (ok, _, err) = gg(&cache, &["run", &page]).await;
+    assert!(!ok && err.contains("usage: gg [GG_FLAGS] run"), "{err}");
+    let (ok, _, err) = gg(&cache, &["--print-paths=yes", "paths", &page]).await;
+    assert!(!ok && err.contains("--print-paths takes no value"), "{err}");
+
+    // A host command sharing a subcommand's name runs when its words do not fit the
+    // subcommand.
+    let bin = dir.join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let fake = bin.join("fetch");
+    std::fs::write(&fake, "#!/bin/sh\necho \"host fetch $1\"\n").unwrap();
+    std::fs::set_permissions(&fake, std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap());
+    let out = Command::new(env!("CARGO_BIN_EXE_gg"))
+        .env("PATH", &path)
+        .arg("--cache-dir")
+        .arg(&cache)
+        .args(["fetch", "-q", &page])
+        .output()
+        .await
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout).unwrap(), "host fetch -q\n");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn each_runs_the_host_command_per_file_with_placeholders() {
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/docs/".into(),
+        (
+            200,
+            "text/html",
+            r#"<html><body><p><a href="/docs/alpha">Alpha</a> <a href="/docs/beta">Beta</a> <a href="/docs/guide/">Guide</a> <a href="/docs/guide/setup.html">Setup</a></p></body></html>"#.into(),
+        ),
+    );
+    // Trailing-slash pages and pages in subdirectories get stems from their URL paths; titles
+    // lose `/` and a leading `-`.
+    routes.insert(
+        "/docs/guide/".into(),
+        (
+            200,
+            "text/html",
+            "<html><body><h1>-v/--verbose</h1><p>Text</p></body></html>".into(),
+        ),
+    );
+    routes.insert(
+        "/docs/guide/setup.html".into(),
+        (
+            200,
+            "text/html",
+            "<html><body><h1>Setup</h1><p>Text</p></body></html>".into(),
+        ),
+    );
+    for name in ["alpha", "beta"] {
+        routes.insert(
+            format!("/docs/{name}"),
+            (
+                200,
+                "text/html",
+                format!(
+                    r#"<html><body><h1 id="{name}">The {name} page</h1><p>Text</p></body></html>"#
+                ),
+            ),
+        );
+    }
+    let base = common::serve(routes, None).await;
+    let dir = std::env::temp_dir().join(format!("gg-each-{}", std::process::id()));
+    let cache = dir.join("cache");
+    let glob = format!("{base}docs/**");
+
+    // The subtree glob lists its pages one by one instead of passing the directory.
+    let (ok, out, err) = gg(
+        &cache,
+        &[
+            "--each",
+            "--jobs",
+            "2",
+            "echo",
+            "{stem}:{title}",
+            "{url}",
+            &glob,
+        ],
+    )
+    .await;
+    assert!(ok, "{err}");
+    let mut lines: Vec<&str> = out.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [
+            format!("alpha:The alpha page {base}docs/alpha"),
+            format!("beta:The beta page {base}docs/beta"),
+            format!("docs:docs {base}docs/"),
+            format!("guide-setup:Setup {base}docs/guide/setup.html"),
+            format!("guide:v---verbose {base}docs/guide/"),
+        ]
+    );
+
+    // Without placeholders the path is appended, as with xargs.
+    let (ok, out, err) = gg(
+        &cache,
+        &["--each", "wc", "-l", &format!("{base}docs/alpha")],
+    )
+    .await;
+    assert!(ok, "{err}");
+    assert!(out.trim_end().ends_with("/docs/alpha.md"), "{out}");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_gg"))
+        .arg("--cache-dir")
+        .arg(&cache)
+        .args(["--each", "--cmd", "false", &glob])
+        .output()
+        .await
+        .unwrap();
+    assert_eq!(out.status.code(), Some(123));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
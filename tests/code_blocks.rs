@@ -0,0 +1,85 @@
+use gg::code;
+use gg::crawl::{self, CrawlOptions};
+
+fn convert(html: &str, guess: bool) -> String {
+    let opts = CrawlOptions {
+        guess_code_language: guess,
+        ..Default::default()
+    };
+    crawl::convert_page_for_test(html, None, &opts, None)
+        .unwrap()
+        .markdown
+}
+
+#[test]
+fn aliases_map_to_canonical_names() {
+    assert_eq!(code::normalize_language("JS"), "javascript");
+    assert_eq!(code::normalize_language("golang"), "go");
+    assert_eq!(code::normalize_language("yml"), "yaml");
+    assert_eq!(code::normalize_language("c++"), "cpp");
+    assert_eq!(code::normalize_language("mermaid"), "mermaid");
+}
+
+#[test]
+fn highlighter_classes_name_the_language() {
+    let lang = |class: &str| code::language_from_class(class);
+    assert_eq!(lang("hljs language-rust").as_deref(), Some("rust"));
+    assert_eq!(lang("hljs rs").as_deref(), Some("rust"));
+    assert_eq!(lang("highlight highlight-source-go").as_deref(), Some("go"));
+    assert_eq!(
+        lang("highlight-python notranslate").as_deref(),
+        Some("python")
+    );
+    assert_eq!(lang("brush: java; gutter: false").as_deref(), Some("java"));
+    assert_eq!(lang("sourceCode py").as_deref(), Some("python"));
+    assert_eq!(lang("highlight-default notranslate"), None);
+    assert_eq!(lang("rust"), None, "bare names need a highlighter marker");
+}
+
+#[test]
+fn fences_are_tagged_from_block_and_wrapper_markup() {
+    let md = convert(
+        r#"<pre><code class="hljs rust">fn main() {}</code></pre>"#,
+        false,
+    );
+    assert!(md.contains("```rust\nfn main() {}\n```"), "{md}");
+
+    let md = convert(
+        r#"<div class="highlight highlight-source-go"><pre>package main</pre></div>"#,
+        false,
+    );
+    assert!(md.contains("```go\npackage main\n```"), "{md}");
+
+    let md = convert(r#"<pre class="brush: java;">class A {}</pre>"#, false);
+    assert!(md.contains("```java\n"), "{md}");
+
+    let md = convert(
+        r#"<pre><code class="language-yml">a: 1</code></pre>"#,
+        false,
+    );
+    assert!(md.contains("```yaml\n"), "{md}");
+
+    // A page-level `lang-*` class is not a code language.
+    let md = convert(r#"<div class="lang-en"><pre>plain</pre></div>"#, false);
+    assert!(md.contains("```\nplain\n```"), "{md}");
+}
+
+#[test]
+fn content_heuristic_is_opt_in() {
+    let html = "<pre>#!/usr/bin/env python3\nprint('hi')</pre>";
+    assert!(convert(html, false).contains("```\n#!/usr/bin/env python3"));
+    assert!(convert(html, true).contains("```python\n#!/usr/bin/env python3"));
+
+    assert_eq!(
+        code::guess_language("fn main() {\n    let x = 1;\n}"),
+        Some("rust")
+    );
+    assert_eq!(
+        code::guess_language("package main\n\nfunc main() {}"),
+        Some("go")
+    );
+    assert_eq!(code::guess_language("{\"a\": [1, 2]}"), Some("json"));
+    assert_eq!(code::guess_language("$ cargo build"), Some("bash"));
+    assert_eq!(code::guess_language("from os import path"), Some("python"));
+    assert_eq!(code::guess_language("just some words"), None);
+}
@@ -0,0 +1,78 @@
+use gg::crawl::{self, CrawlOptions};
+
+fn convert(html: &str, strip_prompts: bool) -> String {
+    let opts = CrawlOptions {
+        strip_prompts,
+        ..Default::default()
+    };
+    crawl::convert_page_for_test(html, None, &opts, None)
+        .unwrap()
+        .markdown
+}
+
+#[test]
+fn gutter_tables_are_unwrapped() {
+    let pygments = r#"<div class="highlight-python"><table class="highlighttable"><tr>
+<td class="linenos"><div class="linenodiv"><pre>1
+2</pre></div></td>
+<td class="code"><div class="highlight"><pre><span></span>import os
+print(os.sep)
+</pre></div></td></tr></table></div>"#;
+    let md = convert(pygments, false);
+    assert!(
+        md.contains("```python\nimport os\nprint(os.sep)\n```"),
+        "{md}"
+    );
+    assert!(!md.contains('|'));
+
+    let hljs_ln = r#"<pre><code class="hljs language-bash"><table class="hljs-ln"><tbody>
+<tr><td class="hljs-ln-line hljs-ln-numbers"><div class="hljs-ln-n" data-line-number="1"></div></td><td class="hljs-ln-line hljs-ln-code">cd app</td></tr>
+<tr><td class="hljs-ln-line hljs-ln-numbers"><div class="hljs-ln-n" data-line-number="2"></div></td><td class="hljs-ln-line hljs-ln-code">make</td></tr>
+</tbody></table></code></pre>"#;
+    let md = convert(hljs_ln, false);
+    assert!(md.contains("```bash\ncd app\nmake\n```"), "{md}");
+}
+
+#[test]
+fn gutter_spans_inside_code_are_dropped() {
+    let chroma = r#"<pre class="chroma"><code class="language-go" data-lang="go"><span class="line"><span class="ln">1</span><span class="cl">package main
+</span></span><span class="line"><span class="ln">2</span><span class="cl">func main() {}</span></span></code></pre>"#;
+    let md = convert(chroma, false);
+    assert!(
+        md.contains("```go\npackage main\nfunc main() {}\n```"),
+        "{md}"
+    );
+}
+
+#[test]
+fn prompts_are_stripped_only_when_enabled() {
+    let html = r#"<pre><code class="language-console"><span class="gp">$ </span>cargo build
+   Compiling gg
+$ cargo test</code></pre>
+<pre><code class="language-pycon">&gt;&gt;&gt; for i in range(2):
+...     print(i)
+0
+1</code></pre>"#;
+    let md = convert(html, false);
+    assert!(md.contains("$ cargo build"), "{md}");
+    assert!(md.contains(">>> for i in range(2):"));
+
+    let md = convert(html, true);
+    assert!(
+        md.contains("```bash\ncargo build\n   Compiling gg\ncargo test\n```"),
+        "{md}"
+    );
+    assert!(
+        md.contains("```pycon\nfor i in range(2):\n    print(i)\n0\n1\n```"),
+        "{md}"
+    );
+
+    assert_eq!(
+        gg::code::strip_prompts("user@box:~/src$ make\nok\n"),
+        "make\nok\n"
+    );
+    assert_eq!(
+        gg::code::strip_prompts("x = 1 # costs $ 5\n"),
+        "x = 1 # costs $ 5\n"
+    );
+}
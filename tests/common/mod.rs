@@ -0,0 +1,65 @@
+//! Minimal HTTP/1.1 server for crawl tests: serves fixed responses by path.
+
+use std::collections::HashMap;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// Status, content type and body served for a path (query included).
+pub type Routes = HashMap<String, (u16, &'static str, String)>;
+
+/// Start serving `routes` on an ephemeral port and return the base URL (`http://127.0.0.1:N/`).
+/// Unknown paths get a 404. Request lines are recorded in `log` when given. `{base}` in a body is
+/// replaced by the base URL, for documents that need absolute links.
+pub async fn serve(
+    routes: Routes,
+    log: Option<std::sync::Arc<std::sync::Mutex<Vec<String>>>>,
+) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let base = format!("http://{addr}/");
+    let routes: Routes = routes
+        .into_iter()
+        .map(|(path, (status, ct, body))| (path, (status, ct, body.replace("{base}", &base))))
+        .collect();
+    let routes = std::sync::Arc::new(routes);
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut sock, _)) = listener.accept().await else {
+                return;
+            };
+            let routes = routes.clone();
+            let log = log.clone();
+            tokio::spawn(async move {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match sock.read(&mut chunk).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    }
+                }
+                let head = String::from_utf8_lossy(&buf).to_string();
+                let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+                if let Some(log) = &log {
+                    log.lock()
+                        .unwrap()
+                        .push(head.lines().next().unwrap_or("").to_string());
+                }
+                let (status, ct, body) = routes.get(&path).cloned().unwrap_or((
+                    404,
+                    "text/plain",
+                    "not found".to_string(),
+                ));
+                let resp = format!(
+                    "HTTP/1.1 {status} X\r\nContent-Type: {ct}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = sock.write_all(resp.as_bytes()).await;
+            });
+        }
+    });
+    base
+}
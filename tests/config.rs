@@ -0,0 +1,402 @@
+mod common;
+
+use gg::cli::{self, FlagKind};
+use gg::config::{insert_cli_flag, Config, Origin, Settings};
+use gg::crawl::{DiscoverMode, ImageMode};
+use gg::render::RenderWhen;
+use gg::sections::AnchorStyle;
+use gg::tables::TableMode;
+use gg::util::parse_duration_secs;
+use tokio::process::Command;
+
+#[test]
+fn resolves_site_aliases() {
+    let config = Config::parse(
+        r#"
+[alias]
+rustbook = "https://doc.rust-lang.org/book"
+
+[alias.tokio]
+url = "https://docs.rs/tokio/latest/tokio/"
+include = ["sync/**", "/task/**"]
+max_depth = 3
+ttl = "7d"
+"#,
+    )
+    .unwrap();
+
+    let resolve = |token: &str| -> Vec<String> {
+        config.resolve(token).into_iter().map(|(s, _)| s).collect()
+    };
+    assert_eq!(resolve("@rustbook"), ["https://doc.rust-lang.org/book/"]);
+    assert_eq!(
+        resolve("rustbook:ch10-*"),
+        ["https://doc.rust-lang.org/book/ch10-*"]
+    );
+    assert_eq!(
+        resolve("!rustbook:appendix-*"),
+        ["!https://doc.rust-lang.org/book/appendix-*"]
+    );
+    assert_eq!(
+        resolve("@tokio"),
+        [
+            "https://docs.rs/tokio/latest/tokio/sync/**",
+            "https://docs.rs/tokio/latest/tokio/task/**"
+        ]
+    );
+    assert_eq!(resolve("@unknown"), ["@unknown"]);
+    assert_eq!(resolve("https://x.dev/a"), ["https://x.dev/a"]);
+
+    let tokio = &config.aliases["tokio"];
+    assert_eq!(
+        (tokio.max_depth, tokio.ttl_secs),
+        (Some(3), Some(7 * 24 * 3600))
+    );
+    assert!(config.is_alias_token("rustbook:ch01"));
+    assert!(!config.is_alias_token("other:ch01"));
+
+    assert!(Config::parse("[alias]\nre = \"https://x.dev/\"\n").is_err());
+    assert!(Config::parse("[alias.x]\nurl = \"https://x.dev/\"\nttl = \"soon\"\n").is_err());
+
+    assert_eq!(parse_duration_secs("90").unwrap(), 90);
+    assert_eq!(parse_duration_secs("12h").unwrap(), 12 * 3600);
+    assert!(parse_duration_secs("3y").is_err());
+    assert!(parse_duration_secs("99999999999999999w").is_err());
+    assert!(parse_duration_secs(&format!("{}s", u64::MAX)).is_ok());
+}
+
+#[tokio::test]
+async fn alias_sources_use_alias_settings() {
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/docs/".into(),
+        (
+            200,
+            "text/html",
+            r#"<html><body><p>Docs: <a href="/docs/intro">Intro</a> <a href="/docs/api">API</a></p></body></html>"#
+                .into(),
+        ),
+    );
+    for name in ["intro", "api"] {
+        routes.insert(
+            format!("/docs/{name}"),
+            (
+                200,
+                "text/html",
+                format!("<html><body><p>{name}</p></body></html>"),
+            ),
+        );
+    }
+    let base = common::serve(routes, None).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    std::fs::write(
+        &config,
+        format!(
+            "[alias.site]\nurl = \"{base}docs/\"\ninclude = [\"i*\"]\nuser_agent = \"gg-test\"\n"
+        ),
+    )
+    .unwrap();
+
+    let run = |source: &'static str| {
+        let (dir, config) = (dir.clone(), config.clone());
+        async move {
+            let out = Command::new(env!("CARGO_BIN_EXE_gg"))
+                .arg("--cache-dir")
+                .arg(dir.join("cache"))
+                .arg("--config")
+                .arg(config)
+                .args(["--print-paths", "cat", source])
+                .output()
+                .await
+                .unwrap();
+            assert!(
+                out.status.success(),
+                "{}",
+                String::from_utf8_lossy(&out.stderr)
+            );
+            String::from_utf8(out.stdout).unwrap()
+        }
+    };
+
+    let stdout = run("@site").await;
+    let names: Vec<&str> = stdout
+        .lines()
+        .map(|l| l.rsplit('/').next().unwrap())
+        .collect();
+    assert_eq!(names, ["intro.md"]);
+
+    let stdout = run("site:api").await;
+    assert!(stdout.trim_end().ends_with("/docs/api.md"), "{stdout}");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// Settings for command-line flags given as `(flag, value)`.
+fn cli_settings(flags: &[(&str, &str)]) -> Settings {
+    let mut settings = Settings::new();
+    for (name, value) in flags {
+        let (flag, on) = cli::find_flag(name).unwrap();
+        insert_cli_flag(&mut settings, flag, value, on);
+    }
+    settings
+}
+
+#[test]
+fn flag_choices_and_defaults_parse() {
+    fn parse(key: &str, value: &str) -> anyhow::Result<()> {
+        match key {
+            "discover" => value.parse::<DiscoverMode>().map(drop),
+            "images" => value.parse::<ImageMode>().map(drop),
+            "tables" => value.parse::<TableMode>().map(drop),
+            "heading_anchors" => value.parse::<AnchorStyle>().map(drop),
+            "render" => value.parse::<RenderWhen>().map(drop),
+            other => panic!("no parser for {other}"),
+        }
+    }
+    for flag in cli::settings() {
+        if flag.kind == FlagKind::Switch {
+            assert!(["true", "false"].contains(&flag.default), "{}", flag.long);
+        }
+        if flag.choices.is_empty() {
+            continue;
+        }
+        assert_eq!(flag.default, flag.choices[0], "{}", flag.long);
+        for choice in flag.choices {
+            parse(flag.key, choice).unwrap();
+        }
+        let err = parse(flag.key, "bogus").unwrap_err().to_string();
+        assert!(flag.choices.iter().all(|c| err.contains(c)), "{err}");
+    }
+}
+
+#[test]
+fn layers_settings_with_origins() {
+    let dir = std::env::temp_dir().join(format!("gg-config-layers-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let system = dir.join("system.toml");
+    let project = dir.join(".gg.toml");
+    std::fs::write(
+        &system,
+        "parallelism = 8\nsitemap = false\ntimeout = 10\nsanitize_rules = \"rules.toml\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        &project,
+        r#"
+parallelism = 16
+allow_host = ["a.dev", "b.dev"]
+
+[host."*.example.com"]
+user_agent = "wild"
+max_depth = 1
+
+[host."docs.example.com"]
+user_agent = "exact"
+timeout = 5
+"#,
+    )
+    .unwrap();
+
+    let env = [
+        ("GG_TIMEOUT".to_string(), "30".to_string()),
+        ("GG_URL".to_string(), "https://x.dev/".to_string()),
+    ];
+    let config = Config::load_from(std::slice::from_ref(&system), Some(&project), env).unwrap();
+    let origin = |key: &str| config.settings[key].1.clone();
+    assert_eq!(config.settings["parallelism"].0, "16");
+    assert_eq!(origin("parallelism"), Origin::File(project.clone()));
+    assert_eq!(origin("sitemap"), Origin::File(system.clone()));
+    assert_eq!(config.env["timeout"].1, Origin::Env("GG_TIMEOUT".into()));
+    assert_eq!(
+        config.settings["sanitize_rules"].0,
+        dir.join("rules.toml").display().to_string()
+    );
+
+    let effective = config.effective(None, &Settings::new());
+    assert_eq!(effective.get("parallelism"), Some("16"));
+    assert!(!effective.switch("sitemap"));
+    assert_eq!(effective.list("allow_host"), ["a.dev", "b.dev"]);
+    assert_eq!(effective.get("user_agent"), None);
+    assert_eq!(effective.get("discover"), Some("both"));
+
+    // The exact host section overrides the wildcard one; both override the global settings.
+    let docs = config.effective(Some("docs.example.com"), &Settings::new());
+    assert_eq!(docs.get("user_agent"), Some("exact"));
+    assert_eq!(docs.get("max_depth"), Some("1"));
+    // GG_* variables override host sections too.
+    assert_eq!(docs.get("timeout"), Some("30"));
+    assert!(config.has_host_overrides("api.example.com"));
+
+    // Command-line flags resolve each key once: switches turn off, lists replace.
+    let cli = cli_settings(&[
+        ("--sitemap", ""),
+        ("--allow-host", "c.dev"),
+        ("--allow-host", "d.dev"),
+    ]);
+    let effective = config.effective(None, &cli);
+    assert!(effective.switch("sitemap"));
+    assert_eq!(effective.list("allow_host"), ["c.dev", "d.dev"]);
+    let switches = Config::parse("refresh = true\nsplit_sections = true\n").unwrap();
+    let effective = switches.effective(None, &cli_settings(&[("--no-refresh", "")]));
+    assert!(!effective.switch("refresh") && effective.switch("split_sections"));
+    assert!(!config.has_host_overrides("example.com"));
+
+    let invalid = cli_settings(&[("--max-depth", "deep")]);
+    let err = config.effective(None, &invalid).parse::<usize>("max_depth");
+    assert!(err
+        .unwrap_err()
+        .to_string()
+        .contains("--max-depth value \"deep\" (from command line)"));
+
+    let shown = config.show(&cli_settings(&[("--timeout", "5")]));
+    let line = |key: &str| {
+        shown
+            .lines()
+            .find(|l| l.starts_with(key))
+            .unwrap()
+            .to_string()
+    };
+    assert!(line("timeout = 5").ends_with("# command line"), "{shown}");
+    assert!(
+        line("sitemap = false").ends_with(&format!("# {}", system.display())),
+        "{shown}"
+    );
+    assert!(
+        line("discover = \"both\"").ends_with("# default"),
+        "{shown}"
+    );
+    assert!(shown.contains("[host.\"docs.example.com\"]"), "{shown}");
+
+    assert!(Config::parse("paralellism = 4\n").is_err());
+    assert!(Config::parse("[host.\"x.dev\"]\ncache_dir = \"/tmp\"\n").is_err());
+    assert!(Config::parse("sitemap = \"maybe\"\n").is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn project_files_cannot_set_commands() {
+    let dir = std::env::temp_dir().join(format!("gg-config-trust-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let project = dir.join(".gg.toml");
+    for text in [
+        "render_cmd = \"sh -c 'touch pwned'\"\n",
+        "[host.\"docs.rs\"]\nrender_cmd = \"evil\"\n",
+        "converters = \"converters.toml\"\n",
+    ] {
+        std::fs::write(&project, text).unwrap();
+        let err = Config::load_from(&[], Some(&project), []).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("cannot be set in a project .gg.toml"),
+            "{err:#}"
+        );
+    }
+
+    // The same keys are fine in the user's own config.
+    let user = dir.join("config.toml");
+    std::fs::write(&user, "render_cmd = \"chromium --dump-dom\"\n").unwrap();
+    let config = Config::load_from(&[user], None, []).unwrap();
+    assert_eq!(config.settings["render_cmd"].0, "chromium --dump-dom");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn project_config_and_host_sections_apply_to_runs() {
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/docs/".into(),
+        (
+            200,
+            "text/html",
+            r#"<html><body><p>Docs: <a href="/docs/intro">Intro</a></p></body></html>"#.into(),
+        ),
+    );
+    routes.insert(
+        "/docs/intro".into(),
+        (
+            200,
+            "text/html",
+            "<html><body><p>Intro</p></body></html>".into(),
+        ),
+    );
+    let base = common::serve(routes, None).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-config-project-{}", std::process::id()));
+    let work = dir.join("project").join("sub");
+    std::fs::create_dir_all(&work).unwrap();
+    std::fs::write(
+        dir.join("project").join(".gg.toml"),
+        "print_paths = true\n\n[host.\"127.0.0.1\"]\ndiscover = \"none\"\n",
+    )
+    .unwrap();
+
+    let out = Command::new(env!("CARGO_BIN_EXE_gg"))
+        .current_dir(&work)
+        .arg("--cache-dir")
+        .arg(dir.join("cache"))
+        .args(["cat", &format!("{base}docs/{{,intro}}")])
+        .output()
+        .await
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let names: Vec<&str> = stdout
+        .lines()
+        .map(|l| l.rsplit('/').next().unwrap())
+        .collect();
+    assert_eq!(names, ["index.md"]);
+
+    // --no-print-paths turns the configured switch off, so the host command runs.
+    let out = Command::new(env!("CARGO_BIN_EXE_gg"))
+        .current_dir(&work)
+        .arg("--cache-dir")
+        .arg(dir.join("cache"))
+        .args(["--no-print-paths", "cat", &format!("{base}docs/intro")])
+        .output()
+        .await
+        .unwrap();
+    assert!(out.status.success());
+    assert!(String::from_utf8(out.stdout).unwrap().contains("Intro"));
+
+    let out = Command::new(env!("CARGO_BIN_EXE_gg"))
+        .current_dir(&work)
+        .env("GG_MAX_DEPTH", "2")
+        .args(["--images", "alt", "config", "show"])
+        .output()
+        .await
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let shown = String::from_utf8(out.stdout).unwrap();
+    assert!(
+        shown
+            .lines()
+            .any(|l| l.starts_with("max_depth = 2") && l.ends_with("# GG_MAX_DEPTH")),
+        "{shown}"
+    );
+    assert!(
+        shown
+            .lines()
+            .any(|l| l.starts_with("images = \"alt\"") && l.ends_with("# command line")),
+        "{shown}"
+    );
+    assert!(
+        shown
+            .lines()
+            .any(|l| l.starts_with("print_paths = true") && l.ends_with(".gg.toml")),
+        "{shown}"
+    );
+
+    std::fs::remove_dir_all(&dir).ok();
+}
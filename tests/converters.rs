@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use gg::external::{self, ExternalConverter};
+use url::Url;
+
+fn converter(
+    host: Option<&str>,
+    content_type: Option<&str>,
+    command: &[&str],
+) -> ExternalConverter {
+    ExternalConverter {
+        host: host.map(str::to_string),
+        content_type: content_type.map(str::to_string),
+        command: command.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+#[test]
+fn converters_match_by_host_and_content_type() {
+    let url = Url::parse("https://api.example.com/spec").unwrap();
+    let pdf = converter(None, Some("application/pdf"), &["pdftotext"]);
+    let site = converter(Some("*.example.com"), Some("text/*"), &["pandoc"]);
+
+    assert!(pdf.matches(&url, Some("application/pdf")));
+    assert!(!pdf.matches(&url, Some("text/html")));
+    assert!(site.matches(&url, Some("text/html; charset=utf-8")));
+    assert!(!site.matches(
+        &Url::parse("https://example.org/").unwrap(),
+        Some("text/html")
+    ));
+
+    let all = [pdf, site];
+    let found = external::find_converter(&all, &url, Some("text/html")).unwrap();
+    assert_eq!(found.command, ["pandoc"]);
+}
+
+#[test]
+fn converters_file_is_loaded() {
+    let path = std::env::temp_dir().join(format!("gg-converters-{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        "[[converter]]\nhost = \"docs.example.com\"\ncommand = [\"pandoc\", \"-f\", \"html\", \"-t\", \"gfm\"]\n",
+    )
+    .unwrap();
+    let loaded = external::load_converters(&path).unwrap();
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].host.as_deref(), Some("docs.example.com"));
+    assert_eq!(loaded[0].command, ["pandoc", "-f", "html", "-t", "gfm"]);
+
+    std::fs::write(&path, "[[converter]]\ncommand = []\n").unwrap();
+    assert!(external::load_converters(&path).is_err());
+    std::fs::remove_file(&path).ok();
+}
+
+#[tokio::test]
+async fn converter_gets_body_on_stdin_and_url_in_env() {
+    let c = converter(
+        None,
+        None,
+        &[
+            "sh",
+            "-c",
+            "printf '# %s (%s)\\n\\n' \"$GG_URL\" \"$GG_CONTENT_TYPE\"; cat",
+        ],
+    );
+    let url = Url::parse("https://docs.example.com/a").unwrap();
+    let md = external::run_converter(
+        &c,
+        &url,
+        Some("text/html"),
+        b"body text\n",
+        Duration::from_secs(10),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        md,
+        "# https://docs.example.com/a (text/html)\n\nbody text\n"
+    );
+
+    let failing = converter(None, None, &["sh", "-c", "echo 'bad input' >&2; exit 3"]);
+    let err = external::run_converter(&failing, &url, None, b"", Duration::from_secs(10))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("bad input"), "{err}");
+}
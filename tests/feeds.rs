@@ -0,0 +1,176 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use gg::cache::Cache;
+use gg::crawl::{self, CrawlOptions};
+use gg::feeds;
+use gg::util::parse_datetime;
+use url::Url;
+
+#[test]
+fn parses_feed_timestamps() {
+    assert_eq!(parse_datetime("1970-01-01"), Some(0));
+    assert_eq!(parse_datetime("2024-05-01T10:00:00Z"), Some(1_714_557_600));
+    assert_eq!(
+        parse_datetime("2024-05-01T12:00:00.250+02:00"),
+        Some(1_714_557_600)
+    );
+    assert_eq!(
+        parse_datetime("Wed, 01 May 2024 10:00:00 GMT"),
+        Some(1_714_557_600)
+    );
+    assert_eq!(
+        parse_datetime("01 May 2024 06:00:00 -0400"),
+        Some(1_714_557_600)
+    );
+    assert_eq!(parse_datetime("2024-13-01"), None);
+    // Absurd years are rejected rather than overflowing.
+    assert_eq!(parse_datetime("99999999999999999-01-01"), None);
+    assert_eq!(
+        parse_datetime("Wed, 01 May 9223372036854775807 10:00:00 GMT"),
+        None
+    );
+    assert_eq!(parse_datetime("0000-01-01"), None);
+    assert_eq!(parse_datetime("9999-12-31"), Some(253_402_214_400));
+    assert_eq!(parse_datetime("yesterday"), None);
+}
+
+#[test]
+fn parses_rss_and_atom_entries() {
+    let base = Url::parse("https://x.dev/blog/feed.xml").unwrap();
+    let rss = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/"><channel>
+  <link>https://x.dev/blog/</link>
+  <item><title>One</title><link>https://x.dev/blog/one</link><pubDate>Wed, 01 May 2024 10:00:00 GMT</pubDate></item>
+  <item><title>Two</title><guid>https://x.dev/blog/two</guid><dc:date>2024-05-02</dc:date></item>
+  <item><title>Three</title><guid isPermaLink="false">tag:x.dev,2024:3</guid></item>
+</channel></rss>"#;
+    let entries = feeds::parse_feed(rss.as_bytes(), &base).unwrap();
+    let got: Vec<(String, Option<i64>)> = entries
+        .iter()
+        .map(|e| (e.url.to_string(), e.updated))
+        .collect();
+    assert_eq!(
+        got,
+        [
+            ("https://x.dev/blog/one".to_string(), Some(1_714_557_600)),
+            ("https://x.dev/blog/two".to_string(), Some(1_714_608_000)),
+        ]
+    );
+
+    let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+  <link rel="self" href="/blog/atom.xml"/>
+  <entry>
+    <link rel="edit" href="/api/posts/1"/>
+    <link href="posts/one.html"/>
+    <published>2024-05-01T00:00:00Z</published>
+    <updated>2024-05-03T00:00:00Z</updated>
+  </entry>
+</feed>"#;
+    let entries = feeds::parse_feed(atom.as_bytes(), &base).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].url.as_str(), "https://x.dev/blog/posts/one.html");
+    assert_eq!(entries[0].updated, Some(1_714_694_400));
+
+    let html = r#"<head><link rel="alternate" type="application/atom+xml" href="/blog/atom.xml">
+<link rel="alternate" hreflang="de" href="/de/"><link rel="stylesheet" href="a.css"></head>"#;
+    let links: Vec<String> = feeds::feed_links(html, &base)
+        .iter()
+        .map(|u| u.to_string())
+        .collect();
+    assert_eq!(links, ["https://x.dev/blog/atom.xml"]);
+}
+
+#[tokio::test]
+async fn refresh_skips_pages_a_feed_reports_unchanged() {
+    let page = |body: &str| {
+        (
+            200,
+            "text/html",
+            format!("<html><body>{body}</body></html>"),
+        )
+    };
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/blog/".into(),
+        (
+            200,
+            "text/html",
+            r#"<html><head><link rel="alternate" type="application/rss+xml" href="rss"></head><body><p>Blog</p></body></html>"#
+                .into(),
+        ),
+    );
+    routes.insert(
+        "/blog/rss".into(),
+        (
+            200,
+            "application/rss+xml",
+            r#"<rss><channel>
+<item><link>old</link><pubDate>Wed, 01 May 2024 10:00:00 GMT</pubDate></item>
+<item><link>new</link><pubDate>Fri, 01 Jan 2100 00:00:00 GMT</pubDate></item>
+</channel></rss>"#
+                .into(),
+        ),
+    );
+    routes.insert(
+        "/blog/old".into(),
+        page(
+            r#"<p>Old post, see <a href="/blog/linked">more</a>.</p>
+<p><a href="/blog/nav">Next post</a></p>"#,
+        ),
+    );
+    routes.insert(
+        "/blog/nav".into(),
+        page("<p>Reached from a link-only line</p>"),
+    );
+    routes.insert("/blog/new".into(), page("<p>New post</p>"));
+    routes.insert("/blog/linked".into(), page("<p>Linked page</p>"));
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let base = common::serve(routes, Some(log.clone())).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-feeds-{}", std::process::id()));
+    let cache = Cache::new(Some(dir.clone())).unwrap();
+    let opts = CrawlOptions {
+        use_sitemap: false,
+        ..Default::default()
+    };
+    let root = Url::parse(&base).unwrap().join("blog/").unwrap();
+    let first = crawl::ensure_subtree_cached(&cache, &opts, root.clone(), true)
+        .await
+        .unwrap();
+    assert_eq!(first.pages.len(), 5, "{:?}", first.pages);
+
+    log.lock().unwrap().clear();
+    let second = crawl::ensure_subtree_cached(&cache, &opts, root.clone(), true)
+        .await
+        .unwrap();
+    let requests = log.lock().unwrap().clone();
+    assert!(
+        !requests.iter().any(|r| r.contains("/blog/old ")),
+        "{requests:?}"
+    );
+    assert!(
+        requests.iter().any(|r| r.contains("/blog/new ")),
+        "{requests:?}"
+    );
+
+    // The reused page keeps its entry and its links are still followed.
+    let old_before = first
+        .pages
+        .iter()
+        .find(|p| p.url.ends_with("/blog/old"))
+        .unwrap();
+    let old_after = second
+        .pages
+        .iter()
+        .find(|p| p.url.ends_with("/blog/old"))
+        .unwrap();
+    assert_eq!(old_after.fetched_at, old_before.fetched_at);
+    assert!(second.pages.iter().any(|p| p.url.ends_with("/blog/linked")));
+    // Links the nav-links rule dropped from the cached Markdown are still followed.
+    assert!(second.pages.iter().any(|p| p.url.ends_with("/blog/nav")));
+    assert_eq!(second.pages.len(), first.pages.len());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
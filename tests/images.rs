@@ -0,0 +1,221 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use gg::crawl::{self, CrawlOptions, ImageMode};
+use gg::sanitize::UserRule;
+use gg::sections::AnchorStyle;
+use gg::tables::TableMode;
+use url::Url;
+
+const HTML: &str = r#"<p>Intro</p>
+<p><img src="/img/flow.png" alt="Request lifecycle" title="Overview"></p>
+<p><img src="data:image/png;base64,AAAA" alt="Inline badge"></p>
+<p>Outro</p>"#;
+
+fn with_images(images: ImageMode) -> CrawlOptions {
+    CrawlOptions {
+        images,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn alt_mode_keeps_text_placeholders() {
+    let page =
+        crawl::convert_page_for_test(HTML, None, &with_images(ImageMode::Alt), None).unwrap();
+    let (md, refs) = (page.markdown, page.images);
+    assert!(md.contains("[Image: Request lifecycle — Overview]"));
+    assert!(md.contains("[Image: Inline badge]"));
+    assert!(!md.contains("flow.png"));
+    assert!(refs.is_empty());
+}
+
+#[test]
+fn download_mode_collects_resolved_image_urls() {
+    let base = Url::parse("https://example.com/docs/guide").unwrap();
+    let page =
+        crawl::convert_page_for_test(HTML, None, &with_images(ImageMode::Download), Some(&base))
+            .unwrap();
+    let (md, refs) = (page.markdown, page.images);
+    assert_eq!(refs.len(), 1);
+    assert_eq!(refs[0].url.as_str(), "https://example.com/img/flow.png");
+    assert!(md.contains("![Request lifecycle](https://example.com/img/flow.png)"));
+    // data: URIs are never downloaded.
+    assert!(md.contains("[Image: Inline badge]"));
+}
+
+#[test]
+fn asset_paths_mirror_image_urls_under_site() {
+    let cache = gg::cache::Cache::new(Some(std::path::PathBuf::from("/tmp/gg-test"))).unwrap();
+    let page = Url::parse("https://example.com/docs/guide").unwrap();
+    let img = Url::parse("https://cdn.example.net/a/b.png?v=2").unwrap();
+    let p = cache.asset_path(&page, &img).unwrap();
+    let s = p.to_string_lossy();
+    assert!(s.contains("sites/https/example.com/.gg/assets/cdn.example.net/a/b__q"));
+    assert!(s.ends_with(".png"));
+
+    let page_path = cache.page_path(&page).unwrap();
+    let link = gg::util::relative_link(page_path.parent().unwrap(), &p);
+    assert!(link.starts_with("../.gg/assets/cdn.example.net/a/"));
+}
+
+#[tokio::test]
+async fn download_mode_keeps_only_image_responses() {
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/docs/guide".into(),
+        (
+            200,
+            "text/html",
+            r#"<html><body><p>Guide</p><p><img src="/img/flow.png" alt="Flow"></p>
+<p><img src="/img/gone.png" alt="Gone"></p></body></html>"#
+                .into(),
+        ),
+    );
+    routes.insert("/img/flow.png".into(), (200, "image/png", "PNG".into()));
+    // A soft 404: the server answers 200 with an HTML page.
+    routes.insert(
+        "/img/gone.png".into(),
+        (
+            200,
+            "text/html",
+            "<html><body>Not found</body></html>".into(),
+        ),
+    );
+    let base = common::serve(routes, None).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-images-{}", std::process::id()));
+    let cache = gg::cache::Cache::new(Some(dir.clone())).unwrap();
+    let opts = with_images(ImageMode::Download);
+    let client = gg::http::build_client_all(&opts.http).unwrap();
+    let url = Url::parse(&format!("{base}docs/guide")).unwrap();
+    let path = crawl::ensure_page_cached(&cache, &client, &opts, url.clone(), true)
+        .await
+        .unwrap();
+    let md = std::fs::read_to_string(&path).unwrap();
+    assert!(md.contains("![Flow](../.gg/assets/"), "{md}");
+    assert!(md.contains("[Image: Gone]"), "{md}");
+    let gone = Url::parse(&format!("{base}img/gone.png")).unwrap();
+    assert!(!cache.asset_path(&url, &gone).unwrap().exists());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn cached_pages_follow_conversion_options() {
+    let mut routes = common::Routes::new();
+    let page =
+        r#"<html><body><p>Text</p><p><img src="/img/flow.png" alt="Flow"></p></body></html>"#;
+    routes.insert("/docs/".into(), (200, "text/html", page.into()));
+    routes.insert("/docs/guide".into(), (200, "text/html", page.into()));
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let base = common::serve(routes, Some(log.clone())).await;
+    let guide_requests = || {
+        log.lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.contains("/docs/guide "))
+            .count()
+    };
+
+    let dir = std::env::temp_dir().join(format!("gg-images-options-{}", std::process::id()));
+    let cache = gg::cache::Cache::new(Some(dir.clone())).unwrap();
+    let client = gg::http::build_client_all(&CrawlOptions::default().http).unwrap();
+    let url = Url::parse(&format!("{base}docs/guide")).unwrap();
+    let cached = |opts: CrawlOptions| {
+        let (cache, client, url) = (&cache, &client, url.clone());
+        async move {
+            let path = crawl::ensure_page_cached(cache, client, &opts, url, false)
+                .await
+                .unwrap();
+            std::fs::read_to_string(path).unwrap()
+        }
+    };
+
+    // A page cached without images is converted again when they are asked for, then reused.
+    assert!(!cached(with_images(ImageMode::Drop))
+        .await
+        .contains("[Image"));
+    assert!(cached(with_images(ImageMode::Alt))
+        .await
+        .contains("[Image: Flow]"));
+    assert!(cached(with_images(ImageMode::Alt))
+        .await
+        .contains("[Image: Flow]"));
+    assert_eq!(guide_requests(), 2);
+
+    // So is a crawl.
+    let root = Url::parse(&format!("{base}docs/")).unwrap();
+    for (images, expected) in [(ImageMode::Drop, false), (ImageMode::Alt, true)] {
+        let opts = CrawlOptions {
+            use_sitemap: false,
+            use_feeds: false,
+            ..with_images(images)
+        };
+        let manifest = crawl::ensure_subtree_cached(&cache, &opts, root.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!(manifest.conversion, opts.conversion_fingerprint());
+        let md = std::fs::read_to_string(dir.join(&manifest.pages[0].cache_path)).unwrap();
+        assert_eq!(md.contains("[Image: Flow]"), expected, "{images:?}: {md}");
+    }
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn conversion_fingerprint_covers_every_conversion_option() {
+    let default = CrawlOptions::default();
+    let mut sanitize_off = CrawlOptions::default();
+    sanitize_off.sanitize.enabled = false;
+    let mut rule_off = CrawlOptions::default();
+    rule_off.sanitize.disable("junk-lines").unwrap();
+    let mut user_rule = CrawlOptions::default();
+    user_rule
+        .sanitize
+        .user_rules
+        .push(UserRule::new("ads", "^Sponsored", None).unwrap());
+    let variants = [
+        sanitize_off,
+        rule_off,
+        user_rule,
+        with_images(ImageMode::Alt),
+        CrawlOptions {
+            tables: TableMode::Gfm,
+            ..Default::default()
+        },
+        CrawlOptions {
+            heading_anchors: AnchorStyle::Attr,
+            ..Default::default()
+        },
+        CrawlOptions {
+            split_min_bytes: Some(1024),
+            ..Default::default()
+        },
+        CrawlOptions {
+            guess_code_language: true,
+            ..Default::default()
+        },
+        CrawlOptions {
+            strip_prompts: true,
+            ..Default::default()
+        },
+    ];
+    for variant in &variants {
+        assert_ne!(
+            variant.conversion_fingerprint(),
+            default.conversion_fingerprint(),
+            "{variant:?}"
+        );
+    }
+    // Crawl settings that do not change the Markdown keep it.
+    let deeper = CrawlOptions {
+        max_depth: Some(3),
+        ..Default::default()
+    };
+    assert_eq!(
+        deeper.conversion_fingerprint(),
+        default.conversion_fingerprint()
+    );
+}
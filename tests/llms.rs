@@ -0,0 +1,126 @@
+mod common;
+
+use gg::cache::Cache;
+use gg::crawl::{self, CrawlOptions};
+use gg::llms::{self, MarkdownOptions};
+use url::Url;
+
+#[test]
+fn variants_and_page_urls_round_trip() {
+    let u = |s: &str| Url::parse(s).unwrap();
+    let names = |url: &str| -> Vec<String> {
+        llms::markdown_variants(&u(url))
+            .iter()
+            .map(|v| v.path().to_string())
+            .collect()
+    };
+    assert_eq!(names("https://x.dev/docs/intro"), ["/docs/intro.md"]);
+    assert_eq!(names("https://x.dev/docs/"), ["/docs/index.md"]);
+    assert_eq!(names("https://x.dev/a.html"), ["/a.html.md", "/a.md"]);
+
+    assert_eq!(
+        llms::page_url(&u("https://x.dev/docs/intro.md")),
+        u("https://x.dev/docs/intro")
+    );
+    assert_eq!(
+        llms::page_url(&u("https://x.dev/docs/index.md")),
+        u("https://x.dev/docs/")
+    );
+    assert_eq!(
+        llms::page_url(&u("https://x.dev/a.html.md")),
+        u("https://x.dev/a.html")
+    );
+}
+
+#[test]
+fn markdown_responses_and_links() {
+    assert!(llms::is_markdown_response(
+        Some("text/markdown; charset=utf-8"),
+        b"# Hi"
+    ));
+    assert!(llms::is_markdown_response(Some("text/plain"), b"# Hi"));
+    assert!(!llms::is_markdown_response(
+        Some("text/plain"),
+        b"<!doctype html><html>"
+    ));
+    assert!(!llms::is_markdown_response(Some("text/html"), b"# Hi"));
+
+    let base = Url::parse("https://x.dev/docs/llms.txt").unwrap();
+    let txt = "# X\n\n> Docs\n\n## Guides\n\n- [Intro](intro.md): start here\n- [API](https://x.dev/docs/api/index.md)\n- [Mail](mailto:a@x.dev)\n";
+    let links: Vec<String> = llms::markdown_links(txt, &base)
+        .iter()
+        .map(|u| u.to_string())
+        .collect();
+    assert_eq!(
+        links,
+        ["https://x.dev/docs/intro", "https://x.dev/docs/api/"]
+    );
+}
+
+#[tokio::test]
+async fn crawl_seeds_from_llms_txt_and_caches_server_markdown() {
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/docs/".into(),
+        (
+            200,
+            "text/html",
+            "<html><body><p>Root page</p></body></html>".into(),
+        ),
+    );
+    routes.insert(
+        "/docs/llms.txt".into(),
+        (
+            200,
+            "text/plain",
+            "# Docs\n\n- [Guide](guide.md)\n- [Old](old)\n".into(),
+        ),
+    );
+    routes.insert(
+        "/docs/guide.md".into(),
+        (
+            200,
+            "text/markdown",
+            "# Guide\n\nServer **Markdown**.\n".into(),
+        ),
+    );
+    routes.insert(
+        "/docs/old".into(),
+        (
+            200,
+            "text/html",
+            "<html><body><h1>Old</h1><p>Only HTML here.</p></body></html>".into(),
+        ),
+    );
+    let base = common::serve(routes, None).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-llms-{}", std::process::id()));
+    let cache = Cache::new(Some(dir.clone())).unwrap();
+    let opts = CrawlOptions {
+        markdown: MarkdownOptions::enabled(),
+        use_sitemap: false,
+        ..Default::default()
+    };
+    let root = Url::parse(&base).unwrap().join("docs/").unwrap();
+    let manifest = crawl::ensure_subtree_cached(&cache, &opts, root.clone(), true)
+        .await
+        .unwrap();
+
+    let guide = manifest
+        .pages
+        .iter()
+        .find(|p| p.url.ends_with("/docs/guide"))
+        .expect("guide seeded from llms.txt");
+    let md = std::fs::read_to_string(cache.root().join(&guide.cache_path)).unwrap();
+    assert_eq!(md, "# Guide\n\nServer **Markdown**.\n");
+
+    let old = manifest
+        .pages
+        .iter()
+        .find(|p| p.url.ends_with("/docs/old"))
+        .unwrap();
+    let md = std::fs::read_to_string(cache.root().join(&old.cache_path)).unwrap();
+    assert!(md.contains("Only HTML here."), "{md}");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
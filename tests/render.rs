@@ -0,0 +1,98 @@
+use gg::render::{self, RenderOptions, RenderWhen};
+use url::Url;
+
+const SHELL: &str = r#"<!doctype html><html><head><title>Docs</title><script src="/app.js"></script></head>
+<body><div id="root"></div><noscript>You need to enable JavaScript to run this app.</noscript>
+<script>window.__DATA__ = {"pages": ["a", "b", "c"]};</script></body></html>"#;
+
+const CONTENT: &str = r#"<html><head><script src="/analytics.js"></script></head><body><main>
+<h1>Getting started</h1><p>Install the CLI with your package manager, then run the init command in an empty
+directory. The command writes a configuration file and a sample page that you can edit to build your
+first site. Every option is documented in the reference section, along with examples.</p></main></body></html>"#;
+
+/// Stand-in for a headless browser: prints a page built from its URL argument and stdin size.
+fn stand_in(when: RenderWhen, command: &[&str]) -> RenderOptions {
+    RenderOptions {
+        command: Some(command.iter().map(|s| s.to_string()).collect()),
+        when,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn detects_empty_shells() {
+    assert!(render::is_empty_shell(SHELL));
+    assert!(!render::is_empty_shell(CONTENT));
+    assert!(!render::is_empty_shell("<p>short static page</p>"));
+}
+
+#[tokio::test]
+async fn auto_mode_renders_only_shells() {
+    let url = Url::parse("https://spa.example.com/docs/").unwrap();
+    let opts = stand_in(
+        RenderWhen::Auto,
+        &[
+            "sh",
+            "-c",
+            "printf '<h1>Rendered %s</h1><a href=\"/docs/next\">next</a>' \"$1\"",
+            "sh",
+        ],
+    );
+
+    let html = render::maybe_render(&opts, &url, SHELL.as_bytes())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        html,
+        "<h1>Rendered https://spa.example.com/docs/</h1><a href=\"/docs/next\">next</a>"
+    );
+    assert!(render::maybe_render(&opts, &url, CONTENT.as_bytes())
+        .await
+        .unwrap()
+        .is_none());
+
+    let always = RenderOptions {
+        when: RenderWhen::Always,
+        ..opts
+    };
+    assert!(render::maybe_render(&always, &url, CONTENT.as_bytes())
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn url_placeholder_and_failures() {
+    let url = Url::parse("https://spa.example.com/").unwrap();
+    let opts = stand_in(
+        RenderWhen::Always,
+        &["sh", "-c", "echo \"<p>$0 $GG_URL</p>\"", "{url}"],
+    );
+    let html = render::maybe_render(&opts, &url, b"")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        html.trim(),
+        "<p>https://spa.example.com/ https://spa.example.com/</p>"
+    );
+
+    let empty = stand_in(RenderWhen::Always, &["true"]);
+    assert!(render::maybe_render(&empty, &url, b"").await.is_err());
+    assert!(
+        render::maybe_render(&RenderOptions::default(), &url, SHELL.as_bytes())
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn command_lines_split_on_whitespace_and_quotes() {
+    assert_eq!(
+        gg::util::split_command_line(r#"chrome-dump --wait "2 s" '{url}'"#),
+        ["chrome-dump", "--wait", "2 s", "{url}"]
+    );
+    assert_eq!(gg::util::split_command_line("a ''"), ["a", ""]);
+}
@@ -0,0 +1,183 @@
+use gg::sanitize::{sanitize_markdown, SanitizeOptions, UserRule, BUILTIN_RULES};
+
+fn without(rule: &str) -> SanitizeOptions {
+    let mut opts = SanitizeOptions::default();
+    opts.disable(rule).unwrap();
+    opts
+}
+
+/// Assert `needle` is removed by default and kept once `rule` is disabled.
+fn assert_rule(rule: &str, input: &str, needle: &str) {
+    assert_rule_over(SanitizeOptions::default(), rule, input, needle);
+}
+
+/// Like `assert_rule`, starting from `base` (used when another rule overlaps).
+fn assert_rule_over(base: SanitizeOptions, rule: &str, input: &str, needle: &str) {
+    let on = sanitize_markdown(input, &base);
+    assert!(
+        !on.contains(needle),
+        "rule {rule} should remove {needle:?}:\n{on}"
+    );
+    let mut off = base;
+    off.disable(rule).unwrap();
+    let off = sanitize_markdown(input, &off);
+    assert!(
+        off.contains(needle),
+        "disabled rule {rule} should keep {needle:?}:\n{off}"
+    );
+}
+
+#[test]
+fn rule_frontmatter() {
+    assert_rule(
+        "frontmatter",
+        "---\nmeta-foo: bar\n---\n\n# Title\n",
+        "meta-foo",
+    );
+}
+
+#[test]
+fn rule_svg() {
+    assert_rule(
+        "svg",
+        "# Title\n<svg width=\"1\">\n<path d=\"M0\"/>\n</svg>\nText\n",
+        "<path",
+    );
+}
+
+#[test]
+fn rule_footer() {
+    assert_rule(
+        "footer",
+        "# Intro\nText\n## Footer\nFooter docs body\n",
+        "Footer docs body",
+    );
+}
+
+#[test]
+fn rule_horizontal_rules() {
+    // `***` is also junk punctuation, so isolate this rule from `junk-lines`.
+    assert_rule_over(
+        without("junk-lines"),
+        "horizontal-rules",
+        "# Title\n\nA\n\n***\n\nB\n",
+        "***",
+    );
+}
+
+#[test]
+fn rule_images() {
+    assert_rule(
+        "images",
+        "# Title\n\nSee ![diagram](/d.png) here\n",
+        "![diagram]",
+    );
+}
+
+#[test]
+fn rule_nav_links() {
+    assert_rule_over(
+        without("link-blocks"),
+        "nav-links",
+        "[Home](/)\n\n# Title\nText\n",
+        "[Home](/)",
+    );
+}
+
+#[test]
+fn rule_link_blocks() {
+    assert_rule(
+        "link-blocks",
+        "# Title\nText\n\n[Docs](https://example.com/docs)\n",
+        "[Docs]",
+    );
+}
+
+#[test]
+fn rule_copyright() {
+    assert_rule(
+        "copyright",
+        "# Title\n© Example Corp 2024\n",
+        "Example Corp",
+    );
+}
+
+#[test]
+fn rule_junk_lines() {
+    assert_rule("junk-lines", "# Title\n[ [ [\nText\n", "[ [ [");
+}
+
+#[test]
+fn rule_copy_buttons() {
+    assert_rule("copy-buttons", "# Title\nCopy page\nText\n", "Copy page");
+}
+
+#[test]
+fn rule_svg_image_marker() {
+    assert_rule(
+        "svg-image-marker",
+        "## hydrate [SVG Image](#hydrate)\n",
+        "[SVG Image]",
+    );
+}
+
+#[test]
+fn every_builtin_rule_is_covered_and_disableable() {
+    assert_eq!(BUILTIN_RULES.len(), 11);
+    for rule in BUILTIN_RULES {
+        assert!(!without(rule).is_enabled(rule));
+    }
+    assert!(SanitizeOptions::default().disable("no-such-rule").is_err());
+}
+
+#[test]
+fn no_sanitize_keeps_input_verbatim() {
+    let input = "---\ntitle: x\n---\nCopy page\n## Footer\nkeep\n";
+    let opts = SanitizeOptions {
+        enabled: false,
+        ..Default::default()
+    };
+    assert_eq!(sanitize_markdown(input, &opts), input);
+}
+
+#[test]
+fn user_rules_drop_and_replace_outside_code() {
+    let mut opts = SanitizeOptions::default();
+    opts.user_rules
+        .push(UserRule::new("cookies", "(?i)^we use cookies", None).unwrap());
+    opts.user_rules
+        .push(UserRule::new("edit", r"\s*\[Edit\]\([^)]*\)", Some(String::new())).unwrap());
+
+    let input =
+        "# Title\nWe use cookies to track you.\nBody [Edit](/edit)\n```\nwe use cookies\n```\n";
+    let out = sanitize_markdown(input, &opts);
+    assert!(!out.contains("track you"));
+    assert!(out.contains("Body\n"));
+    assert!(!out.contains("[Edit]"));
+    assert!(out.contains("we use cookies\n```"));
+}
+
+#[test]
+fn loads_rules_file() {
+    let dir = std::env::temp_dir().join(format!("gg-sanitize-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("rules.toml");
+    std::fs::write(
+        &path,
+        r#"
+# Real docs page titled "Footer".
+disable = ["footer"]
+
+[[rule]]
+name = "beta"
+pattern = '(?i)^beta:'
+"#,
+    )
+    .unwrap();
+
+    let mut opts = SanitizeOptions::default();
+    opts.load_rules_file(&path).unwrap();
+    let out = sanitize_markdown("# Footer\nThe footer element.\nBeta: hidden\n", &opts);
+    assert!(out.contains("The footer element."));
+    assert!(!out.contains("hidden"));
+}
@@ -0,0 +1,132 @@
+use gg::crawl::{self, CrawlOptions};
+use gg::sections::{self, AnchorStyle, HeadingRef};
+
+const SPEC: &str = r#"<p>Living standard.</p>
+<h1 id="intro">Introduction</h1>
+<p>Why this spec exists.</p>
+<h2 id="conformance">Conformance &amp; terms</h2>
+<pre><code># not a heading
+</code></pre>
+<h3 id="detail">Detail</h3>
+<p>Nested detail stays with its H2.</p>
+<h2>Appendix</h2>
+<p>End.</p>"#;
+
+#[test]
+fn splits_at_h1_h2_with_source_anchors() {
+    let page = crawl::convert_page_for_test(SPEC, None, &CrawlOptions::default(), None).unwrap();
+    assert_eq!(
+        page.headings[0],
+        HeadingRef {
+            level: 1,
+            text: "Introduction".to_string(),
+            id: Some("intro".to_string())
+        }
+    );
+
+    let (preamble, parts) = sections::split_markdown(&page.markdown, &page.headings).unwrap();
+    assert!(preamble.contains("Living standard."));
+
+    let names: Vec<&str> = parts.iter().map(|s| s.file_name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "01-introduction.md",
+            "02-conformance-terms.md",
+            "03-appendix.md"
+        ]
+    );
+    let anchors: Vec<Option<&str>> = parts.iter().map(|s| s.anchor.as_deref()).collect();
+    assert_eq!(anchors, [Some("intro"), Some("conformance"), None]);
+
+    assert!(parts[1].markdown.contains("# not a heading"));
+    assert!(parts[1]
+        .markdown
+        .contains("Nested detail stays with its H2."));
+    assert!(parts[2].markdown.starts_with("## Appendix"));
+
+    let index = sections::index_markdown(&preamble, "spec", &parts);
+    assert!(index.contains("- [Introduction](spec/01-introduction.md)"));
+}
+
+#[test]
+fn heading_ids_are_carried_into_markdown() {
+    let convert = |style| {
+        let opts = CrawlOptions {
+            heading_anchors: style,
+            ..Default::default()
+        };
+        crawl::convert_page_for_test(SPEC, None, &opts, None)
+            .unwrap()
+            .markdown
+    };
+
+    let md = convert(AnchorStyle::Attr);
+    assert!(md.contains("# Introduction {#intro}\n"));
+    assert!(md.contains("### Detail {#detail}\n"));
+    assert!(md.contains("## Appendix\n"));
+    assert!(
+        md.contains("# not a heading\n"),
+        "code blocks are untouched:\n{md}"
+    );
+
+    let md = convert(AnchorStyle::Html);
+    assert!(md.contains("## Conformance & terms <a id=\"conformance\"></a>\n"));
+
+    let md = convert(AnchorStyle::None);
+    assert!(md.contains("# Introduction\n"));
+    assert_eq!(AnchorStyle::default(), AnchorStyle::None);
+
+    // Ids come from the element itself, so repeated titles keep their own ids, and ids
+    // that would break the syntax are escaped.
+    let html =
+        r#"<h2 id="a">Usage</h2><p>x</p><h2 id="b">Usage</h2><h2 id='say "hi" {x}'>Odd</h2>"#;
+    let opts = CrawlOptions {
+        heading_anchors: AnchorStyle::Attr,
+        ..Default::default()
+    };
+    let md = crawl::convert_page_for_test(html, None, &opts, None)
+        .unwrap()
+        .markdown;
+    assert!(md.contains("## Usage {#a}\n\nx\n\n## Usage {#b}\n"), "{md}");
+    assert!(md.contains("## Odd {#say%20%22hi%22%20%7Bx%7D}\n"), "{md}");
+    let opts = CrawlOptions {
+        heading_anchors: AnchorStyle::Html,
+        ..opts
+    };
+    let md = crawl::convert_page_for_test(html, None, &opts, None)
+        .unwrap()
+        .markdown;
+    assert!(
+        md.contains("## Odd <a id=\"say &quot;hi&quot; {x}\"></a>\n"),
+        "{md}"
+    );
+}
+
+#[test]
+fn single_heading_pages_are_not_split() {
+    assert!(sections::split_markdown("# Only\n\ntext\n", &[]).is_none());
+}
+
+#[test]
+fn section_files_ignore_child_pages() {
+    let dir = std::env::temp_dir().join(format!("gg-sections-{}", std::process::id()));
+    let page = dir.join("spec.md");
+    std::fs::create_dir_all(dir.join("spec")).unwrap();
+
+    let (_, parts) = sections::split_markdown("# Intro\na\n# Usage\nb\n", &[]).unwrap();
+    for name in ["01-intro.md", "02-usage.md", "2024-notes.md"] {
+        std::fs::write(dir.join("spec").join(name), "x").unwrap();
+    }
+    sections::write_section_list(&page, &parts).unwrap();
+
+    let files: Vec<String> = sections::section_files(&page)
+        .iter()
+        .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(files, ["01-intro.md", "02-usage.md"]);
+
+    sections::clear_section_files(&page);
+    assert!(sections::section_files(&page).is_empty());
+    assert!(dir.join("spec").join("2024-notes.md").is_file());
+}
@@ -0,0 +1,410 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use gg::cache::Cache;
+use gg::crawl::{self, CrawlOptions};
+use gg::sitemap;
+use url::Url;
+
+#[test]
+fn parses_sitemap_metadata() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url>
+    <loc>https://x.dev/docs/a</loc>
+    <lastmod>2024-05-01T10:00:00+00:00</lastmod>
+    <changefreq>Weekly</changefreq>
+    <priority>0.8</priority>
+  </url>
+  <url><priority>1.0</priority><loc>https://x.dev/docs/b</loc></url>
+  <url><loc>https://x.dev/docs/c</loc><lastmod>not a date</lastmod></url>
+</urlset>"#;
+    let parsed = sitemap::parse_sitemap_xml(xml.as_bytes()).unwrap();
+    assert!(parsed.child_sitemaps.is_empty());
+    assert_eq!(parsed.entries.len(), 3);
+
+    let a = &parsed.entries[0];
+    assert_eq!(a.url.as_str(), "https://x.dev/docs/a");
+    assert_eq!(a.lastmod, Some(1_714_557_600));
+    assert_eq!(a.changefreq.as_deref(), Some("weekly"));
+    assert_eq!(a.priority, Some(0.8));
+
+    let b = &parsed.entries[1];
+    assert_eq!(b.url.as_str(), "https://x.dev/docs/b");
+    assert_eq!(b.priority, Some(1.0));
+
+    let c = &parsed.entries[2];
+    assert_eq!((c.lastmod, c.priority), (None, None));
+    assert_eq!(c.effective_priority(), 0.5);
+}
+
+#[tokio::test]
+async fn crawl_orders_by_priority_and_refreshes_by_lastmod() {
+    let page = |body: &str| {
+        (
+            200,
+            "text/html",
+            format!("<html><body><p>{body}</p></body></html>"),
+        )
+    };
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/docs/".into(),
+        page(r#"Docs, see <a href="/docs/linked">linked</a>"#),
+    );
+    routes.insert(
+        "/sitemap.xml".into(),
+        (
+            200,
+            "application/xml",
+            r#"<urlset>
+<url><loc>{base}docs/low</loc><lastmod>2024-05-01</lastmod><priority>0.1</priority></url>
+<url><loc>{base}docs/high</loc><lastmod>2100-01-01</lastmod><priority>0.9</priority><changefreq>daily</changefreq></url>
+<url><loc>{base}docs/mid</loc></url>
+<url><loc>{base}blog/outside</loc><priority>1.0</priority></url>
+</urlset>"#
+                .into(),
+        ),
+    );
+    routes.insert("/docs/low".into(), page("Low"));
+    routes.insert("/docs/high".into(), page("High"));
+    routes.insert("/docs/mid".into(), page("Mid"));
+    routes.insert("/docs/linked".into(), page("Linked"));
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let base = common::serve(routes, Some(log.clone())).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-sitemap-{}", std::process::id()));
+    let cache = Cache::new(Some(dir.clone())).unwrap();
+    let opts = CrawlOptions {
+        parallelism: 1,
+        use_feeds: false,
+        ..Default::default()
+    };
+    let root = Url::parse(&base).unwrap().join("docs/").unwrap();
+    let page_requests = |log: &Arc<Mutex<Vec<String>>>| -> Vec<String> {
+        log.lock()
+            .unwrap()
+            .iter()
+            .filter_map(|r| r.split_whitespace().nth(1))
+            .filter(|p| p.starts_with("/docs/") && *p != "/docs/" && !p.contains("sitemap"))
+            .map(str::to_string)
+            .collect()
+    };
+
+    let first = crawl::ensure_subtree_cached(&cache, &opts, root.clone(), true)
+        .await
+        .unwrap();
+    // Discovered links take the default priority, so they go before low-priority seeds.
+    assert_eq!(
+        page_requests(&log),
+        ["/docs/high", "/docs/mid", "/docs/linked", "/docs/low"]
+    );
+
+    let high = first
+        .pages
+        .iter()
+        .find(|p| p.url.ends_with("/docs/high"))
+        .unwrap();
+    assert_eq!(high.priority, Some(0.9));
+    assert_eq!(high.changefreq.as_deref(), Some("daily"));
+    assert_eq!(high.lastmod, Some(4_102_444_800));
+
+    // Only pages without a lastmod, or modified after they were fetched, are refetched.
+    log.lock().unwrap().clear();
+    let second = crawl::ensure_subtree_cached(&cache, &opts, root.clone(), true)
+        .await
+        .unwrap();
+    assert_eq!(
+        page_requests(&log),
+        ["/docs/high", "/docs/mid", "/docs/linked"]
+    );
+    let low = second
+        .pages
+        .iter()
+        .find(|p| p.url.ends_with("/docs/low"))
+        .unwrap();
+    assert_eq!(low.priority, Some(0.1));
+
+    // A page budget keeps the most important pages; a crawl without one does not reuse it.
+    let budget = CrawlOptions {
+        max_pages: Some(2),
+        ..opts.clone()
+    };
+    let limited = crawl::ensure_subtree_cached(&cache, &budget, root.clone(), true)
+        .await
+        .unwrap();
+    let urls: Vec<&str> = limited.pages.iter().map(|p| p.url.as_str()).collect();
+    assert_eq!(urls, [root.as_str(), &format!("{base}docs/high")]);
+    let full = crawl::ensure_subtree_cached(&cache, &opts, root.clone(), false)
+        .await
+        .unwrap();
+    assert_eq!(full.pages.len(), 5);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn parses_robots_and_plain_text_sitemaps() {
+    let robots = "User-agent: *\nDisallow: /private/\nSitemap: https://x.dev/sitemap-docs.xml\nsitemap:https://x.dev/sitemap.txt\n";
+    let urls: Vec<String> = sitemap::robots_sitemaps(robots)
+        .iter()
+        .map(|u| u.to_string())
+        .collect();
+    assert_eq!(
+        urls,
+        [
+            "https://x.dev/sitemap-docs.xml",
+            "https://x.dev/sitemap.txt"
+        ]
+    );
+
+    let parsed =
+        sitemap::parse_sitemap(b"https://x.dev/docs/a\n\n  https://x.dev/docs/b  \nnot a url\n")
+            .unwrap();
+    let urls: Vec<&str> = parsed.entries.iter().map(|e| e.url.as_str()).collect();
+    assert_eq!(urls, ["https://x.dev/docs/a", "https://x.dev/docs/b"]);
+}
+
+#[tokio::test]
+async fn discovers_every_sitemap_under_the_prefix() {
+    let xml = |body: &str| (200, "application/xml", body.to_string());
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/robots.txt".into(),
+        (
+            200,
+            "text/plain",
+            "User-agent: *\nSitemap: {base}sitemap-api.xml\n".into(),
+        ),
+    );
+    routes.insert(
+        "/sitemap_index.xml".into(),
+        xml("<sitemapindex><sitemap><loc>{base}sitemap-docs.xml</loc></sitemap><sitemap><loc>{base}blog/sitemap.xml</loc></sitemap><sitemap><loc>{base}sitemaps/sitemap-guides.xml</loc></sitemap></sitemapindex>"),
+    );
+    routes.insert(
+        "/sitemaps/sitemap-guides.xml".into(),
+        xml("<urlset><url><loc>{base}docs/tutorial</loc></url><url><loc>{base}blog/post</loc></url></urlset>"),
+    );
+    routes.insert(
+        "/sitemap-docs.xml".into(),
+        xml("<urlset><url><loc>{base}docs/guide</loc></url><url><loc>{base}about</loc></url></urlset>"),
+    );
+    routes.insert(
+        "/sitemap-api.xml".into(),
+        xml("<urlset><url><loc>{base}docs/api/</loc></url></urlset>"),
+    );
+    routes.insert(
+        "/blog/sitemap.xml".into(),
+        xml("<urlset><url><loc>{base}docs/from-blog</loc></url></urlset>"),
+    );
+    routes.insert(
+        "/docs/sitemap.txt".into(),
+        (
+            200,
+            "text/plain",
+            "{base}docs/plain\n{base}docs/guide\n".into(),
+        ),
+    );
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let base = common::serve(routes, Some(log.clone())).await;
+
+    let client = reqwest::Client::new();
+    let root = Url::parse(&base).unwrap().join("docs/").unwrap();
+    let entries = sitemap::discover_sitemap_entries(&client, &root, 1 << 20)
+        .await
+        .unwrap();
+    let mut paths: Vec<&str> = entries.iter().map(|e| e.url.path()).collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        ["/docs/api/", "/docs/guide", "/docs/plain", "/docs/tutorial"]
+    );
+
+    let requests = log.lock().unwrap().clone();
+    assert!(
+        !requests.iter().any(|r| r.contains("/blog/")),
+        "{requests:?}"
+    );
+}
+
+#[tokio::test]
+async fn discover_mode_limits_page_sources() {
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/docs/".into(),
+        (
+            200,
+            "text/html",
+            r#"<html><body><p>Root, see <a href="/docs/linked">linked</a>.</p></body></html>"#
+                .into(),
+        ),
+    );
+    routes.insert(
+        "/sitemap.xml".into(),
+        (
+            200,
+            "application/xml",
+            "<urlset><url><loc>{base}docs/listed</loc></url></urlset>".into(),
+        ),
+    );
+    for path in ["/docs/linked", "/docs/listed"] {
+        routes.insert(
+            path.into(),
+            (
+                200,
+                "text/html",
+                "<html><body><p>Page</p></body></html>".into(),
+            ),
+        );
+    }
+    let base = common::serve(routes, None).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-discover-{}", std::process::id()));
+    let cache = Cache::new(Some(dir.clone())).unwrap();
+    let root = Url::parse(&base).unwrap().join("docs/").unwrap();
+    for (mode, expected) in [
+        ("both", vec!["/docs/", "/docs/linked", "/docs/listed"]),
+        ("sitemap", vec!["/docs/", "/docs/listed"]),
+        ("links", vec!["/docs/", "/docs/linked"]),
+        ("none", vec!["/docs/"]),
+    ] {
+        let opts = CrawlOptions {
+            discover: mode.parse().unwrap(),
+            use_feeds: false,
+            ..Default::default()
+        };
+        // No refresh: each mode must recrawl rather than reuse the previous mode's manifest.
+        let manifest = crawl::ensure_subtree_cached(&cache, &opts, root.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!(manifest.discover, opts.discover);
+        let mut paths: Vec<String> = manifest
+            .pages
+            .iter()
+            .map(|p| Url::parse(&p.url).unwrap().path().to_string())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, expected, "--discover {mode}");
+    }
+    assert!("all".parse::<gg::crawl::DiscoverMode>().is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn sitemap_mode_reuses_unchanged_pages() {
+    let page = || {
+        (
+            200,
+            "text/html",
+            "<html><body><p>Page</p></body></html>".to_string(),
+        )
+    };
+    let mut routes = common::Routes::new();
+    routes.insert("/docs/".into(), page());
+    routes.insert("/docs/listed".into(), page());
+    routes.insert(
+        "/sitemap.xml".into(),
+        (
+            200,
+            "application/xml",
+            "<urlset><url><loc>{base}docs/listed</loc><lastmod>2024-05-01</lastmod></url></urlset>"
+                .into(),
+        ),
+    );
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let base = common::serve(routes, Some(log.clone())).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-sitemap-reuse-{}", std::process::id()));
+    let cache = Cache::new(Some(dir.clone())).unwrap();
+    let opts = CrawlOptions {
+        discover: gg::crawl::DiscoverMode::Sitemap,
+        use_feeds: false,
+        ..Default::default()
+    };
+    let root = Url::parse(&base).unwrap().join("docs/").unwrap();
+    let listed_requests = |log: &Arc<Mutex<Vec<String>>>| {
+        log.lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.split_whitespace().nth(1) == Some("/docs/listed"))
+            .count()
+    };
+
+    crawl::ensure_subtree_cached(&cache, &opts, root.clone(), true)
+        .await
+        .unwrap();
+    assert_eq!(listed_requests(&log), 1);
+
+    // Links are not followed, so none were recorded; the unchanged page is reused anyway.
+    let second = crawl::ensure_subtree_cached(&cache, &opts, root.clone(), true)
+        .await
+        .unwrap();
+    assert_eq!(listed_requests(&log), 1);
+    assert!(second.pages.iter().any(|p| p.url.ends_with("/docs/listed")));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn allowed_hosts_extend_the_crawl() {
+    let mut routes = common::Routes::new();
+    let body = r#"<html><body><p>Root: <a href="http://localhost:{port}/docs/ref">ref</a> <a href="http://localhost:{port}/api/ref">api</a> <a href="/elsewhere">x</a></p></body></html>"#;
+    for path in ["/docs/ref", "/api/ref"] {
+        routes.insert(
+            path.into(),
+            (
+                200,
+                "text/html",
+                "<html><body><p>Ref</p></body></html>".into(),
+            ),
+        );
+    }
+    routes.insert(
+        "/elsewhere".into(),
+        (
+            200,
+            "text/html",
+            "<html><body><p>Out</p></body></html>".into(),
+        ),
+    );
+    // The port is only known once serving, so the root page is served through a second server.
+    let base = common::serve(routes.clone(), None).await;
+    let port = Url::parse(&base).unwrap().port().unwrap();
+    routes.insert(
+        "/docs/".into(),
+        (200, "text/html", body.replace("{port}", &port.to_string())),
+    );
+    let docs_base = common::serve(routes, None).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-hosts-{}", std::process::id()));
+    let cache = Cache::new(Some(dir.clone())).unwrap();
+    let root = Url::parse(&docs_base).unwrap().join("docs/").unwrap();
+    for (allow, expected) in [
+        (vec![], vec!["/docs/"]),
+        // Extra hosts are held to the same path prefix as the root.
+        (vec!["localhost".to_string()], vec!["/docs/", "/docs/ref"]),
+    ] {
+        let opts = CrawlOptions {
+            allow_hosts: allow,
+            use_sitemap: false,
+            use_feeds: false,
+            ..Default::default()
+        };
+        // No refresh: allowing another host must recrawl rather than reuse the manifest.
+        let manifest = crawl::ensure_subtree_cached(&cache, &opts, root.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!(manifest.allow_hosts, opts.allow_hosts);
+        let mut paths: Vec<String> = manifest
+            .pages
+            .iter()
+            .map(|p| Url::parse(&p.url).unwrap().path().to_string())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, expected);
+    }
+
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use gg::crawl::{self, CrawlOptions};
+use gg::tables::TableMode;
+
+const PARAMS: &str = r#"<h2>Parameters</h2>
+<table>
+  <caption>Request parameters</caption>
+  <thead>
+    <tr><th rowspan="2">Name</th><th colspan="2">Details</th></tr>
+    <tr><th>Type</th><th>Description</th></tr>
+  </thead>
+  <tbody>
+    <tr><td><code>limit</code></td><td>int</td><td>Max items | per page<ul><li>default 10</li><li>max 100</li></ul></td></tr>
+    <tr><td rowspan="2"><code>sort</code></td><td>string</td><td>Field name</td></tr>
+    <tr><td>enum</td><td>One of <code>asc</code>, <code>desc</code></td></tr>
+  </tbody>
+</table>
+<p>After</p>"#;
+
+fn convert(html: &str, tables: TableMode) -> String {
+    let opts = CrawlOptions {
+        tables,
+        ..Default::default()
+    };
+    crawl::convert_page_for_test(html, None, &opts, None)
+        .unwrap()
+        .markdown
+}
+
+#[test]
+fn gfm_mode_expands_spans_into_single_line_cells() {
+    let md = convert(PARAMS, TableMode::Gfm);
+    assert!(md.contains("Table: Request parameters"));
+    assert!(md.contains("| Name | Details / Type | Details / Description |\n| --- | --- | --- |"));
+    assert!(md.contains("| `limit` | int | Max items \\| per page<br>- default 10<br>- max 100 |"));
+    // Rowspan repeats the cell so each row stands alone.
+    assert!(md.contains("| `sort` | enum | One of `asc`, `desc` |"));
+    assert!(md.contains("After"));
+}
+
+#[test]
+fn rows_mode_emits_one_line_per_row() {
+    let md = convert(PARAMS, TableMode::Rows);
+    assert!(
+        md.contains("Name: `sort`; Details / Type: string; Details / Description: Field name\n")
+    );
+    assert!(md.contains(
+        "Name: `sort`; Details / Type: enum; Details / Description: One of `asc`, `desc`\n"
+    ));
+    assert!(md.contains("Details / Description: Max items | per page / - default 10 / - max 100"));
+}
+
+#[test]
+fn gfm_mode_promotes_first_row_without_header_and_flattens_code() {
+    let html = r#"<table>
+<tr><td>key</td><td>value</td></tr>
+<tr><td>cmd</td><td><pre><code>gg rg foo
+gg cat bar</code></pre></td></tr>
+</table>"#;
+    let md = convert(html, TableMode::Gfm);
+    assert!(md.contains("| key | value |\n| --- | --- |\n| cmd | gg rg foo<br>gg cat bar |"));
+}
+
+#[test]
+fn native_mode_leaves_tables_to_converter() {
+    let md = convert(PARAMS, TableMode::Native);
+    assert!(!md.contains("GGTABLEPLACEHOLDER"));
+    assert!(!md.contains("Table: Request parameters"));
+}
+
+#[tokio::test]
+async fn gfm_tables_keep_their_separator_row_through_sanitize() {
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/params".into(),
+        (
+            200,
+            "text/html",
+            format!("<html><body>{PARAMS}</body></html>"),
+        ),
+    );
+    let base = common::serve(routes, None).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-tables-{}", std::process::id()));
+    let cache = gg::cache::Cache::new(Some(dir.clone())).unwrap();
+    let opts = CrawlOptions {
+        tables: TableMode::Gfm,
+        ..Default::default()
+    };
+    let client = gg::http::build_client_all(&opts.http).unwrap();
+    let url = url::Url::parse(&format!("{base}params")).unwrap();
+    let path = crawl::ensure_page_cached(&cache, &client, &opts, url, true)
+        .await
+        .unwrap();
+    let md = std::fs::read_to_string(path).unwrap();
+    assert!(
+        md.contains("| Name | Details / Type | Details / Description |\n| --- | --- | --- |\n"),
+        "{md}"
+    );
+
+    std::fs::remove_dir_all(&dir).ok();
+}
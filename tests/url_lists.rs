@@ -0,0 +1,103 @@
+mod common;
+
+use std::process::Stdio;
+
+use gg::util::parse_url_list;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+#[test]
+fn url_lists_skip_blanks_and_comments() {
+    let text = "# docs pages\nhttps://x.dev/a\n\n  https://x.dev/b#install   # keep the fragment\nhttps://x.dev/c/**/*\t# glob\n";
+    assert_eq!(
+        parse_url_list(text),
+        [
+            "https://x.dev/a",
+            "https://x.dev/b#install",
+            "https://x.dev/c/**/*"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reads_sources_from_stdin_and_files() {
+    let mut routes = common::Routes::new();
+    for name in ["one", "two", "three"] {
+        routes.insert(
+            format!("/{name}"),
+            (
+                200,
+                "text/html",
+                format!("<html><body><p>Page {name}</p></body></html>"),
+            ),
+        );
+    }
+    let base = common::serve(routes, None).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-url-lists-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let list = dir.join("urls.txt");
+    std::fs::write(&list, format!("# from a file\n{base}three\n")).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gg"))
+        .arg("--cache-dir")
+        .arg(dir.join("cache"))
+        .args(["--print-paths", "cat"])
+        .arg(format!("@{}", list.display()))
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin
+        .write_all(format!("{base}one\n\n# comment\n{base}two\n").as_bytes())
+        .await
+        .unwrap();
+    drop(stdin);
+    let out = child.wait_with_output().await.unwrap();
+    assert!(out.status.success());
+
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let names: Vec<&str> = stdout
+        .lines()
+        .map(|l| l.rsplit('/').next().unwrap())
+        .collect();
+    assert_eq!(names, ["three.md", "one.md", "two.md"]);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn dash_before_the_first_url_is_a_host_argument() {
+    let mut routes = common::Routes::new();
+    routes.insert(
+        "/page".into(),
+        (
+            200,
+            "text/html",
+            "<html><body><p>From the page</p></body></html>".into(),
+        ),
+    );
+    let base = common::serve(routes, None).await;
+
+    let dir = std::env::temp_dir().join(format!("gg-dash-host-{}", std::process::id()));
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gg"))
+        .arg("--cache-dir")
+        .arg(&dir)
+        .args(["cat", "-", &format!("{base}page")])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"From stdin\n").await.unwrap();
+    drop(stdin);
+    let out = child.wait_with_output().await.unwrap();
+    assert!(out.status.success());
+
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(stdout.starts_with("From stdin\n"), "{stdout}");
+    assert!(stdout.contains("From the page"), "{stdout}");
+
+    std::fs::remove_dir_all(&dir).ok();
+}
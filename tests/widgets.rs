@@ -0,0 +1,52 @@
+use gg::crawl::{self, CrawlOptions};
+
+fn convert(html: &str) -> String {
+    crawl::convert_page_for_test(html, None, &CrawlOptions::default(), None)
+        .unwrap()
+        .markdown
+}
+
+#[test]
+fn math_is_restored_as_tex() {
+    let html = r#"<p>Energy <span class="katex"><span class="katex-mathml"><math><semantics><mi>E</mi><annotation encoding="application/x-tex">E = mc^2 &lt; \infty</annotation></semantics></math></span><span class="katex-html" aria-hidden="true">E=mc2</span></span> holds.</p>
+<span class="katex-display"><span class="katex"><annotation encoding="application/x-tex">\sum_{i=1}^n x_i</annotation></span></span>
+<p>Old <span class="MathJax_Preview">a</span><span class="MathJax">rendered</span><script type="math/tex">a_1 < b</script> style.</p>
+<script type="math/tex; mode=display">\int_0^1 f</script>
+<p>MathML <math alttext="x^2"><msup><mi>x</mi><mn>2</mn></msup></math>.</p>"#;
+    let md = convert(html);
+    assert!(md.contains("Energy $E = mc^2 < \\infty$ holds."), "{md}");
+    assert!(md.contains("\n$$\\sum_{i=1}^n x_i$$\n"));
+    assert!(md.contains("Old $a_1 < b$ style."));
+    assert!(md.contains("$$\\int_0^1 f$$"));
+    assert!(md.contains("MathML $x^2$."));
+    assert!(!md.contains("rendered") && !md.contains("E=mc2"));
+}
+
+#[test]
+fn admonitions_become_labeled_blockquotes() {
+    let html = r#"<div class="admonition warning"><p class="admonition-title">Careful</p><p>Body <code>x</code> one.</p><ul><li>a</li></ul></div>
+<div class="markdown-alert markdown-alert-tip"><p class="markdown-alert-title"><svg></svg>Tip</p><p>Use it.</p></div>
+<details class="note"><summary>More</summary><p>Hidden text.</p></details>"#;
+    let md = convert(html);
+    assert!(
+        md.contains("> **Warning:** Careful\n>\n> Body `x` one.\n>\n> - a\n"),
+        "{md}"
+    );
+    assert!(md.contains("> **Tip:**\n>\n> Use it.\n"));
+    assert!(md.contains("> **Note:** More\n>\n> Hidden text.\n"));
+}
+
+#[test]
+fn tab_groups_become_consecutive_sections() {
+    let html = r#"<div class="tabs-container"><ul role="tablist" class="tabs"><li role="tab">Python</li><li role="tab">Rust</li></ul>
+<div><div role="tabpanel"><pre><code class="language-python">print(1)</code></pre></div><div role="tabpanel" hidden><pre><code class="language-rust">fn main() {}</code></pre></div></div></div>
+<div class="tabbed-set"><input checked id="t1" type="radio"><input id="t2" type="radio"><div class="tabbed-labels"><label for="t1">pip</label><label for="t2">uv</label></div><div class="tabbed-content"><div class="tabbed-block"><p>pip install gg</p></div><div class="tabbed-block"><p>uv add gg</p></div></div></div>"#;
+    let md = convert(html);
+    assert!(
+        md.contains(
+            "**Python**\n\n```python\nprint(1)\n```\n\n**Rust**\n\n```rust\nfn main() {}\n```"
+        ),
+        "{md}"
+    );
+    assert!(md.contains("**pip**\n\npip install gg\n\n**uv**\n\nuv add gg"));
+}